@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::error::RevError;
+
+/// Stops a build or export that is running, e.g. when the user presses Cancel or Ctrl-C
+///
+/// Clones share the same state, so a clone can be kept to cancel an operation running on
+/// another task. Build tools and package managers that are running are killed along with every
+/// process they started, and a partial export is rolled back. A token stays cancelled once it is
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: AtomicBool,
+    notify: Notify,
+    /// The token this one was made from with a timeout. Cancelling it cancels this one too
+    parent: Option<CancellationToken>,
+    timeout: Option<Timeout>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Timeout {
+    step: &'static str,
+    after: Duration,
+    deadline: Instant,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    /// Returns true if the token was cancelled, or the timeout of the step it is for has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+            || self
+                .state
+                .timeout
+                .is_some_and(|timeout| Instant::now() >= timeout.deadline)
+            || self
+                .state
+                .parent
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Waits until the token is cancelled
+    pub async fn cancelled(&self) {
+        let cancelled = async {
+            loop {
+                // Created before checking the flag, so that a cancel in between is not missed
+                let notified = self.state.notify.notified();
+                if self.state.cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        let parent_cancelled = async {
+            match &self.state.parent {
+                Some(parent) => Box::pin(parent.cancelled()).await,
+                None => std::future::pending().await,
+            }
+        };
+        let timed_out = async {
+            match self.state.timeout {
+                Some(timeout) => tokio::time::sleep_until(timeout.deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = cancelled => {}
+            _ = parent_cancelled => {}
+            _ = timed_out => {}
+        }
+    }
+
+    /// Returns a token for one step of an operation, which is cancelled along with this one or
+    /// when `timeout` has passed. `step` is the name of the step's timeout setting, e.g. "build".
+    /// A timeout too far in the future to be represented is treated as no timeout
+    pub(crate) fn with_timeout(
+        &self,
+        step: &'static str,
+        timeout: Option<Duration>,
+    ) -> CancellationToken {
+        let Some(timeout) = timeout else {
+            return self.clone();
+        };
+        let Some(deadline) = Instant::now().checked_add(timeout) else {
+            return self.clone();
+        };
+        CancellationToken {
+            state: Arc::new(State {
+                parent: Some(self.clone()),
+                timeout: Some(Timeout {
+                    step,
+                    after: timeout,
+                    deadline,
+                }),
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Returns the error for an operation that stopped because the token was cancelled: a
+    /// timeout if a step ran out of time, unless the token itself was cancelled
+    pub(crate) fn error(&self) -> RevError {
+        let mut timed_out = None;
+        let mut token = Some(self);
+        while let Some(current) = token {
+            if current.state.cancelled.load(Ordering::SeqCst) {
+                return RevError::Cancelled;
+            }
+            if timed_out.is_none() {
+                timed_out = current
+                    .state
+                    .timeout
+                    .filter(|timeout| Instant::now() >= timeout.deadline);
+            }
+            token = current.state.parent.as_ref();
+        }
+        match timed_out {
+            Some(timeout) => RevError::TimedOut {
+                step: timeout.step,
+                after: timeout.after,
+            },
+            None => RevError::Cancelled,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::cmds::export::addin_file;
+use crate::cmds::export::scope::InstallScope;
+use crate::cmds::list;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditFindingKind {
+    /// Two enabled entries share an AddInId. Revit silently refuses to load the second one
+    DuplicateAddinId,
+    /// The assembly named in the manifest does not exist
+    MissingAssembly,
+    /// The manifest could not be parsed
+    UnparsableManifest,
+    /// The manifest still contains the template placeholders
+    TemplateManifest,
+    /// A manifest that was renamed so that Revit no longer loads it, e.g. 'MyAddin.addin.disabled'
+    DisabledManifest,
+    /// A folder in the addins directory that no manifest loads anything from
+    OrphanedFolder,
+}
+
+impl AuditFindingKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditFindingKind::DuplicateAddinId => "Duplicate AddInId",
+            AuditFindingKind::MissingAssembly => "Missing assembly",
+            AuditFindingKind::UnparsableManifest => "Unparsable manifest",
+            AuditFindingKind::TemplateManifest => "Template manifest",
+            AuditFindingKind::DisabledManifest => "Disabled manifest",
+            AuditFindingKind::OrphanedFolder => "Orphaned folder",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFinding {
+    pub kind: AuditFindingKind,
+    /// The scope the manifest was found in, e.g. "user" or "machine"
+    pub scope: String,
+    pub revit_version: String,
+    /// The manifest or folder that the finding is about
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Audits the installed manifests and prints every problem found. Returns true if no problems were found
+///
+/// `addins_root` is the folder that contains a folder for each Revit version, such as a copy
+/// taken from another machine. If it is not given, `scope` is audited, or every scope if no
+/// scope is given either
+pub fn execute(addins_root: Option<&str>, json: bool, scope: Option<&InstallScope>) -> bool {
+    let scopes = match (addins_root, scope) {
+        (Some(addins_root), _) => vec![InstallScope::Path(PathBuf::from(addins_root))],
+        (None, Some(scope)) => vec![scope.clone()],
+        (None, None) => InstallScope::load_order(),
+    };
+
+    let mut findings = Vec::new();
+    for scope in &scopes {
+        match scope.addins_root() {
+            Ok(addins_root) if addins_root.is_dir() => findings.extend(audit_scope(scope)),
+            Ok(addins_root) => {
+                // Only complain about missing folders that were explicitly asked for
+                if scopes.len() == 1 {
+                    println!(
+                        "Addins directory does not exist: {}",
+                        addins_root.to_string_lossy()
+                    );
+                    return false;
+                }
+            }
+            Err(e) => {
+                println!("Could not audit the {} scope: {}", scope, e);
+                return false;
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&findings).unwrap());
+    } else {
+        for finding in &findings {
+            println!(
+                "[{} {}] {}: {}",
+                finding.scope,
+                finding.revit_version,
+                finding.kind.label(),
+                finding.message
+            );
+            println!("    {}", finding.path.to_string_lossy());
+        }
+        if findings.is_empty() {
+            println!("No problems found");
+        } else {
+            println!("Found {} problems", findings.len());
+        }
+    }
+    findings.is_empty()
+}
+
+/// Audits the manifests for every Revit version in the scope
+///
+/// A custom root is usually a folder copied from another machine, so absolute assembly paths
+/// into that machine's Addins folder are looked up in the copy instead
+pub fn audit_scope(scope: &InstallScope) -> Vec<AuditFinding> {
+    let mut findings = Vec::new();
+    if let Ok(addins_root) = scope.addins_root() {
+        let copied_root = match scope {
+            InstallScope::Path(_) => Some(addins_root.as_path()),
+            _ => None,
+        };
+        for (revit_version, version_dir) in list::get_revit_version_dirs(&addins_root) {
+            findings.extend(audit_version_dir(
+                &scope.to_string(),
+                &revit_version,
+                &version_dir,
+                copied_root,
+            ));
+        }
+    }
+    findings
+}
+
+/// Audits the manifests directly inside of a single Revit version directory
+///
+/// `copied_root` is the root of an Addins folder copied from another machine. Absolute assembly
+/// paths such as 'C:\ProgramData\Autodesk\Revit\Addins\2024\X\X.dll' are mapped onto it
+pub fn audit_version_dir(
+    scope: &str,
+    revit_version: &str,
+    version_dir: &Path,
+    copied_root: Option<&Path>,
+) -> Vec<AuditFinding> {
+    let mut findings = Vec::new();
+    let finding = |kind, path: &Path, message: String| AuditFinding {
+        kind,
+        scope: scope.to_string(),
+        revit_version: revit_version.to_string(),
+        path: path.to_path_buf(),
+        message,
+    };
+
+    // The directories that at least one manifest, enabled or not, loads an assembly from
+    let mut referenced_dirs = Vec::new();
+    let mut manifests_by_addin_id: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    for manifest_path in list::get_manifest_paths(version_dir) {
+        let entries = match addin_file::get_addin_manifest_entries(&manifest_path.to_string_lossy())
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                findings.push(finding(
+                    AuditFindingKind::UnparsableManifest,
+                    &manifest_path,
+                    format!("Could not parse the manifest: {}", e),
+                ));
+                // Avoid reporting the folder of a broken manifest as orphaned as well
+                if let Some(stem) = manifest_path.file_stem() {
+                    referenced_dirs.push(version_dir.join(stem));
+                }
+                continue;
+            }
+        };
+
+        if addin_file::is_addin_file_a_template_or_missing(&manifest_path) {
+            findings.push(finding(
+                AuditFindingKind::TemplateManifest,
+                &manifest_path,
+                "The manifest still contains template placeholders".to_string(),
+            ));
+        }
+
+        for entry in entries {
+            let assembly_path = resolve_assembly_path(&manifest_path, &entry.assembly, copied_root);
+            if !assembly_path.is_file() {
+                findings.push(finding(
+                    AuditFindingKind::MissingAssembly,
+                    &manifest_path,
+                    format!(
+                        "{} loads {}, which does not exist",
+                        entry.name,
+                        assembly_path.to_string_lossy()
+                    ),
+                ));
+            }
+            if let Some(assembly_dir) = assembly_path.parent() {
+                referenced_dirs.push(assembly_dir.to_path_buf());
+            }
+            let manifests = manifests_by_addin_id
+                .entry(addin_file::normalize_addin_id(&entry.addin_id))
+                .or_default();
+            manifests.push(manifest_path.clone());
+        }
+    }
+
+    let mut duplicate_ids: Vec<_> = manifests_by_addin_id
+        .into_iter()
+        .filter(|(addin_id, manifests)| !addin_id.is_empty() && manifests.len() > 1)
+        .collect();
+    duplicate_ids.sort();
+    for (addin_id, manifests) in duplicate_ids {
+        let names = manifests
+            .iter()
+            .map(|m| {
+                m.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        for manifest_path in manifests.iter().skip(1) {
+            findings.push(finding(
+                AuditFindingKind::DuplicateAddinId,
+                manifest_path,
+                format!(
+                    "AddInId {} is used by {}. Revit will only load the first one",
+                    addin_id, names
+                ),
+            ));
+        }
+    }
+
+    for manifest_path in get_disabled_manifest_paths(version_dir) {
+        findings.push(finding(
+            AuditFindingKind::DisabledManifest,
+            &manifest_path,
+            "The manifest has been renamed and will not be loaded by Revit".to_string(),
+        ));
+        if let Ok(entries) =
+            addin_file::get_addin_manifest_entries(&manifest_path.to_string_lossy())
+        {
+            for entry in entries {
+                let assembly_path =
+                    resolve_assembly_path(&manifest_path, &entry.assembly, copied_root);
+                if let Some(assembly_dir) = assembly_path.parent() {
+                    referenced_dirs.push(assembly_dir.to_path_buf());
+                }
+            }
+        }
+    }
+
+    if let Ok(entries) = std::fs::read_dir(version_dir) {
+        let mut folders: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        folders.sort();
+        for folder in folders {
+            if !referenced_dirs.iter().any(|dir| dir.starts_with(&folder)) {
+                findings.push(finding(
+                    AuditFindingKind::OrphanedFolder,
+                    &folder,
+                    "No manifest loads anything from this folder".to_string(),
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+/// Resolves the assembly path like Revit would, except that absolute paths into the Addins
+/// folder of the machine the manifests were copied from are mapped onto `copied_root`
+fn resolve_assembly_path(
+    manifest_path: &Path,
+    assembly: &str,
+    copied_root: Option<&Path>,
+) -> PathBuf {
+    let assembly_path = addin_file::resolve_assembly_path(manifest_path, assembly);
+    let Some(copied_root) = copied_root else {
+        return assembly_path;
+    };
+    // Checked by hand, since 'C:\' paths are not absolute when the copy is audited on Linux
+    let parts: Vec<&str> = assembly.trim().split(['\\', '/']).collect();
+    if !(parts[0].is_empty() || parts[0].ends_with(':')) {
+        return assembly_path;
+    }
+    let version_index = parts.windows(2).position(|pair| {
+        pair[0].eq_ignore_ascii_case("addins")
+            && pair[1].len() == 4
+            && pair[1].chars().all(|c| c.is_ascii_digit())
+    });
+    match version_index {
+        Some(i) => parts[i + 1..]
+            .iter()
+            .fold(copied_root.to_path_buf(), |path, part| path.join(part)),
+        None => assembly_path,
+    }
+}
+
+/// Returns the manifests that were renamed to stop Revit from loading them, e.g. 'MyAddin.addin.bak'
+fn get_disabled_manifest_paths(version_dir: &Path) -> Vec<PathBuf> {
+    let mut manifest_paths = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(version_dir) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.path().is_file()
+                && file_name.contains(".addin")
+                && !file_name.ends_with(".addin")
+            {
+                manifest_paths.push(entry.path());
+            }
+        }
+    }
+    manifest_paths.sort();
+    manifest_paths
+}
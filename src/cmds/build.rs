@@ -1,51 +1,202 @@
-use crate::{external_cmds::{dotnet::{self, DotnetError}, msbuild::{self, MsBuildError}}, utils};
+use std::path::{Path, PathBuf};
 
+use crate::{
+    cancel::CancellationToken,
+    error::{BuildDiagnostic, BuildError, DiagnosticSeverity, DiscoveryError, RevError, Toolchain},
+    events::{ConsoleSink, Event, EventSink, Phase},
+    external_cmds::{
+        dotnet::{self, DotnetError},
+        msbuild::{self, MsBuildError},
+    },
+    project_config::{self, ProjectConfig},
+    utils,
+};
 
-pub async fn execute(starting_dir: &str) {
-    match build_csharp_project(starting_dir).await {
-        Ok(_output) => {
-            println!("Project successfully built");
-        }
-        Err(msg) => {
-            println!("Build Error: {}", msg)
-        }
+/// Builds the project, printing its progress and the compiler's errors and warnings as they are
+/// reported
+pub async fn execute(starting_dir: &str, cancel: &CancellationToken) {
+    let result = build_project(starting_dir, &ConsoleSink, cancel).await;
+    // A failed compile was already printed as its diagnostics
+    if let Err(e) = result
+        && !matches!(&e, RevError::Build(build_error) if build_error.errors().next().is_some())
+    {
+        println!("Error: {}", e);
     }
 }
 
 /// Returns the output from the build command if it was successful, or an error message.
+/// Progress is printed to the console
 pub async fn build_csharp_project(starting_dir: &str) -> Result<String, String> {
-    let csproj_path =
-        utils::recursively_check_for_file(starting_dir, "*.csproj", 3, utils::SearchDirection::Child);
-    if let Some(csproj_path) = csproj_path {
-        match msbuild::build_project(&csproj_path).await {
-            Ok(output) => Ok(output),
-            Err(e) => {
-                match e {
-                    MsBuildError::NotFound => {
-                        println!(
-                            "Could not find MSBuild installation. Defaulting to using dotnet to build the project..."
-                        );
-                    }
-                    MsBuildError::Output(output) => {
-                        println!("Error building with MSBuild: {}", output);
-                    }
-                }
-                // Try dotnet since msbuild did not work:
-                match dotnet::build_project(&csproj_path).await {
-                    Ok(output) => Ok(output),
-                    Err(dotnet_err) => match dotnet_err {
-                        DotnetError::NotFound => {
-                            Err("Could not find Dotnet installation on system".to_string())
-                        }
-                        DotnetError::Output(output) => {
-                            Err(format!("Error building with dotnet: {}", output))
-                        }
-                    },
-                }
-            }
+    build_project(starting_dir, &ConsoleSink, &CancellationToken::new())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Builds the project with MSBuild, falling back to dotnet if MSBuild is not installed or fails.
+/// Returns the output from the build command if it was successful
+pub async fn build_project(
+    starting_dir: &str,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, RevError> {
+    let csproj_path = utils::recursively_check_for_file(
+        starting_dir,
+        "*.csproj",
+        3,
+        utils::SearchDirection::Child,
+    )
+    .ok_or_else(|| DiscoveryError::Project {
+        starting_dir: PathBuf::from(starting_dir),
+    })?;
+    // A rev.json that cannot be read is reported by the commands that need its other settings
+    let project_config = Path::new(&csproj_path)
+        .parent()
+        .and_then(|csproj_dir| {
+            project_config::get_project_config(&csproj_dir.to_string_lossy()).ok()
+        })
+        .unwrap_or_default();
+    build_csproj(&csproj_path, &project_config, events, cancel).await
+}
+
+/// Builds the project file with MSBuild, falling back to dotnet if MSBuild is not installed or fails.
+/// The build is stopped when `cancel` is cancelled, or after the build timeout in `project_config`
+pub async fn build_csproj(
+    csproj_path: &str,
+    project_config: &ProjectConfig,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, RevError> {
+    let cancel = &cancel.with_timeout("build", project_config.timeouts.build_timeout());
+
+    events.emit(Event::PhaseStarted(Phase::Build));
+    let mut result = build_with_fallback(csproj_path, events, cancel).await;
+    if result.is_err() && cancel.is_cancelled() {
+        result = Err(cancel.error());
+    }
+    match &result {
+        Ok(output) => emit_diagnostics(&parse_diagnostics(output), events),
+        Err(RevError::Build(build_error)) => emit_diagnostics(&build_error.diagnostics, events),
+        Err(_) => {}
+    }
+    events.emit(Event::PhaseFinished {
+        phase: Phase::Build,
+        success: result.is_ok(),
+    });
+    result
+}
+
+async fn build_with_fallback(
+    csproj_path: &str,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, RevError> {
+    let msbuild_error = match msbuild::build_project(csproj_path, events, cancel).await {
+        Ok(output) => return Ok(output),
+        Err(MsBuildError::NotFound) => {
+            events.emit(Event::Message(
+                "Could not find MSBuild installation. Defaulting to using dotnet to build the project..."
+                    .to_string(),
+            ));
+            None
+        }
+        Err(MsBuildError::Output(output)) => {
+            let msbuild_error = get_build_error(Toolchain::MsBuild, output);
+            events.emit(Event::Message(msbuild_error.to_string()));
+            Some(msbuild_error)
+        }
+    };
+    if cancel.is_cancelled() {
+        return Err(cancel.error());
+    }
+    // Try dotnet since msbuild did not work:
+    match dotnet::build_project(csproj_path, events, cancel).await {
+        Ok(output) => Ok(output),
+        Err(DotnetError::Output(output)) => Err(get_build_error(Toolchain::Dotnet, output).into()),
+        // The MSBuild errors explain more than dotnet being missing
+        Err(DotnetError::NotFound) => match msbuild_error {
+            Some(msbuild_error) => Err(msbuild_error.into()),
+            None => Err(RevError::ToolchainNotFound {
+                tried: vec![Toolchain::MsBuild, Toolchain::Dotnet],
+            }),
+        },
+    }
+}
+
+fn emit_diagnostics(diagnostics: &[BuildDiagnostic], events: &dyn EventSink) {
+    for diagnostic in diagnostics {
+        events.emit(Event::Diagnostic(diagnostic.clone()));
+    }
+}
+
+fn get_build_error(toolchain: Toolchain, output: String) -> BuildError {
+    BuildError {
+        toolchain,
+        diagnostics: parse_diagnostics(&output),
+        output,
+    }
+}
+
+/// Finds the compiler errors and warnings in MSBuild output, which are written as
+/// "<file>(<line>,<column>): error <code>: <message> [<project>]", or without the file for
+/// errors such as "MSBUILD : error MSB1009: Project file does not exist."
+pub fn parse_diagnostics(output: &str) -> Vec<BuildDiagnostic> {
+    let mut diagnostics: Vec<BuildDiagnostic> = Vec::new();
+    for line in output.lines() {
+        let Some(diagnostic) = parse_diagnostic(line.trim()) else {
+            continue;
+        };
+        // MSBuild lists every error again in the summary at the end of the build
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
         }
-    } else {
-        Err("No csproj file found".to_string())
     }
+    diagnostics
 }
 
+fn parse_diagnostic(line: &str) -> Option<BuildDiagnostic> {
+    let (location, severity, rest) = [
+        (": error ", DiagnosticSeverity::Error),
+        (": warning ", DiagnosticSeverity::Warning),
+    ]
+    .into_iter()
+    .find_map(|(separator, severity)| {
+        line.split_once(separator)
+            .map(|(location, rest)| (location.trim(), severity, rest))
+    })?;
+    let (code, message) = rest.split_once(": ")?;
+    if code.contains(char::is_whitespace) {
+        return None;
+    }
+    // Drop the project the diagnostic belongs to
+    let message = match message.rfind(" [") {
+        Some(index) if message.ends_with(']') => &message[..index],
+        _ => message,
+    };
+
+    let (file, line, column) = match location
+        .strip_suffix(')')
+        .and_then(|location| location.rsplit_once('('))
+    {
+        Some((file, position)) => {
+            let mut numbers = position.split(',').map(|n| n.trim().parse::<u32>().ok());
+            (
+                Some(file.to_string()),
+                numbers.next().flatten(),
+                numbers.next().flatten(),
+            )
+        }
+        // Errors from MSBuild itself start with the name of the tool
+        None if location.is_empty() || location.eq_ignore_ascii_case("MSBUILD") => {
+            (None, None, None)
+        }
+        None => (Some(location.to_string()), None, None),
+    };
+    Some(BuildDiagnostic {
+        severity,
+        code: code.to_string(),
+        message: message.trim().to_string(),
+        file,
+        line,
+        column,
+    })
+}
@@ -0,0 +1,366 @@
+use crate::cmds::build;
+use crate::cmds::export::addin_file;
+use crate::cmds::locate;
+use crate::utils::assembly_metadata::{self, AssemblyMetadata};
+use crate::utils::error_list::ErrorList;
+use std::fmt::Display;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameworkKind {
+    /// .NET Framework, e.g. net48
+    Framework,
+    /// .NET Core and .NET 5 onwards, e.g. net8.0-windows
+    Core,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetFramework {
+    pub kind: FrameworkKind,
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl TargetFramework {
+    /// Parses the value of a `TargetFrameworkAttribute`, e.g. ".NETFramework,Version=v4.8"
+    pub fn parse(value: &str) -> Option<Self> {
+        let (identifier, rest) = value.split_once(',')?;
+        let kind = match identifier.trim() {
+            ".NETFramework" => FrameworkKind::Framework,
+            ".NETCoreApp" => FrameworkKind::Core,
+            _ => return None,
+        };
+        let version = rest
+            .split(',')
+            .find_map(|part| part.trim().strip_prefix("Version="))?
+            .trim_start_matches('v');
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().unwrap_or("0").parse().ok()?;
+        Some(Self { kind, major, minor })
+    }
+
+    /// Returns the framework that the given version of Revit loads addins into
+    pub fn required_by_revit(revit_version: u32) -> Self {
+        let (kind, major, minor) = if revit_version >= 2025 {
+            (FrameworkKind::Core, 8, 0)
+        } else if revit_version >= 2021 {
+            (FrameworkKind::Framework, 4, 8)
+        } else {
+            (FrameworkKind::Framework, 4, 7)
+        };
+        Self { kind, major, minor }
+    }
+
+    /// Returns the value to use for `<TargetFramework>` in the csproj file, e.g. "net48"
+    pub fn moniker(&self) -> String {
+        match self.kind {
+            FrameworkKind::Framework => format!("net{}{}", self.major, self.minor),
+            FrameworkKind::Core => format!("net{}.{}-windows", self.major, self.minor),
+        }
+    }
+}
+
+impl Display for TargetFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            FrameworkKind::Framework => write!(f, ".NET Framework {}.{}", self.major, self.minor),
+            FrameworkKind::Core => write!(f, ".NET {}", self.major),
+        }
+    }
+}
+
+/// Builds the project and checks that the built DLL can be loaded by the given version of Revit,
+/// and that the classes in the project's '.addin' file exist in it. Returns true if no errors were found
+pub async fn execute(starting_dir: &str, for_version: &str) -> bool {
+    let Ok(revit_version) = for_version.parse::<u32>() else {
+        println!("Invalid Revit version: {}", for_version);
+        return false;
+    };
+    if let Err(e) = build::build_csharp_project(starting_dir).await {
+        println!("Could not build project: {}", e);
+    }
+    let dll_path = match locate::get_main_dll_path(true, starting_dir) {
+        Ok(dll_path) => dll_path,
+        Err(e) => {
+            println!("Could not find DLL for project: {}", e);
+            return false;
+        }
+    };
+
+    let mut errors = check_compatibility(Path::new(&dll_path), revit_version);
+    if let Ok(project_info) = locate::get_project_info(starting_dir) {
+        let addin_file_path = Path::new(&project_info.csproj_dir)
+            .join(format!("{}.addin", project_info.project_name));
+        if !addin_file::is_addin_file_a_template_or_missing(&addin_file_path)
+            && let Ok(metadata) = assembly_metadata::read_assembly_metadata(Path::new(&dll_path))
+        {
+            errors.extend(&addin_file::check_manifest_classes(
+                &addin_file_path,
+                &metadata,
+            ));
+        }
+    }
+    for error in errors.view_errors() {
+        println!("Error: {}", error);
+    }
+    for warning in errors.view_warnings() {
+        println!("Warning: {}", warning);
+    }
+    if !errors.has_errors() && !errors.has_warnings() {
+        println!("{} is compatible with Revit {}", dll_path, revit_version);
+    }
+    !errors.has_errors()
+}
+
+/// Reads the metadata of the built DLL and reports anything that would stop it from loading
+/// in the given version of Revit
+pub fn check_compatibility(dll_path: &Path, revit_version: u32) -> ErrorList {
+    match assembly_metadata::read_assembly_metadata(dll_path) {
+        Ok(metadata) => check_metadata(&metadata, revit_version),
+        Err(e) => {
+            let mut error_list = ErrorList::new();
+            error_list.add_warning(&format!(
+                "Could not read the metadata of {}: {}",
+                dll_path.to_string_lossy(),
+                e
+            ));
+            error_list
+        }
+    }
+}
+
+fn check_metadata(metadata: &AssemblyMetadata, revit_version: u32) -> ErrorList {
+    let mut error_list = ErrorList::new();
+    check_target_framework(metadata, revit_version, &mut error_list);
+    check_revit_api_version(metadata, revit_version, &mut error_list);
+    error_list
+}
+
+fn check_target_framework(
+    metadata: &AssemblyMetadata,
+    revit_version: u32,
+    error_list: &mut ErrorList,
+) {
+    let required = TargetFramework::required_by_revit(revit_version);
+    let Some(target_framework) = metadata
+        .target_framework
+        .as_deref()
+        .and_then(TargetFramework::parse)
+    else {
+        error_list.add_warning(&format!(
+            "Could not determine the target framework of {}. Revit {} requires {}",
+            metadata.name, revit_version, required
+        ));
+        return;
+    };
+
+    if target_framework.kind != required.kind {
+        error_list.add_error(&format!(
+            "{} targets {}, but Revit {} loads add-ins into {}. Set <TargetFramework>{}</TargetFramework> in the .csproj file",
+            metadata.name,
+            target_framework,
+            revit_version,
+            required,
+            required.moniker()
+        ));
+    } else if (target_framework.major, target_framework.minor) > (required.major, required.minor) {
+        let message = format!(
+            "{} targets {}, which is newer than the {} used by Revit {}",
+            metadata.name, target_framework, required, revit_version
+        );
+        // A newer .NET Framework can still be installed on the machine, but a newer .NET cannot be loaded at all
+        match required.kind {
+            FrameworkKind::Framework => error_list.add_warning(&message),
+            FrameworkKind::Core => error_list.add_error(&message),
+        }
+    }
+}
+
+fn check_revit_api_version(
+    metadata: &AssemblyMetadata,
+    revit_version: u32,
+    error_list: &mut ErrorList,
+) {
+    let Some(reference) = ["RevitAPI", "RevitAPIUI"]
+        .iter()
+        .find_map(|api_assembly| metadata.get_reference(api_assembly))
+    else {
+        return;
+    };
+    // The Revit API assembly versions are the last two digits of the year, e.g. 24.0.0.0 for 2024
+    let api_year = 2000 + reference.version.major as u32;
+    if api_year > revit_version {
+        error_list.add_error(&format!(
+            "{} was compiled against the Revit {} API ({} {}), which does not exist in Revit {}",
+            metadata.name, api_year, reference.name, reference.version, revit_version
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::assembly_metadata::{AssemblyReference, AssemblyVersion};
+
+    fn framework(kind: FrameworkKind, major: u32, minor: u32) -> TargetFramework {
+        TargetFramework { kind, major, minor }
+    }
+
+    fn test_metadata(
+        target_framework: Option<&str>,
+        revit_api_year: Option<u16>,
+    ) -> AssemblyMetadata {
+        let version = |major| AssemblyVersion {
+            major,
+            minor: 0,
+            build: 0,
+            revision: 0,
+        };
+        AssemblyMetadata {
+            name: "MyAddin".to_string(),
+            version: version(1),
+            target_framework: target_framework.map(str::to_string),
+            references: revit_api_year
+                .map(|year| AssemblyReference {
+                    name: "RevitAPI".to_string(),
+                    version: version(year - 2000),
+                })
+                .into_iter()
+                .collect(),
+            types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_target_framework_attributes() {
+        assert_eq!(
+            TargetFramework::parse(".NETFramework,Version=v4.8"),
+            Some(framework(FrameworkKind::Framework, 4, 8))
+        );
+        assert_eq!(
+            TargetFramework::parse(".NETFramework,Version=v4.7.2"),
+            Some(framework(FrameworkKind::Framework, 4, 7))
+        );
+        assert_eq!(
+            TargetFramework::parse(".NETFramework, Profile=Client, Version=v4.8"),
+            Some(framework(FrameworkKind::Framework, 4, 8))
+        );
+        assert_eq!(
+            TargetFramework::parse(".NETCoreApp,Version=v8.0"),
+            Some(framework(FrameworkKind::Core, 8, 0))
+        );
+        assert_eq!(
+            TargetFramework::parse(".NETCoreApp,Version=v10"),
+            Some(framework(FrameworkKind::Core, 10, 0))
+        );
+        assert_eq!(TargetFramework::parse(".NETStandard,Version=v2.0"), None);
+        assert_eq!(TargetFramework::parse(".NETFramework"), None);
+        assert_eq!(TargetFramework::parse(".NETFramework,Profile=Client"), None);
+        assert_eq!(TargetFramework::parse(".NETCoreApp,Version=vNext"), None);
+    }
+
+    #[test]
+    fn picks_the_framework_of_each_revit_version() {
+        for (revit_version, moniker) in [
+            (2019, "net47"),
+            (2020, "net47"),
+            (2021, "net48"),
+            (2024, "net48"),
+            (2025, "net8.0-windows"),
+            (2026, "net8.0-windows"),
+        ] {
+            assert_eq!(
+                TargetFramework::required_by_revit(revit_version).moniker(),
+                moniker,
+                "Revit {}",
+                revit_version
+            );
+        }
+        assert_eq!(
+            TargetFramework::required_by_revit(2025).to_string(),
+            ".NET 8"
+        );
+        assert_eq!(
+            TargetFramework::required_by_revit(2024).to_string(),
+            ".NET Framework 4.8"
+        );
+    }
+
+    #[test]
+    fn rejects_net48_for_revit_2025() {
+        let errors = check_metadata(
+            &test_metadata(Some(".NETFramework,Version=v4.8"), None),
+            2025,
+        );
+        assert_eq!(errors.view_errors().len(), 1);
+        assert!(
+            errors.view_errors()[0].contains("<TargetFramework>net8.0-windows</TargetFramework>")
+        );
+    }
+
+    #[test]
+    fn rejects_net8_for_revit_2024() {
+        let errors = check_metadata(&test_metadata(Some(".NETCoreApp,Version=v8.0"), None), 2024);
+        assert_eq!(errors.view_errors().len(), 1);
+        assert!(errors.view_errors()[0].contains("<TargetFramework>net48</TargetFramework>"));
+    }
+
+    #[test]
+    fn checks_newer_framework_versions() {
+        // A newer .NET cannot be loaded by Revit at all
+        let errors = check_metadata(&test_metadata(Some(".NETCoreApp,Version=v9.0"), None), 2025);
+        assert!(errors.has_errors());
+        // A newer .NET Framework may be installed on the machine
+        let errors = check_metadata(
+            &test_metadata(Some(".NETFramework,Version=v4.8"), None),
+            2020,
+        );
+        assert!(!errors.has_errors());
+        assert_eq!(errors.view_warnings().len(), 1);
+        // An older .NET Framework runs on the newer one
+        let errors = check_metadata(
+            &test_metadata(Some(".NETFramework,Version=v4.7.2"), None),
+            2024,
+        );
+        assert!(!errors.has_errors() && !errors.has_warnings());
+    }
+
+    #[test]
+    fn rejects_a_newer_revit_api() {
+        let errors = check_metadata(
+            &test_metadata(Some(".NETFramework,Version=v4.8"), Some(2023)),
+            2021,
+        );
+        assert_eq!(errors.view_errors().len(), 1);
+        assert!(errors.view_errors()[0].contains("Revit 2023 API (RevitAPI 23.0.0.0)"));
+
+        for revit_version in [2023, 2024] {
+            let errors = check_metadata(
+                &test_metadata(Some(".NETFramework,Version=v4.8"), Some(2023)),
+                revit_version,
+            );
+            assert!(!errors.has_errors() && !errors.has_warnings());
+        }
+    }
+
+    #[test]
+    fn warns_when_the_framework_is_unknown() {
+        let errors = check_metadata(&test_metadata(None, None), 2024);
+        assert!(!errors.has_errors());
+        assert_eq!(errors.view_warnings().len(), 1);
+    }
+
+    #[test]
+    fn warns_when_the_dll_cannot_be_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let errors = check_compatibility(&dir.path().join("MyAddin.dll"), 2024);
+        assert!(!errors.has_errors());
+        assert_eq!(errors.view_warnings().len(), 1);
+
+        std::fs::write(dir.path().join("MyAddin.dll"), b"not a DLL").unwrap();
+        let errors = check_compatibility(&dir.path().join("MyAddin.dll"), 2024);
+        assert!(!errors.has_errors());
+        assert_eq!(errors.view_warnings().len(), 1);
+    }
+}
@@ -0,0 +1,629 @@
+use crate::cmds::export::payload;
+use crate::cmds::locate;
+use crate::cmds::profile;
+use crate::error::{DiscoveryError, RevError};
+use crate::external_cmds::registry;
+use crate::project_config;
+use crate::revit_version;
+use crate::state;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::path::Path;
+
+/// Where a setting is saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// The settings for every project, saved with the rev state
+    Global,
+    /// The settings of the current project, saved in its rev.json
+    Project,
+}
+
+impl Display for ConfigScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigScope::Global => write!(f, "global"),
+            ConfigScope::Project => write!(f, "project"),
+        }
+    }
+}
+
+/// The kinds of values a setting can hold, which decide how values given on the command line are
+/// parsed and validated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    /// The year of a supported Revit version that is installed on this machine
+    RevitVersion,
+    Email,
+    Boolean,
+    /// One of a fixed set of values
+    Choice(&'static [&'static str]),
+    /// A list of glob patterns, given as a JSON array or separated by commas
+    Patterns,
+    /// A file path relative to the csproj directory, whose folder must exist
+    FilePath,
+    /// The name of a vendor profile
+    Profile,
+    /// A whole number of seconds, up to `MAX_TIMEOUT_SECONDS`
+    Seconds,
+}
+
+/// The longest timeout that can be set, a week. Longer limits are the same as no limit
+const MAX_TIMEOUT_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// A setting that can be read and written with `rev config`
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub key: &'static str,
+    pub scope: ConfigScope,
+    pub kind: SettingKind,
+    pub description: &'static str,
+    /// The path to the value in the JSON file the setting is saved in
+    path: &'static [&'static str],
+}
+
+pub const SETTINGS: [Setting; 15] = [
+    Setting {
+        key: "revitVersion",
+        scope: ConfigScope::Global,
+        kind: SettingKind::RevitVersion,
+        description: "The Revit version that add-ins are built and exported for",
+        path: &["revit_version"],
+    },
+    Setting {
+        key: "emailAddress",
+        scope: ConfigScope::Global,
+        kind: SettingKind::Email,
+        description: "The vendor email address written to new .addin files",
+        path: &["email_address"],
+    },
+    Setting {
+        key: "revitVersion",
+        scope: ConfigScope::Project,
+        kind: SettingKind::RevitVersion,
+        description: "The Revit version the project is pinned to, instead of the global one",
+        path: &["revitVersion"],
+    },
+    Setting {
+        key: "profile",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Profile,
+        description: "The vendor profile that pre-fills the .addin file",
+        path: &["profile"],
+    },
+    Setting {
+        key: "export.include",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Patterns,
+        description: "Extra files from the build output to export with the add-in",
+        path: &["export", "include"],
+    },
+    Setting {
+        key: "export.exclude",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Patterns,
+        description: "Files from the build output to leave out even if they are included",
+        path: &["export", "exclude"],
+    },
+    Setting {
+        key: "web.packageManager",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Choice(&["npm", "yarn", "pnpm", "bun"]),
+        description: "The package manager that builds the web apps",
+        path: &["web", "packageManager"],
+    },
+    Setting {
+        key: "web.exclude",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Patterns,
+        description: "Folders that are never searched for web apps. Defaults to node_modules",
+        path: &["web", "exclude"],
+    },
+    Setting {
+        key: "web.types.sources",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Patterns,
+        description: "The C# files to generate TypeScript types from",
+        path: &["web", "types", "sources"],
+    },
+    Setting {
+        key: "web.types.output",
+        scope: ConfigScope::Project,
+        kind: SettingKind::FilePath,
+        description: "The TypeScript file the generated types are written to",
+        path: &["web", "types", "output"],
+    },
+    Setting {
+        key: "web.types.propertyNaming",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Choice(&["preserve", "camelCase"]),
+        description: "How property names are written in the JSON messages",
+        path: &["web", "types", "propertyNaming"],
+    },
+    Setting {
+        key: "web.types.enumsAsStrings",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Boolean,
+        description: "Whether every enum is serialized by name",
+        path: &["web", "types", "enumsAsStrings"],
+    },
+    Setting {
+        key: "timeouts.build",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Seconds,
+        description: "The seconds the C# build may take before it is stopped",
+        path: &["timeouts", "build"],
+    },
+    Setting {
+        key: "timeouts.webBuild",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Seconds,
+        description: "The seconds the web apps may take to install and build",
+        path: &["timeouts", "webBuild"],
+    },
+    Setting {
+        key: "timeouts.export",
+        scope: ConfigScope::Project,
+        kind: SettingKind::Seconds,
+        description: "The seconds an export may take, not counting the C# build",
+        path: &["timeouts", "export"],
+    },
+];
+
+/// Returns the setting with the key in `scope`. Keys are matched regardless of case, and global
+/// settings can also be written as they are in the state file, e.g. 'revit_version'. A key used
+/// in both scopes, such as 'revitVersion', is the global setting unless `scope` is the project
+pub fn find_setting(key: &str, scope: Option<ConfigScope>) -> Result<&'static Setting, String> {
+    let mut matches = SETTINGS.iter().filter(|setting| {
+        setting.key.eq_ignore_ascii_case(key)
+            || (setting.scope == ConfigScope::Global
+                && setting.path.join(".").eq_ignore_ascii_case(key))
+    });
+    // SETTINGS lists the global settings first
+    let first = matches.next().ok_or_else(|| {
+        format!(
+            "Unknown setting '{}'. Run 'rev config list' to see every setting",
+            key
+        )
+    })?;
+    match scope {
+        None => Ok(first),
+        Some(scope) => std::iter::once(first)
+            .chain(matches)
+            .find(|setting| setting.scope == scope)
+            .ok_or_else(|| {
+                format!(
+                    "{} is a {} setting, so it cannot be used with --{}",
+                    first.key, first.scope, scope
+                )
+            }),
+    }
+}
+
+/// Prints the value of a setting. Returns false if the setting could not be read
+pub fn execute_get(starting_dir: &str, key: &str, scope: Option<ConfigScope>) -> bool {
+    let result = find_setting(key, scope)
+        .and_then(|setting| get_value(starting_dir, setting).map_err(|e| e.to_string()));
+    match result {
+        Ok(Some(value)) => {
+            println!("{}", format_value(&value));
+            true
+        }
+        Ok(None) => {
+            println!("{} is not set", key);
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// Validates and saves the value of a setting. Returns false if it was not saved
+pub async fn execute_set(
+    starting_dir: &str,
+    key: &str,
+    value: &str,
+    scope: Option<ConfigScope>,
+) -> bool {
+    let setting = match find_setting(key, scope) {
+        Ok(setting) => setting,
+        Err(e) => {
+            println!("Error: {}", e);
+            return false;
+        }
+    };
+    let result = match parse_value(starting_dir, setting, value).await {
+        Ok(parsed) => set_value(starting_dir, setting, Some(parsed.clone()))
+            .await
+            .map(|_| parsed)
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(parsed) => {
+            println!(
+                "Set {} {} to {}",
+                setting.scope,
+                setting.key,
+                format_value(&parsed)
+            );
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// Removes a setting, so that its default is used. Returns false if it could not be removed
+pub async fn execute_unset(starting_dir: &str, key: &str, scope: Option<ConfigScope>) -> bool {
+    let result = match find_setting(key, scope) {
+        Ok(setting) => set_value(starting_dir, setting, None)
+            .await
+            .map(|_| setting)
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(setting) => {
+            println!("Unset {} {}", setting.scope, setting.key);
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// Prints every setting in the scope, or in both scopes if none is given, with its value
+pub fn execute_list(starting_dir: &str, scope: Option<ConfigScope>) -> bool {
+    let mut success = true;
+    for config_scope in [ConfigScope::Global, ConfigScope::Project] {
+        if scope.is_some_and(|scope| scope != config_scope) {
+            continue;
+        }
+        println!("{}:", config_scope);
+        for setting in SETTINGS.iter().filter(|s| s.scope == config_scope) {
+            match get_value(starting_dir, setting) {
+                Ok(Some(value)) => println!("  {} = {}", setting.key, format_value(&value)),
+                Ok(None) => println!("  {} is not set. {}", setting.key, setting.description),
+                Err(e) => {
+                    println!("  {}", e);
+                    success = false;
+                    break;
+                }
+            }
+        }
+    }
+    success
+}
+
+/// Returns the saved value of a setting, or None if it is not set
+pub fn get_value(starting_dir: &str, setting: &Setting) -> Result<Option<Value>, RevError> {
+    let config = match setting.scope {
+        // Serializing the state cannot fail, it only holds strings and maps
+        ConfigScope::Global => {
+            serde_json::to_value(state::get_state_or_default()).unwrap_or_default()
+        }
+        ConfigScope::Project => {
+            project_config::get_project_config_json(&get_csproj_dir(starting_dir)?)?
+        }
+    };
+    let value = setting
+        .path
+        .iter()
+        .try_fold(&config, |value, key| value.get(key));
+    // Global settings are saved as empty strings when they are not set
+    Ok(value
+        .filter(|value| !value.is_null() && value.as_str() != Some(""))
+        .cloned())
+}
+
+/// Saves the value of a setting, or removes it if `value` is None
+pub async fn set_value(
+    starting_dir: &str,
+    setting: &Setting,
+    value: Option<Value>,
+) -> Result<(), RevError> {
+    match setting.scope {
+        ConfigScope::Global => {
+            let mut error = None;
+            state::update_state(|state| {
+                let mut config = serde_json::to_value(&*state).unwrap_or_default();
+                set_path(&mut config, setting.path, value.or(Some(Value::from(""))));
+                match serde_json::from_value(config) {
+                    Ok(updated) => *state = updated,
+                    Err(e) => error = Some(RevError::config(e.to_string())),
+                }
+            })
+            .await?;
+            error.map_or(Ok(()), Err)
+        }
+        ConfigScope::Project => {
+            let csproj_dir = get_csproj_dir(starting_dir)?;
+            let mut config = project_config::get_project_config_json(&csproj_dir)?;
+            set_path(&mut config, setting.path, value);
+            project_config::save_project_config_json(&csproj_dir, &config)
+        }
+    }
+}
+
+/// Parses a value given on the command line into the JSON the setting is saved as, returning an
+/// error if the value is not valid for the setting
+pub async fn parse_value(
+    starting_dir: &str,
+    setting: &Setting,
+    value: &str,
+) -> Result<Value, String> {
+    let value = value.trim();
+    match setting.kind {
+        SettingKind::RevitVersion => {
+            let version = revit_version::parse_revit_version(value)?;
+            if let Some(installed) = registry::get_installed_revit_versions().await
+                && !installed.iter().any(|year| year.to_string() == version)
+            {
+                let installed: Vec<String> = installed.iter().map(u32::to_string).collect();
+                return Err(format!(
+                    "Revit {} is not installed. Installed versions: {}",
+                    version,
+                    if installed.is_empty() {
+                        "none".to_string()
+                    } else {
+                        installed.join(", ")
+                    }
+                ));
+            }
+            Ok(Value::from(version))
+        }
+        SettingKind::Email => {
+            if is_email_address(value) {
+                Ok(Value::from(value))
+            } else {
+                Err(format!("'{}' is not an email address", value))
+            }
+        }
+        SettingKind::Boolean => match value.to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok(Value::Bool(true)),
+            "false" | "no" | "0" => Ok(Value::Bool(false)),
+            _ => Err(format!("'{}' is not true or false", value)),
+        },
+        SettingKind::Choice(choices) => choices
+            .iter()
+            .find(|choice| choice.eq_ignore_ascii_case(value))
+            .map(|choice| Value::from(*choice))
+            .ok_or_else(|| format!("'{}' must be one of: {}", value, choices.join(", "))),
+        SettingKind::Patterns => {
+            let patterns: Vec<String> = if value.starts_with('[') {
+                serde_json::from_str(value)
+                    .map_err(|e| format!("'{}' is not a list of patterns: {}", value, e))?
+            } else {
+                value
+                    .split(',')
+                    .map(|pattern| pattern.trim().to_string())
+                    .filter(|pattern| !pattern.is_empty())
+                    .collect()
+            };
+            payload::compile_patterns(&patterns)?;
+            Ok(Value::from(patterns))
+        }
+        SettingKind::Profile => {
+            let state = state::get_state_or_default();
+            match profile::find_profile(&state.profiles, value) {
+                Some((name, _)) => Ok(Value::from(name.as_str())),
+                None => Err(format!(
+                    "The profile '{}' does not exist. Create it with 'rev profile set {}'",
+                    value, value
+                )),
+            }
+        }
+        SettingKind::Seconds => match value.parse::<u64>() {
+            Ok(seconds) if (1..=MAX_TIMEOUT_SECONDS).contains(&seconds) => Ok(Value::from(seconds)),
+            _ => Err(format!(
+                "'{}' is not a number of seconds from 1 to {}. Unset the setting to remove the limit",
+                value, MAX_TIMEOUT_SECONDS
+            )),
+        },
+        SettingKind::FilePath => {
+            let path = Path::new(value);
+            if value.is_empty() || path.is_absolute() {
+                return Err(format!(
+                    "'{}' must be a file path relative to the project folder",
+                    value
+                ));
+            }
+            let folder = Path::new(&get_csproj_dir(starting_dir).map_err(|e| e.to_string())?)
+                .join(path)
+                .parent()
+                .map(Path::to_path_buf);
+            if !folder.as_deref().is_some_and(Path::is_dir) {
+                return Err(format!(
+                    "The folder for '{}' does not exist in the project",
+                    value
+                ));
+            }
+            Ok(Value::from(value.replace('\\', "/")))
+        }
+    }
+}
+
+fn get_csproj_dir(starting_dir: &str) -> Result<String, RevError> {
+    locate::get_project_info(starting_dir)
+        .map(|project_info| project_info.csproj_dir)
+        .map_err(|_| {
+            DiscoveryError::Project {
+                starting_dir: starting_dir.into(),
+            }
+            .into()
+        })
+}
+
+/// Sets the value at the path, creating the objects along it. With None, the value is removed
+/// along with any objects left empty
+fn set_path(config: &mut Value, path: &[&str], value: Option<Value>) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+    if !config.is_object() {
+        *config = Value::Object(Map::new());
+    }
+    let Value::Object(object) = config else {
+        return;
+    };
+    match (rest.is_empty(), value) {
+        (true, Some(value)) => {
+            object.insert(key.to_string(), value);
+        }
+        (true, None) => {
+            object.remove(*key);
+        }
+        (false, Some(value)) => {
+            set_path(
+                object.entry(key.to_string()).or_insert(Value::Null),
+                rest,
+                Some(value),
+            );
+        }
+        (false, None) => {
+            if let Some(child) = object.get_mut(*key) {
+                set_path(child, rest, None);
+                if child.as_object().is_some_and(Map::is_empty) {
+                    object.remove(*key);
+                }
+            }
+        }
+    }
+}
+
+/// Returns true for an address with a local part and a domain of at least two labels, none of
+/// them empty
+pub fn is_email_address(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+/// Formats a value for the console. Strings are printed without quotes and lists as JSON
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sets_nested_values() {
+        let mut config = json!({ "web": { "packageManager": "npm" } });
+        set_path(
+            &mut config,
+            &["web", "types", "output"],
+            Some(json!("types.ts")),
+        );
+        set_path(&mut config, &["timeouts", "build"], Some(json!(60)));
+        assert_eq!(
+            config,
+            json!({
+                "web": { "packageManager": "npm", "types": { "output": "types.ts" } },
+                "timeouts": { "build": 60 }
+            })
+        );
+
+        // A value in the way of the path is replaced by an object
+        set_path(
+            &mut config,
+            &["timeouts", "build", "seconds"],
+            Some(json!(30)),
+        );
+        assert_eq!(config["timeouts"], json!({ "build": { "seconds": 30 } }));
+        let mut config = Value::Null;
+        set_path(&mut config, &["profile"], Some(json!("Client")));
+        assert_eq!(config, json!({ "profile": "Client" }));
+    }
+
+    #[test]
+    fn unsetting_removes_objects_left_empty() {
+        let mut config = json!({
+            "web": { "packageManager": "npm", "types": { "output": "types.ts" } },
+            "timeouts": { "build": 60 }
+        });
+        set_path(&mut config, &["web", "types", "output"], None);
+        assert_eq!(config["web"], json!({ "packageManager": "npm" }));
+        set_path(&mut config, &["timeouts", "build"], None);
+        assert_eq!(config, json!({ "web": { "packageManager": "npm" } }));
+
+        // Unsetting a value that is not set changes nothing
+        set_path(&mut config, &["export", "include"], None);
+        set_path(&mut config, &["web", "exclude"], None);
+        assert_eq!(config, json!({ "web": { "packageManager": "npm" } }));
+    }
+
+    #[test]
+    fn finds_settings_in_their_scope() {
+        let global = find_setting("revitVersion", None).unwrap();
+        assert_eq!(global.scope, ConfigScope::Global);
+        assert_eq!(
+            find_setting("revitversion", Some(ConfigScope::Global))
+                .unwrap()
+                .scope,
+            ConfigScope::Global
+        );
+        let project = find_setting("RevitVersion", Some(ConfigScope::Project)).unwrap();
+        assert_eq!(project.scope, ConfigScope::Project);
+        assert_eq!(project.path, &["revitVersion"]);
+
+        // Global settings can be written as they are saved in the state file
+        assert_eq!(
+            find_setting("email_address", None).unwrap().key,
+            "emailAddress"
+        );
+        assert!(find_setting("email_address", Some(ConfigScope::Project)).is_err());
+
+        // A project setting is found without a scope, but not in the global scope
+        assert_eq!(
+            find_setting("web.types.output", None).unwrap().scope,
+            ConfigScope::Project
+        );
+        assert_eq!(
+            find_setting("web.types.output", Some(ConfigScope::Global)).unwrap_err(),
+            "web.types.output is a project setting, so it cannot be used with --global"
+        );
+        assert!(find_setting("web.types", None).is_err());
+        assert!(find_setting("", None).is_err());
+    }
+
+    #[test]
+    fn accepts_only_email_addresses() {
+        for valid in ["a@b.co", "first.last+rev@mail.example.com", "A@B.C"] {
+            assert!(is_email_address(valid), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "a",
+            "a@b",
+            "@b.com",
+            "a@",
+            "a@.com",
+            "a@b.",
+            "a@b..c",
+            "a@@b.com",
+            "a@b@c.com",
+            "a b@c.com",
+            "a@b.com ",
+        ] {
+            assert!(!is_email_address(invalid), "{}", invalid);
+        }
+    }
+}
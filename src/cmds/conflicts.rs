@@ -0,0 +1,264 @@
+use crate::cmds::build;
+use crate::cmds::export::scope::InstallScope;
+use crate::cmds::export::{dependencies, payload, revit_assemblies};
+use crate::cmds::list;
+use crate::cmds::locate;
+use crate::project_config;
+use crate::utils;
+use crate::utils::assembly_metadata::{self, AssemblyVersion};
+use crate::utils::error_list::ErrorList;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// A copy of an assembly that an addin ships in its folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShippedAssembly {
+    /// The name of the addin's manifest, or the project name for the addin being exported
+    pub addin: String,
+    pub version: AssemblyVersion,
+    pub path: PathBuf,
+}
+
+/// An assembly that the exported addin shares with other installed addins in a different version.
+/// Revit loads every addin into one process, so only the first version to load is used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyConflict {
+    pub assembly_name: String,
+    /// Every copy of the assembly, starting with the one in the export payload
+    pub shipped: Vec<ShippedAssembly>,
+}
+
+/// Prints the assemblies that the project would export in a different version than another
+/// addin installed for the same Revit version. Returns true if no conflicts were found
+///
+/// `scope` limits the search to a single scope. Otherwise every scope is searched, since Revit
+/// loads the addins from all of them into the same process
+pub async fn execute(
+    starting_dir: &str,
+    for_version: &str,
+    json: bool,
+    scope: Option<&InstallScope>,
+) -> bool {
+    if let Err(e) = build::build_csharp_project(starting_dir).await {
+        println!("Could not build project: {}", e);
+    }
+    let project_info = match locate::get_project_info(starting_dir) {
+        Ok(project_info) => project_info,
+        Err(e) => {
+            println!("Could not find project: {}", e);
+            return false;
+        }
+    };
+    let main_dll_path = match locate::get_main_dll_path(true, starting_dir) {
+        Ok(dll_path) => PathBuf::from(dll_path),
+        Err(e) => {
+            println!("Could not find DLL for project: {}", e);
+            return false;
+        }
+    };
+    let project_config = match project_config::get_project_config(&project_info.csproj_dir) {
+        Ok(project_config) => project_config,
+        Err(e) => {
+            println!("Error reading project config: {}", e);
+            return false;
+        }
+    };
+    let scopes = match scope {
+        Some(scope) => vec![scope.clone()],
+        None => InstallScope::load_order(),
+    };
+
+    let mut error_list = ErrorList::new();
+    let csproj_path = Path::new(&project_info.csproj_dir).join(&project_info.full_project_name);
+    let payload_assemblies = get_payload_assemblies(
+        &csproj_path,
+        &main_dll_path,
+        &project_config.export,
+        &mut error_list,
+    );
+    let installed_assemblies = get_installed_assemblies(
+        for_version,
+        &project_info.project_name,
+        &scopes,
+        &mut error_list,
+    );
+    let conflicts = find_assembly_conflicts(
+        &project_info.project_name,
+        &payload_assemblies,
+        &installed_assemblies,
+    );
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&conflicts).unwrap());
+        return conflicts.is_empty();
+    }
+    for warning in error_list.view_warnings() {
+        println!("Warning: {}", warning);
+    }
+    for conflict in &conflicts {
+        println!("{}:", conflict.assembly_name);
+        for shipped in &conflict.shipped {
+            println!(
+                "  {:<16} {} ({})",
+                shipped.version.to_string(),
+                shipped.addin,
+                shipped.path.to_string_lossy()
+            );
+        }
+    }
+    if conflicts.is_empty() {
+        println!(
+            "No assembly version conflicts found for Revit {}",
+            for_version
+        );
+    } else {
+        println!(
+            "Found {} assemblies shipped in different versions. Revit only loads the first version of each assembly, so the add-ins that ship another version may fail to load or call missing methods",
+            conflicts.len()
+        );
+    }
+    conflicts.is_empty()
+}
+
+/// Returns the DLLs that exporting the project would install: the main DLL, its dependencies and
+/// the DLLs matched by the project config
+pub fn get_payload_assemblies(
+    csproj_path: &Path,
+    main_dll_path: &Path,
+    export_config: &project_config::ExportConfig,
+    error_list: &mut ErrorList,
+) -> Vec<PathBuf> {
+    let mut assemblies = vec![main_dll_path.to_path_buf()];
+    let output_dir = main_dll_path.parent().unwrap_or(Path::new(""));
+    let mut payload_files =
+        dependencies::resolve_dependencies(csproj_path, main_dll_path, None, error_list);
+    match payload::collect_payload_files(output_dir, export_config) {
+        Ok(files) => payload_files.extend(files),
+        Err(e) => error_list.add_warning(&format!("Error collecting files to export: {}", e)),
+    }
+    for payload_file in payload_files {
+        if is_dll(&payload_file.source) && !assemblies.contains(&payload_file.source) {
+            assemblies.push(payload_file.source);
+        }
+    }
+    assemblies
+}
+
+/// Returns every DLL shipped by the addins installed for the Revit version, paired with the
+/// name of the manifest that loads it. `exclude_addin` is the manifest name of the addin being
+/// exported, whose installed copy would be replaced
+///
+/// Addins with their own folder ship everything in it. Addins whose assembly sits directly in
+/// the version directory only ship that assembly
+pub fn get_installed_assemblies(
+    revit_version: &str,
+    exclude_addin: &str,
+    scopes: &[InstallScope],
+    error_list: &mut ErrorList,
+) -> Vec<(String, PathBuf)> {
+    let mut seen = HashSet::new();
+    let mut assemblies = Vec::new();
+    for scope in scopes {
+        for addin in list::find_installed_addins(scope, error_list) {
+            let manifest_name = addin
+                .manifest_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            if addin.revit_version != revit_version
+                || manifest_name.eq_ignore_ascii_case(exclude_addin)
+                || !addin.assembly_exists
+            {
+                continue;
+            }
+            let Some(addin_dir) = addin.assembly_path.parent() else {
+                continue;
+            };
+            let dlls = if Some(addin_dir) == addin.manifest_path.parent() {
+                vec![addin.assembly_path.clone()]
+            } else {
+                utils::get_all_files_in_directory(addin_dir)
+                    .into_iter()
+                    .filter(|path| is_dll(path))
+                    .collect()
+            };
+            for dll in dlls {
+                if seen.insert((manifest_name.clone(), dll.clone())) {
+                    assemblies.push((manifest_name.clone(), dll));
+                }
+            }
+        }
+    }
+    assemblies
+}
+
+/// Compares the assembly versions in the export payload against the installed assemblies.
+/// Files that are not .NET assemblies and assemblies that ship with Revit are ignored
+pub fn find_assembly_conflicts(
+    addin_name: &str,
+    payload_assemblies: &[PathBuf],
+    installed_assemblies: &[(String, PathBuf)],
+) -> Vec<AssemblyConflict> {
+    // Keyed by the lowercase assembly name, since .NET compares assembly names regardless of case
+    let mut shipped_by_name: BTreeMap<String, (String, Vec<ShippedAssembly>)> = BTreeMap::new();
+    for path in payload_assemblies {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if revit_assemblies::is_revit_assembly(&file_name, None) {
+            continue;
+        }
+        if let Ok(metadata) = assembly_metadata::read_assembly_metadata(path) {
+            shipped_by_name
+                .entry(metadata.name.to_lowercase())
+                .or_insert_with(|| (metadata.name.clone(), Vec::new()))
+                .1
+                .push(ShippedAssembly {
+                    addin: addin_name.to_string(),
+                    version: metadata.version,
+                    path: path.clone(),
+                });
+        }
+    }
+
+    for (addin, path) in installed_assemblies {
+        // Assemblies are almost always named after their file, which saves reading every DLL
+        let file_stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        if !shipped_by_name.contains_key(&file_stem) {
+            continue;
+        }
+        if let Ok(metadata) = assembly_metadata::read_assembly_metadata(path)
+            && let Some((_, shipped)) = shipped_by_name.get_mut(&metadata.name.to_lowercase())
+        {
+            shipped.push(ShippedAssembly {
+                addin: addin.clone(),
+                version: metadata.version,
+                path: path.clone(),
+            });
+        }
+    }
+
+    shipped_by_name
+        .into_values()
+        .filter(|(_, shipped)| {
+            shipped
+                .iter()
+                .any(|other| other.version != shipped[0].version)
+        })
+        .map(|(assembly_name, shipped)| AssemblyConflict {
+            assembly_name,
+            shipped,
+        })
+        .collect()
+}
+
+fn is_dll(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("dll"))
+}
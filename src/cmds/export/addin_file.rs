@@ -1,38 +1,39 @@
-use crate::state;
-use crate::utils;
+use crate::cmds::locate::ProjectInfo;
+use crate::error::{ManifestError, ManifestErrorKind};
+use crate::state::{self, VendorProfile};
+use crate::utils::assembly_metadata::{self, AssemblyMetadata};
+use crate::utils::error_list::ErrorList;
 use crate::utils::input::{prompt_user, prompt_user_with_default};
 use serde::{Deserialize, Serialize};
 use std::{
     fs::File,
     io::{BufWriter, Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-pub fn handle_addin_file(starting_dir: &str) -> Result<String, String> {
-    let csproj_path = utils::recursively_check_for_file(
-        starting_dir,
-        "*.csproj",
-        3,
-        utils::SearchDirection::Child,
-    );
-    if let Some(csproj_path) = csproj_path {
-        let parent_dir = Path::new(&csproj_path).parent().unwrap();
-        let project_name = Path::new(&csproj_path)
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap();
-        let project_name = project_name.strip_suffix(".csproj").unwrap();
-
-        let addin_file_path = parent_dir.join(format!("{}.addin", project_name));
-        if is_addin_file_a_template_or_missing(&addin_file_path) {
-            let addin_info = prompt_user_for_addin_file_info(project_name);
-            create_addin_file(&addin_file_path, addin_info).map_err(|e| e.to_string())?;
-        }
-        Ok(addin_file_path.to_string_lossy().into_owned())
-    } else {
-        Err("No csproj file found".to_string())
+/// Returns the path to the project's '.addin' file, asking for its details and writing it first
+/// if it is missing or still the template. `main_dll_path` is the built DLL, whose classes are
+/// offered as entry points
+pub async fn handle_addin_file(
+    project_info: &ProjectInfo,
+    main_dll_path: Option<&Path>,
+    profile: Option<&VendorProfile>,
+) -> Result<String, String> {
+    let addin_file_path =
+        Path::new(&project_info.csproj_dir).join(format!("{}.addin", project_info.project_name));
+    if is_addin_file_a_template_or_missing(&addin_file_path) {
+        let detected_entry_points = find_entry_points_in_built_dll(main_dll_path);
+        let (addin_info, entry_points) = prompt_user_for_addin_file_info(
+            &project_info.project_name,
+            &detected_entry_points,
+            profile,
+        )
+        .await;
+        write_addin_file(&addin_file_path, &addin_info, &entry_points)
+            .map_err(|e| e.to_string())?;
     }
+    Ok(addin_file_path.to_string_lossy().into_owned())
 }
 
 /// Returns true if the addin file contains template information or does not exist
@@ -42,43 +43,242 @@ pub fn is_addin_file_a_template_or_missing(path: &Path) -> bool {
         Err(_) => return true,
     };
     let mut contents = String::new();
-    addin_file.read_to_string(&mut contents).unwrap();
+    if addin_file.read_to_string(&mut contents).is_err() {
+        return false;
+    }
 
     contents.contains("Template Plugin")
         || contents.contains("youremail@example.com")
         || contents.contains("Insert description here")
 }
 
-fn prompt_user_for_addin_file_info(project_name: &str) -> AddinFileInfo {
+/// The interfaces that Revit can load, and the `Type` of the manifest entry that registers each
+pub const REVIT_ADDIN_INTERFACES: [(&str, &str); 3] = [
+    ("Autodesk.Revit.UI.IExternalApplication", "Application"),
+    ("Autodesk.Revit.UI.IExternalCommand", "Command"),
+    ("Autodesk.Revit.DB.IExternalDBApplication", "DBApplication"),
+];
+
+/// A class in the built assembly that Revit can load
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddinEntryPoint {
+    /// Either "Application", "Command" or "DBApplication"
+    pub addin_type: String,
+    pub full_class_name: String,
+}
+
+/// Returns every public, non-abstract type that implements one of the Revit addin interfaces
+pub fn find_addin_entry_points(metadata: &AssemblyMetadata) -> Vec<AddinEntryPoint> {
+    let mut entry_points = Vec::new();
+    for assembly_type in &metadata.types {
+        if !assembly_type.is_public || assembly_type.is_abstract {
+            continue;
+        }
+        for (interface, addin_type) in REVIT_ADDIN_INTERFACES {
+            if assembly_type.implements(interface) {
+                entry_points.push(AddinEntryPoint {
+                    addin_type: addin_type.to_string(),
+                    full_class_name: assembly_type.full_name.clone(),
+                });
+            }
+        }
+    }
+    entry_points
+}
+
+/// Returns the entry points of the project's DLL if it has already been built
+fn find_entry_points_in_built_dll(main_dll_path: Option<&Path>) -> Vec<AddinEntryPoint> {
+    main_dll_path
+        .and_then(|dll_path| assembly_metadata::read_assembly_metadata(dll_path).ok())
+        .map(|metadata| find_addin_entry_points(&metadata))
+        .unwrap_or_default()
+}
+
+/// Checks that every class the manifest registers from the assembly exists, can be created by
+/// Revit and implements the interface for the `Type` of its entry. A class that derives from a
+/// class in another assembly may implement the interface there, so only a warning is given for it
+pub fn check_manifest_classes(addin_file_path: &Path, metadata: &AssemblyMetadata) -> ErrorList {
+    let mut error_list = ErrorList::new();
+    let entries = match get_addin_manifest_entries(&addin_file_path.to_string_lossy()) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error_list.add_warning(&e.to_string());
+            return error_list;
+        }
+    };
+
+    let entry_points = find_addin_entry_points(metadata);
+    for entry in entries {
+        // Only check entries that load this assembly
+        let assembly_file_name = entry
+            .assembly
+            .rsplit(['\\', '/'])
+            .next()
+            .unwrap_or_default();
+        if !assembly_file_name.eq_ignore_ascii_case(&format!("{}.dll", metadata.name)) {
+            continue;
+        }
+        let Some(assembly_type) = metadata.get_type(&entry.full_class_name) else {
+            let found = entry_points
+                .iter()
+                .filter(|entry_point| entry_point.addin_type == entry.addin_type)
+                .map(|entry_point| entry_point.full_class_name.as_str())
+                .collect::<Vec<_>>();
+            error_list.add_error(&format!(
+                "The FullClassName {} in {} does not exist in {}.dll{}",
+                entry.full_class_name,
+                addin_file_path.to_string_lossy(),
+                metadata.name,
+                if found.is_empty() {
+                    String::new()
+                } else {
+                    format!(". Did you mean {}?", found.join(" or "))
+                }
+            ));
+            continue;
+        };
+        if !assembly_type.is_public || assembly_type.is_abstract {
+            error_list.add_error(&format!(
+                "{} must be a public, non-abstract class for Revit to create it",
+                entry.full_class_name
+            ));
+        }
+        let interface = REVIT_ADDIN_INTERFACES
+            .iter()
+            .find(|(_, addin_type)| *addin_type == entry.addin_type)
+            .map(|(interface, _)| *interface);
+        if let Some(interface) = interface
+            && !assembly_type.implements(interface)
+        {
+            match &assembly_type.external_base_type {
+                Some(base_type) => error_list.add_warning(&format!(
+                    "{} is registered as Type=\"{}\". It derives from {} in another assembly, so rev cannot check that it implements {}",
+                    entry.full_class_name, entry.addin_type, base_type, interface
+                )),
+                None => error_list.add_error(&format!(
+                    "{} is registered as Type=\"{}\" but does not implement {}",
+                    entry.full_class_name, entry.addin_type, interface
+                )),
+            }
+        }
+    }
+    error_list
+}
+
+/// Asks the user which of the detected classes to register. Falls back to asking for the class
+/// name when the DLL has not been built yet or no classes were found
+fn prompt_user_for_entry_points(
+    project_name: &str,
+    detected: &[AddinEntryPoint],
+) -> Vec<AddinEntryPoint> {
+    if detected.is_empty() {
+        let full_class_name = prompt_user_with_default(
+            "Enter the full class name of your addin",
+            &format!("{}.App", project_name),
+        );
+        return vec![AddinEntryPoint {
+            addin_type: "Application".to_string(),
+            full_class_name,
+        }];
+    }
+
+    println!("Found the following classes that Revit can load:");
+    for (i, entry_point) in detected.iter().enumerate() {
+        println!(
+            "  {}. {} ({})",
+            i + 1,
+            entry_point.full_class_name,
+            entry_point.addin_type
+        );
+    }
+    if detected.len() == 1 {
+        return detected.to_vec();
+    }
+    loop {
+        let selection = prompt_user_with_default(
+            "Enter the numbers of the classes to register, separated by commas",
+            "all",
+        );
+        if selection.eq_ignore_ascii_case("all") {
+            return detected.to_vec();
+        }
+        let selected: Option<Vec<AddinEntryPoint>> = selection
+            .split(',')
+            .map(|number| {
+                let index = number.trim().parse::<usize>().ok()?;
+                detected.get(index.checked_sub(1)?).cloned()
+            })
+            .collect();
+        match selected {
+            Some(selected) if !selected.is_empty() => return selected,
+            _ => println!("Invalid selection"),
+        }
+    }
+}
+
+/// Prompts for the manifest details. The vendor details default to those of `profile`
+async fn prompt_user_for_addin_file_info(
+    project_name: &str,
+    detected_entry_points: &[AddinEntryPoint],
+    profile: Option<&VendorProfile>,
+) -> (AddinFileInfo, Vec<AddinEntryPoint>) {
     let state = state::get_state_or_default();
+    let profile = profile.cloned().unwrap_or_default();
 
     let name = prompt_user("Enter the name of your addin");
     let assembly = format!("{}\\{}.dll", project_name, project_name);
     let addin_id = uuid::Uuid::new_v4().to_string();
-    let full_class_name = format!("{}.App", project_name);
-    let vendor_id = prompt_user_with_default("Enter your vendor ID", "Development");
-    let vendor_description = prompt_user("Enter a description of your add-in");
+    let entry_points = prompt_user_for_entry_points(project_name, detected_entry_points);
+    let full_class_name = entry_points[0].full_class_name.clone();
+    let vendor_id = prompt_user_with_default(
+        "Enter your vendor ID",
+        if profile.vendor_id.is_empty() {
+            "Development"
+        } else {
+            &profile.vendor_id
+        },
+    );
+    let vendor_description = if profile.vendor_description.is_empty() {
+        prompt_user("Enter a description of your add-in")
+    } else {
+        prompt_user_with_default(
+            "Enter a description of your add-in",
+            &profile.vendor_description,
+        )
+    };
 
-    let vendor_email = if state.email_address.is_empty() {
+    // The profile's email is used over the one saved for every project
+    let default_email = if profile.email_address.is_empty() {
+        &state.email_address
+    } else {
+        &profile.email_address
+    };
+    let vendor_email = if default_email.is_empty() {
         prompt_user("Enter your work email address")
     } else {
-        prompt_user_with_default("Enter your work email address", &state.email_address)
+        prompt_user_with_default("Enter your work email address", default_email)
     };
 
-    state::save_state(&state::State {
-        email_address: vendor_email.clone(),
-        ..state
-    });
-
-    AddinFileInfo {
-        name,
-        assembly,
-        addin_id,
-        full_class_name,
-        vendor_id,
-        vendor_description,
-        vendor_email,
+    if profile.email_address.is_empty()
+        && let Err(e) =
+            state::update_state(|state| state.email_address = vendor_email.clone()).await
+    {
+        println!("Warning: could not save the email address: {}", e);
     }
+
+    (
+        AddinFileInfo {
+            name,
+            assembly,
+            addin_id,
+            full_class_name,
+            vendor_id,
+            vendor_description,
+            vendor_email,
+        },
+        entry_points,
+    )
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,17 +292,37 @@ pub struct AddinFileInfo {
     pub vendor_email: String,
 }
 
+/// A single `<AddIn>` entry of a '.addin' manifest. Unlike `AddinFileInfo`, this also covers
+/// commands and manifests that were not generated by this tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddinManifestEntry {
+    /// Either "Application", "Command" or "DBApplication"
+    pub addin_type: String,
+    /// The `Name` of the entry, or the `Text` for commands that do not have a name
+    pub name: String,
+    pub assembly: String,
+    pub addin_id: String,
+    pub full_class_name: String,
+    pub vendor_id: String,
+}
+
 // Wrapper structs for XML deserialization
 #[derive(Debug, Deserialize)]
 struct RevitAddIns {
-    #[serde(rename = "AddIn")]
-    add_in: AddIn,
+    #[serde(rename = "AddIn", default)]
+    add_ins: Vec<AddIn>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 struct AddIn {
+    #[serde(rename = "@Type")]
+    addin_type: String,
     #[serde(rename = "Name")]
     name: String,
+    #[serde(rename = "Text")]
+    text: String,
     #[serde(rename = "Assembly")]
     assembly: String,
     #[serde(rename = "AddInId")]
@@ -117,82 +337,266 @@ struct AddIn {
     vendor_email: String,
 }
 
-#[derive(Debug, Clone)]
-pub enum GetAddinFileInfoError {
-    FileNotFound,
-    FailedToOpenFile(String),
-    FailedToReadFile(String),
-    FailedToParseXml(String),
+pub fn get_addin_file_info(path_to_addin_file: &str) -> Result<AddinFileInfo, ManifestError> {
+    let revit_addins = read_revit_addins(path_to_addin_file)?;
+    let add_in = revit_addins
+        .add_ins
+        .into_iter()
+        .next()
+        .ok_or_else(|| ManifestError {
+            path: PathBuf::from(path_to_addin_file),
+            kind: ManifestErrorKind::NoAddIn,
+        })?;
+
+    // Convert to our AddinFileInfo struct
+    let addin_info = AddinFileInfo {
+        name: add_in.name,
+        assembly: add_in.assembly,
+        addin_id: add_in.addin_id,
+        full_class_name: add_in.full_class_name,
+        vendor_id: add_in.vendor_id,
+        vendor_description: add_in.vendor_description,
+        vendor_email: add_in.vendor_email,
+    };
+
+    Ok(addin_info)
 }
 
-pub fn get_addin_file_info(
+/// Returns every `<AddIn>` entry in the '.addin' file
+pub fn get_addin_manifest_entries(
     path_to_addin_file: &str,
-) -> Result<AddinFileInfo, GetAddinFileInfoError> {
-    if !Path::new(path_to_addin_file).exists() {
-        return Err(GetAddinFileInfoError::FileNotFound);
+) -> Result<Vec<AddinManifestEntry>, ManifestError> {
+    let revit_addins = read_revit_addins(path_to_addin_file)?;
+    Ok(revit_addins
+        .add_ins
+        .into_iter()
+        .map(|add_in| AddinManifestEntry {
+            addin_type: add_in.addin_type,
+            name: if add_in.name.is_empty() {
+                add_in.text
+            } else {
+                add_in.name
+            },
+            assembly: add_in.assembly,
+            addin_id: add_in.addin_id,
+            full_class_name: add_in.full_class_name,
+            vendor_id: add_in.vendor_id,
+        })
+        .collect())
+}
+
+/// Returns the path that Revit will load the assembly from. Relative assembly paths are
+/// resolved against the directory of the '.addin' file
+///
+/// Both '\\' and '/' are accepted as separators so that manifests copied from a Windows
+/// machine can be inspected on any OS
+pub fn resolve_assembly_path(addin_file_path: &Path, assembly: &str) -> PathBuf {
+    let assembly_path: PathBuf = assembly
+        .trim()
+        .split(['\\', '/'])
+        .enumerate()
+        .map(|(i, part)| {
+            // Keep the root of absolute paths such as "C:\" or "/"
+            if i == 0 && (part.is_empty() || part.ends_with(':')) {
+                format!("{}{}", part, std::path::MAIN_SEPARATOR)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect();
+    if assembly_path.is_absolute() {
+        assembly_path
+    } else {
+        addin_file_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(assembly_path)
     }
+}
 
-    let mut file = File::open(path_to_addin_file)
-        .map_err(|e| GetAddinFileInfoError::FailedToOpenFile(e.to_string()))?;
+/// AddInIds are GUIDs, which Revit compares regardless of case and surrounding braces
+pub fn normalize_addin_id(addin_id: &str) -> String {
+    addin_id
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .to_lowercase()
+}
+
+fn read_revit_addins(path_to_addin_file: &str) -> Result<RevitAddIns, ManifestError> {
+    let error = |kind| ManifestError {
+        path: PathBuf::from(path_to_addin_file),
+        kind,
+    };
+    if !Path::new(path_to_addin_file).exists() {
+        return Err(error(ManifestErrorKind::NotFound));
+    }
 
     let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .map_err(|e| GetAddinFileInfoError::FailedToReadFile(e.to_string()))?;
+    File::open(path_to_addin_file)
+        .and_then(|mut file| file.read_to_string(&mut contents))
+        .map_err(|e| error(ManifestErrorKind::Unreadable(Arc::new(e))))?;
 
     // Parse the XML using serde-xml-rs
-    let revit_addins: RevitAddIns = serde_xml_rs::from_str(&contents)
-        .map_err(|e| GetAddinFileInfoError::FailedToParseXml(e.to_string()))?;
-
-    // Convert to our AddinFileInfo struct
-    let addin_info = AddinFileInfo {
-        name: revit_addins.add_in.name,
-        assembly: revit_addins.add_in.assembly,
-        addin_id: revit_addins.add_in.addin_id,
-        full_class_name: revit_addins.add_in.full_class_name,
-        vendor_id: revit_addins.add_in.vendor_id,
-        vendor_description: revit_addins.add_in.vendor_description,
-        vendor_email: revit_addins.add_in.vendor_email,
-    };
+    serde_xml_rs::from_str(&contents)
+        .map_err(|e| error(ManifestErrorKind::InvalidXml(e.to_string())))
+}
 
-    Ok(addin_info)
+/// Fills the vendor details that are empty in `addin_info` from the profile
+pub fn apply_profile(addin_info: &mut AddinFileInfo, profile: &VendorProfile) {
+    for (field, default) in [
+        (&mut addin_info.vendor_id, &profile.vendor_id),
+        (
+            &mut addin_info.vendor_description,
+            &profile.vendor_description,
+        ),
+        (&mut addin_info.vendor_email, &profile.email_address),
+    ] {
+        if field.is_empty() {
+            *field = default.clone();
+        }
+    }
 }
 
 pub fn create_addin_file(path: &Path, addin_info: AddinFileInfo) -> Result<(), std::io::Error> {
+    let entry_point = AddinEntryPoint {
+        addin_type: "Application".to_string(),
+        full_class_name: addin_info.full_class_name.clone(),
+    };
+    write_addin_file(path, &addin_info, &[entry_point])
+}
+
+/// Writes a manifest with an `<AddIn>` entry for each entry point. The first entry uses the
+/// AddInId of `addin_info` and the others are given new ones
+pub fn write_addin_file(
+    path: &Path,
+    addin_info: &AddinFileInfo,
+    entry_points: &[AddinEntryPoint],
+) -> Result<(), std::io::Error> {
     let addin_file = File::create(path)?;
     let mut addin_file = BufWriter::new(addin_file);
 
     writeln!(addin_file, "<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
     writeln!(addin_file, "<RevitAddIns>")?;
-    writeln!(addin_file, "\t<AddIn Type=\"Application\">")?;
-    writeln!(addin_file, "\t\t<Name>{}</Name>", addin_info.name)?;
-    writeln!(
-        addin_file,
-        "\t\t<Assembly>{}</Assembly>",
-        addin_info.assembly
-    )?;
-    writeln!(addin_file, "\t\t<AddInId>{}</AddInId>", addin_info.addin_id)?;
-    writeln!(
-        addin_file,
-        "\t\t<FullClassName>{}</FullClassName>",
-        addin_info.full_class_name
-    )?;
-    writeln!(
-        addin_file,
-        "\t\t<VendorId>{}</VendorId>",
-        addin_info.vendor_id
-    )?;
-    writeln!(
-        addin_file,
-        "\t\t<VendorDescription>{}</VendorDescription>",
-        addin_info.vendor_description
-    )?;
-    writeln!(
-        addin_file,
-        "\t\t<VendorEmail>{}</VendorEmail>",
-        addin_info.vendor_email
-    )?;
-    writeln!(addin_file, "\t</AddIn>")?;
+    for (i, entry_point) in entry_points.iter().enumerate() {
+        let addin_id = if i == 0 {
+            addin_info.addin_id.clone()
+        } else {
+            uuid::Uuid::new_v4().to_string()
+        };
+        writeln!(addin_file, "\t<AddIn Type=\"{}\">", entry_point.addin_type)?;
+        // Commands are shown in the External Tools menu using their Text instead of a Name
+        if entry_point.addin_type == "Command" {
+            let class_name = entry_point
+                .full_class_name
+                .rsplit(['.', '+'])
+                .next()
+                .unwrap_or_default();
+            writeln!(addin_file, "\t\t<Text>{}</Text>", class_name)?;
+        } else {
+            writeln!(addin_file, "\t\t<Name>{}</Name>", addin_info.name)?;
+        }
+        writeln!(
+            addin_file,
+            "\t\t<Assembly>{}</Assembly>",
+            addin_info.assembly
+        )?;
+        writeln!(addin_file, "\t\t<AddInId>{}</AddInId>", addin_id)?;
+        writeln!(
+            addin_file,
+            "\t\t<FullClassName>{}</FullClassName>",
+            entry_point.full_class_name
+        )?;
+        writeln!(
+            addin_file,
+            "\t\t<VendorId>{}</VendorId>",
+            addin_info.vendor_id
+        )?;
+        writeln!(
+            addin_file,
+            "\t\t<VendorDescription>{}</VendorDescription>",
+            addin_info.vendor_description
+        )?;
+        writeln!(
+            addin_file,
+            "\t\t<VendorEmail>{}</VendorEmail>",
+            addin_info.vendor_email
+        )?;
+        writeln!(addin_file, "\t</AddIn>")?;
+    }
     writeln!(addin_file, "</RevitAddIns>")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::assembly_metadata::{AssemblyType, AssemblyVersion};
+
+    fn metadata_with_class(external_base_type: Option<&str>) -> AssemblyMetadata {
+        AssemblyMetadata {
+            name: "MyAddin".to_string(),
+            version: AssemblyVersion {
+                major: 1,
+                minor: 0,
+                build: 0,
+                revision: 0,
+            },
+            target_framework: None,
+            references: Vec::new(),
+            types: vec![AssemblyType {
+                full_name: "MyAddin.Foo".to_string(),
+                is_public: true,
+                is_abstract: false,
+                interfaces: Vec::new(),
+                external_base_type: external_base_type.map(str::to_string),
+            }],
+        }
+    }
+
+    fn write_command_manifest(dir: &Path) -> PathBuf {
+        let addin_file_path = dir.join("MyAddin.addin");
+        let addin_info = AddinFileInfo {
+            name: "MyAddin".to_string(),
+            assembly: "MyAddin/MyAddin.dll".to_string(),
+            addin_id: uuid::Uuid::new_v4().to_string(),
+            full_class_name: "MyAddin.Foo".to_string(),
+            vendor_id: "ACME".to_string(),
+            vendor_description: "ACME".to_string(),
+            vendor_email: "info@example.com".to_string(),
+        };
+        let entry_point = AddinEntryPoint {
+            addin_type: "Command".to_string(),
+            full_class_name: "MyAddin.Foo".to_string(),
+        };
+        write_addin_file(&addin_file_path, &addin_info, &[entry_point]).unwrap();
+        addin_file_path
+    }
+
+    #[test]
+    fn rejects_class_without_the_interface() {
+        let dir = tempfile::tempdir().unwrap();
+        let addin_file_path = write_command_manifest(dir.path());
+        let errors = check_manifest_classes(&addin_file_path, &metadata_with_class(None));
+        assert_eq!(
+            errors.view_errors(),
+            [
+                "MyAddin.Foo is registered as Type=\"Command\" but does not implement Autodesk.Revit.UI.IExternalCommand"
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_class_with_base_type_in_another_assembly() {
+        let dir = tempfile::tempdir().unwrap();
+        let addin_file_path = write_command_manifest(dir.path());
+        let errors = check_manifest_classes(
+            &addin_file_path,
+            &metadata_with_class(Some("CompanyLib.CommandBase")),
+        );
+        assert!(!errors.has_errors());
+        assert_eq!(errors.view_warnings().len(), 1);
+        assert!(errors.view_warnings()[0].contains("CompanyLib.CommandBase"));
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use path_absolutize::Absolutize;
+use serde_json::Value;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::cmds::export::payload::PayloadFile;
+use crate::cmds::export::revit_assemblies;
+use crate::utils::error_list::ErrorList;
+
+/// Returns the files from the build output directory that the addin needs at runtime, not
+/// including the main DLL itself or any assembly that Revit provides. `revit_version` is the
+/// year of the Revit version being exported to, or None to leave out what any version provides.
+///
+/// SDK-style projects are resolved from the `<Assembly>.deps.json` file written by the build.
+/// Older projects are resolved from the copy-local outputs recorded by MSBuild and the
+/// references in the csproj file. Dependencies that cannot be found are added as warnings
+pub fn resolve_dependencies(
+    csproj_path: &Path,
+    main_dll_path: &Path,
+    revit_version: Option<u32>,
+    error_list: &mut ErrorList,
+) -> Vec<PayloadFile> {
+    let output_dir = main_dll_path.parent().unwrap_or(Path::new(""));
+    let assembly_name = main_dll_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+
+    let deps_json_path = output_dir.join(format!("{}.deps.json", assembly_name));
+    let relative_paths = if deps_json_path.exists() {
+        match get_deps_json_dependencies(&deps_json_path, &assembly_name) {
+            Ok(relative_paths) => relative_paths,
+            Err(e) => {
+                error_list.add_warning(&format!(
+                    "Could not read {}: {}",
+                    deps_json_path.to_string_lossy(),
+                    e
+                ));
+                Vec::new()
+            }
+        }
+    } else {
+        let mut relative_paths = get_copy_local_outputs(csproj_path, output_dir);
+        relative_paths.extend(get_csproj_references(csproj_path));
+        relative_paths
+    };
+
+    let main_dll_name = main_dll_path.file_name().unwrap_or_default();
+    let mut seen = HashSet::new();
+    let mut dependencies = Vec::new();
+    for relative in relative_paths {
+        let file_name = relative.file_name().unwrap_or_default();
+        if file_name == main_dll_name
+            || revit_assemblies::is_revit_assembly(&file_name.to_string_lossy(), revit_version)
+            || !seen.insert(relative.clone())
+        {
+            continue;
+        }
+        let source = output_dir.join(&relative);
+        if source.is_file() {
+            dependencies.push(PayloadFile { source, relative });
+        } else {
+            error_list.add_warning(&format!(
+                "Dependency {} was not found in the build output",
+                relative.to_string_lossy()
+            ));
+        }
+    }
+    dependencies.sort_by(|a, b| a.relative.cmp(&b.relative));
+    dependencies
+}
+
+/// Walks the dependency graph in the deps.json file starting from the addin's own library and
+/// returns the output-relative path of every runtime asset it needs
+fn get_deps_json_dependencies(
+    deps_json_path: &Path,
+    assembly_name: &str,
+) -> Result<Vec<PathBuf>, String> {
+    let deps_file = File::open(deps_json_path).map_err(|e| e.to_string())?;
+    let deps: Value =
+        serde_json::from_reader(BufReader::new(deps_file)).map_err(|e| e.to_string())?;
+
+    let targets = deps["targets"]
+        .as_object()
+        .ok_or("The file does not contain any targets")?;
+    let target = deps["runtimeTarget"]["name"]
+        .as_str()
+        .and_then(|name| targets.get(name))
+        .or_else(|| targets.values().next())
+        .and_then(Value::as_object)
+        .ok_or("The file does not contain any targets")?;
+
+    // Libraries are keyed by "<name>/<version>"
+    let root = target
+        .keys()
+        .find(|key| {
+            key.split('/')
+                .next()
+                .is_some_and(|name| name.eq_ignore_ascii_case(assembly_name))
+        })
+        .ok_or_else(|| format!("{} is not listed as a library", assembly_name))?;
+
+    let mut relative_paths = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([root.clone()]);
+    while let Some(key) = queue.pop_front() {
+        if !visited.insert(key.clone()) {
+            continue;
+        }
+        let Some(library) = target.get(&key) else {
+            continue;
+        };
+        if let Some(dependencies) = library["dependencies"].as_object() {
+            for (name, version) in dependencies {
+                if let Some(version) = version.as_str() {
+                    queue.push_back(format!("{}/{}", name, version));
+                }
+            }
+        }
+        if &key == root {
+            continue;
+        }
+        // Managed assemblies are copied to the root of the output directory
+        if let Some(runtime) = library["runtime"].as_object() {
+            for asset in runtime.keys() {
+                if let Some(file_name) = Path::new(asset).file_name() {
+                    relative_paths.push(PathBuf::from(file_name));
+                }
+            }
+        }
+        // Native and RID-specific assemblies keep their 'runtimes/<rid>/...' path
+        if let Some(runtime_targets) = library["runtimeTargets"].as_object() {
+            for asset in runtime_targets.keys() {
+                relative_paths.push(PathBuf::from(asset));
+            }
+        }
+        // Satellite assemblies are copied into a folder named after their culture
+        if let Some(resources) = library["resources"].as_object() {
+            for (asset, details) in resources {
+                if let (Some(locale), Some(file_name)) =
+                    (details["locale"].as_str(), Path::new(asset).file_name())
+                {
+                    relative_paths.push(Path::new(locale).join(file_name));
+                }
+            }
+        }
+    }
+    Ok(relative_paths)
+}
+
+/// Returns the DLLs that MSBuild copied into the output directory, as recorded in the
+/// '<Project>.csproj.FileListAbsolute.txt' file of the build that wrote it. That file is in the
+/// folder under 'obj' that matches the output directory under 'bin', e.g. 'obj/Debug/net48' for
+/// 'bin/Debug/net48', so the lists of other configurations and frameworks are never read
+fn get_copy_local_outputs(csproj_path: &Path, output_dir: &Path) -> Vec<PathBuf> {
+    let Some(csproj_dir) = csproj_path.parent().and_then(|dir| dir.absolutize().ok()) else {
+        return Vec::new();
+    };
+    // An output directory outside of 'bin' has no matching folder under 'obj'
+    let Ok(build_dir) = output_dir.strip_prefix(csproj_dir.join("bin")) else {
+        return Vec::new();
+    };
+    let file_list_path = csproj_dir.join("obj").join(build_dir).join(format!(
+        "{}.FileListAbsolute.txt",
+        csproj_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    ));
+    let Ok(file_list) = std::fs::read_to_string(file_list_path) else {
+        return Vec::new();
+    };
+
+    file_list
+        .lines()
+        .map(|line| PathBuf::from(line.trim()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("dll"))
+        })
+        .filter_map(|path| {
+            path.strip_prefix(output_dir)
+                .ok()
+                .map(|relative| relative.to_path_buf())
+        })
+        .collect()
+}
+
+/// Returns the file names of the assemblies referenced by the csproj file that are copied to
+/// the output directory. References with `<Private>false</Private>` are skipped, as are
+/// framework references that have no `<HintPath>`
+fn get_csproj_references(csproj_path: &Path) -> Vec<PathBuf> {
+    let Ok(csproj_file) = File::open(csproj_path) else {
+        return Vec::new();
+    };
+
+    let mut references = Vec::new();
+    // The reference currently being read: its file name and whether it is copied locally
+    let mut current: Option<(Option<PathBuf>, bool)> = None;
+    let mut current_element = String::new();
+    for event in EventReader::new(BufReader::new(csproj_file))
+        .into_iter()
+        .flatten()
+    {
+        match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let include = attributes
+                    .iter()
+                    .find(|attribute| attribute.name.local_name == "Include")
+                    .map(|attribute| attribute.value.replace('\\', "/"));
+                match (name.local_name.as_str(), include) {
+                    ("Reference", Some(_)) => current = Some((None, true)),
+                    ("ProjectReference", Some(include)) => {
+                        let file_name = Path::new(&include)
+                            .file_stem()
+                            .map(|stem| PathBuf::from(format!("{}.dll", stem.to_string_lossy())));
+                        current = Some((file_name, true));
+                    }
+                    _ => (),
+                }
+                current_element = name.local_name;
+            }
+            XmlEvent::Characters(text) => {
+                if let Some((file_name, copy_local)) = current.as_mut() {
+                    match current_element.as_str() {
+                        "HintPath" => {
+                            *file_name = Path::new(&text.trim().replace('\\', "/"))
+                                .file_name()
+                                .map(PathBuf::from)
+                        }
+                        "Private" => *copy_local = !text.trim().eq_ignore_ascii_case("false"),
+                        _ => (),
+                    }
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                if (name.local_name == "Reference" || name.local_name == "ProjectReference")
+                    && let Some((Some(file_name), true)) = current.take()
+                {
+                    references.push(file_name);
+                }
+                current_element.clear();
+            }
+            _ => (),
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_file_list_of_the_exported_build() {
+        let project_dir = tempfile::tempdir().unwrap();
+        let csproj_path = project_dir.path().join("MyAddin.csproj");
+        let bin_dir = project_dir.path().join("bin");
+        let write_file_list = |build_dir: &str, dll: &str| {
+            let obj_dir = project_dir.path().join("obj").join(build_dir);
+            std::fs::create_dir_all(&obj_dir).unwrap();
+            let output_dir = bin_dir.join(build_dir);
+            let file_list = format!(
+                "{}\n{}\n{}\n",
+                output_dir.join("MyAddin.dll").to_string_lossy(),
+                output_dir.join(dll).to_string_lossy(),
+                obj_dir.join("MyAddin.pdb").to_string_lossy()
+            );
+            std::fs::write(
+                obj_dir.join("MyAddin.csproj.FileListAbsolute.txt"),
+                file_list,
+            )
+            .unwrap();
+        };
+        write_file_list("Debug/net48", "Debug.dll");
+        write_file_list("Release/net48", "Release.dll");
+        write_file_list("Release/net8.0-windows", "Net8.dll");
+
+        let outputs = get_copy_local_outputs(&csproj_path, &bin_dir.join("Release/net48"));
+        assert_eq!(
+            outputs,
+            vec![PathBuf::from("MyAddin.dll"), PathBuf::from("Release.dll")]
+        );
+        let outputs = get_copy_local_outputs(&csproj_path, &bin_dir.join("Release/net8.0-windows"));
+        assert_eq!(
+            outputs,
+            vec![PathBuf::from("MyAddin.dll"), PathBuf::from("Net8.dll")]
+        );
+        assert!(
+            get_copy_local_outputs(&csproj_path, &bin_dir.join("Debug/net8.0-windows")).is_empty()
+        );
+        assert!(get_copy_local_outputs(&csproj_path, &project_dir.path().join("out")).is_empty());
+    }
+}
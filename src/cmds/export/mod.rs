@@ -1,8 +1,25 @@
 pub mod addin_file;
+pub mod dependencies;
+pub mod payload;
+pub mod receipt;
+pub mod revit_assemblies;
+pub mod rollback;
+pub mod scope;
 pub mod web_app;
+use crate::cancel::CancellationToken;
 use crate::cmds::build;
-use crate::cmds::locate;
+use crate::cmds::check;
+use crate::cmds::locate::{self, ProjectInfo};
+use crate::cmds::profile;
+use crate::error::{DiscoveryError, RevError};
+use crate::events::{ConsoleSink, Event, EventSink, Phase};
+use crate::external_cmds::git;
+use crate::project_config::{self, ProjectConfig};
+use crate::state::VendorProfile;
+use crate::utils::assembly_metadata;
 use crate::utils::error_list::ErrorList;
+use rollback::Rollback;
+use scope::InstallScope;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -11,11 +28,44 @@ use std::path::PathBuf;
 /// This function will:
 /// - Build the project
 /// - Copy the DLLs to the addin directory
+/// - Build the web apps, if there are any, and copy them into the addin directory
 /// - Copy the .addin file to the Revit addins directory
 /// - Print out the path to the addin
-pub async fn execute_auto(starting_dir: &str, for_version: &str, extra_dlls: &[&str]) {
-    let destination_dir = get_revit_addins_path(for_version).unwrap();
-    let errors = execute(starting_dir, extra_dlls, &destination_dir).await;
+///
+/// When `cancel` is cancelled, the build tools are stopped and the files already copied are removed
+pub async fn execute_auto(
+    starting_dir: &str,
+    for_version: &str,
+    extra_dlls: &[&str],
+    scope: &InstallScope,
+    profile: Option<&str>,
+    cancel: &CancellationToken,
+) {
+    let destination_dir = match scope.create_addins_path(for_version) {
+        Ok(destination_dir) => destination_dir,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    let mut errors = execute(
+        starting_dir,
+        extra_dlls,
+        &destination_dir,
+        profile,
+        &ConsoleSink,
+        cancel,
+    )
+    .await;
+    if let Ok(project_info) = locate::get_project_info(starting_dir) {
+        for other in scope::find_other_installations(&project_info.project_name, for_version, scope)
+        {
+            errors.add_warning(&format!(
+                "{} is also installed in the {} scope for Revit {}. Revit loads the machine scope first, so only one of them will be loaded",
+                project_info.project_name, other, for_version
+            ));
+        }
+    }
     if errors.has_errors() {
         println!(
             "Build failed with {} errors and {} warnings",
@@ -31,29 +81,149 @@ pub async fn execute_auto(starting_dir: &str, for_version: &str, extra_dlls: &[&
     }
 }
 
-pub async fn execute(starting_dir: &str, extra_dlls: &[&str], destination_dir: &Path) -> ErrorList {
-    let mut dlls_to_export = Vec::new();
-    let project_info = locate::get_project_info(starting_dir);
+/// Builds the project in `starting_dir` and exports it to `destination_dir`
+///
+/// `profile` selects the vendor profile that pre-fills a new '.addin' file. The profile in the
+/// project config is used when it is None. A profile that does not exist fails the export before
+/// anything is built. Progress is reported to `events`, and the export
+/// is stopped and rolled back when `cancel` is cancelled
+pub async fn execute(
+    starting_dir: &str,
+    extra_dlls: &[&str],
+    destination_dir: &Path,
+    profile: Option<&str>,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> ErrorList {
     let mut error_list = ErrorList::new();
-    if let Ok(ref project_info) = project_info {
-        dlls_to_export.push(project_info.project_name.clone() + ".dll");
-    } else {
-        error_list.add_error(
-            "Error getting project info. Ensure you have a .csproj file in the current directory.",
-        );
-        return error_list;
-    }
-    match locate::get_main_dll_path(true, starting_dir) {
-        Ok(dll_path) => {
-            dlls_to_export.push(dll_path);
+    events.emit(Event::PhaseStarted(Phase::Discover));
+    let project_info = locate::get_project_info(starting_dir);
+    events.emit(Event::PhaseFinished {
+        phase: Phase::Discover,
+        success: project_info.is_ok(),
+    });
+    let project_info = match project_info {
+        Ok(project_info) => project_info,
+        Err(_) => {
+            error_list.add_typed_error(
+                DiscoveryError::Project {
+                    starting_dir: PathBuf::from(starting_dir),
+                }
+                .into(),
+            );
+            return error_list;
         }
+    };
+    let project_config = match project_config::get_project_config(&project_info.csproj_dir) {
+        Ok(project_config) => project_config,
         Err(e) => {
-            error_list.add_error(&format!("Error getting project DLL path: {}", e));
+            error_list.add_typed_error(e);
+            return error_list;
+        }
+    };
+    let profile = match profile::get_project_profile(&project_config, profile) {
+        Ok(profile) => profile.map(|(_, profile)| profile),
+        Err(e) => {
+            error_list.add_typed_error(e);
+            return error_list;
+        }
+    };
+    // Build before handling the .addin file so that its classes can be detected from the DLL
+    let csproj_path = Path::new(&project_info.csproj_dir).join(&project_info.full_project_name);
+    if let Err(e) = build::build_csproj(
+        &csproj_path.to_string_lossy(),
+        &project_config,
+        events,
+        cancel,
+    )
+    .await
+    {
+        // A build that was stopped, including by its own timeout, leaves the DLL from the
+        // previous build in place, which must not be exported
+        let stopped = matches!(e, RevError::TimedOut { .. } | RevError::Cancelled);
+        error_list.add_typed_error(e);
+        if stopped {
             return error_list;
         }
     }
+    let main_dll_path =
+        match locate::find_project_dll(true, &project_info.project_name, &project_info) {
+            Ok(dll_path) => PathBuf::from(dll_path),
+            Err(_) => {
+                error_list.add_typed_error(
+                    DiscoveryError::BuildOutput {
+                        dll_name: format!("{}.dll", project_info.project_name),
+                        csproj_dir: PathBuf::from(&project_info.csproj_dir),
+                    }
+                    .into(),
+                );
+                return error_list;
+            }
+        };
+    let project = BuiltProject {
+        info: &project_info,
+        config: &project_config,
+        main_dll_path: &main_dll_path,
+    };
+    error_list.extend(
+        &export_project(
+            starting_dir,
+            &project,
+            extra_dlls,
+            destination_dir,
+            profile.as_ref(),
+            events,
+            cancel,
+        )
+        .await,
+    );
+    error_list
+}
+
+/// A project that has been found and built, with the settings it is exported with
+pub struct BuiltProject<'a> {
+    pub info: &'a ProjectInfo,
+    pub config: &'a ProjectConfig,
+    /// The absolute path of the built DLL
+    pub main_dll_path: &'a Path,
+}
+
+/// Exports a project that has already been found and built to `destination_dir`
+///
+/// `starting_dir` is where web apps are searched for when none are configured, and `profile`
+/// pre-fills a new '.addin' file. When `cancel` is cancelled, or the export timeout in rev.json passes, everything the export wrote to
+/// `destination_dir` is rolled back
+pub async fn export_project(
+    starting_dir: &str,
+    project: &BuiltProject<'_>,
+    extra_dlls: &[&str],
+    destination_dir: &Path,
+    profile: Option<&VendorProfile>,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> ErrorList {
+    let mut error_list = ErrorList::new();
+    let BuiltProject {
+        info: project_info,
+        config: project_config,
+        main_dll_path,
+    } = *project;
+    let mut dlls_to_export = vec![
+        project_info.project_name.clone() + ".dll",
+        main_dll_path.to_string_lossy().into_owned(),
+    ];
+    let cancel = &cancel.with_timeout("export", project_config.timeouts.export_timeout());
+    // Assemblies are left out by what the Revit version being exported to ships
+    let revit_version = revit_assemblies::get_revit_version_of_addins_dir(destination_dir);
     for dll in extra_dlls.iter() {
-        match locate::get_project_dll_path(true, dll.to_string(), starting_dir) {
+        if revit_assemblies::is_revit_assembly(&format!("{}.dll", dll), revit_version) {
+            error_list.add_warning(&format!(
+                "{} ships with Revit and will not be exported",
+                dll
+            ));
+            continue;
+        }
+        match locate::find_project_dll(true, dll, project_info) {
             Ok(dll_path) => {
                 dlls_to_export.push(dll_path);
             }
@@ -67,37 +237,99 @@ pub async fn execute(starting_dir: &str, extra_dlls: &[&str], destination_dir: &
         }
     }
 
-    match addin_file::handle_addin_file(starting_dir) {
+    let finish_phase = |phase: Phase, error_list: &ErrorList| {
+        events.emit(Event::PhaseFinished {
+            phase,
+            success: !error_list.has_errors(),
+        })
+    };
+    events.emit(Event::PhaseStarted(Phase::Manifest));
+    match addin_file::handle_addin_file(project_info, Some(main_dll_path), profile).await {
         Ok(addin_file_path) => {
-            if let Err(e) = build::build_csharp_project(starting_dir).await {
-                error_list.add_error(&format!("Error building project: {}", e));
-            }
             // Clone values to avoid moving them in the loop
-            let project_info_clone = project_info.clone();
             let dlls_to_export_clone = dlls_to_export.clone();
 
-            // Create a new directory for the addin
-            let addin_name = project_info_clone.as_ref().unwrap().project_name.clone();
+            // Revit assemblies in the build output are never exported, but their presence
+            // means the project is set up to copy them, which should be fixed
+            let output_dir = main_dll_path.parent().unwrap();
+
+            // Refuse to install a DLL that the target Revit version would fail to load
+            if let Some(revit_version) = revit_version {
+                let compatibility = check::check_compatibility(main_dll_path, revit_version);
+                error_list.extend(&compatibility);
+                if compatibility.has_errors() {
+                    finish_phase(Phase::Manifest, &error_list);
+                    return error_list;
+                }
+            }
+            if let Ok(metadata) = assembly_metadata::read_assembly_metadata(main_dll_path) {
+                let manifest_classes =
+                    addin_file::check_manifest_classes(Path::new(&addin_file_path), &metadata);
+                error_list.extend(&manifest_classes);
+                if manifest_classes.has_errors() {
+                    finish_phase(Phase::Manifest, &error_list);
+                    return error_list;
+                }
+            }
+            for revit_assembly in
+                revit_assemblies::find_revit_assemblies_in_output(output_dir, revit_version)
+            {
+                error_list.add_warning(&revit_assemblies::get_copy_local_explanation(
+                    &revit_assembly.file_name().unwrap().to_string_lossy(),
+                ));
+            }
+            finish_phase(Phase::Manifest, &error_list);
+            if cancel.is_cancelled() {
+                error_list.add_typed_error(cancel.error());
+                return error_list;
+            }
+
+            // Create a new directory for the addin. Everything written from here on is rolled
+            // back if the export is cancelled
+            events.emit(Event::PhaseStarted(Phase::Copy));
+            let addin_name = project_info.project_name.clone();
             let addin_dir = destination_dir.join(addin_name.clone());
-            std::fs::create_dir_all(&addin_dir).unwrap();
+            let mut rollback = match Rollback::new(destination_dir, &addin_name) {
+                Ok(rollback) => rollback,
+                Err(e) => {
+                    error_list.add_error(&e);
+                    finish_phase(Phase::Copy, &error_list);
+                    return error_list;
+                }
+            };
+            if let Err(e) = rollback.create_dir_all(&addin_dir) {
+                error_list.add_typed_error(RevError::io(&addin_dir, e));
+                finish_phase(Phase::Copy, &error_list);
+                return error_list;
+            }
+            let mut installed_files = Vec::new();
 
             // Copy all DLLs to the addin directory
             for dll_path in &dlls_to_export_clone {
+                if cancel.is_cancelled() {
+                    return roll_back_export(error_list, rollback, cancel, events);
+                }
                 let dll_path = Path::new(&dll_path);
                 if dll_path.exists() {
                     let local_dll_path = dll_path.file_name().unwrap();
                     let target_path = addin_dir.join(local_dll_path);
-                    if let Err(e) = std::fs::copy(dll_path, target_path) {
-                        error_list.add_error(&format!(
-                            "Error copying DLL {}: {}",
-                            local_dll_path.to_string_lossy(),
-                            e
-                        ));
-                    } else {
-                        error_list.add_warning(&format!(
-                            "Successfully copied DLL: {}",
-                            local_dll_path.to_string_lossy()
-                        ));
+                    match rollback
+                        .prepare(&target_path)
+                        .and_then(|_| std::fs::copy(dll_path, &target_path))
+                    {
+                        Ok(bytes) => {
+                            events.emit(Event::FileCopied {
+                                source: dll_path.to_path_buf(),
+                                destination: target_path.clone(),
+                                bytes,
+                            });
+                            installed_files.push(target_path);
+                        }
+                        Err(e) => error_list.add_typed_error(RevError::export_copy(
+                            dll_path,
+                            &target_path,
+                            e,
+                        )),
                     }
                 } else {
                     error_list.add_warning(&format!(
@@ -107,37 +339,177 @@ pub async fn execute(starting_dir: &str, extra_dlls: &[&str], destination_dir: &
                 }
             }
 
+            // Copy every assembly the addin depends on, keeping their folder structure
+            let csproj_path =
+                Path::new(&project_info.csproj_dir).join(&project_info.full_project_name);
+            for dependency in dependencies::resolve_dependencies(
+                &csproj_path,
+                main_dll_path,
+                revit_version,
+                &mut error_list,
+            ) {
+                if cancel.is_cancelled() {
+                    return roll_back_export(error_list, rollback, cancel, events);
+                }
+                if installed_files.contains(&addin_dir.join(&dependency.relative)) {
+                    continue;
+                }
+                match payload::copy_payload_file(&dependency, &addin_dir, &mut rollback, events) {
+                    Ok(target_path) => installed_files.push(target_path),
+                    Err(e) => error_list.add_typed_error(RevError::export_copy(
+                        &dependency.source,
+                        addin_dir.join(&dependency.relative),
+                        e,
+                    )),
+                }
+            }
+
+            // Copy the extra files configured for the project, keeping their folder structure
+            match payload::collect_payload_files(output_dir, &project_config.export) {
+                Ok(payload_files) => {
+                    for payload_file in payload_files {
+                        if cancel.is_cancelled() {
+                            return roll_back_export(error_list, rollback, cancel, events);
+                        }
+                        let file_name = payload_file.relative.file_name().unwrap_or_default();
+                        if installed_files.contains(&addin_dir.join(&payload_file.relative))
+                            || revit_assemblies::is_revit_assembly(
+                                &file_name.to_string_lossy(),
+                                revit_version,
+                            )
+                        {
+                            continue;
+                        }
+                        match payload::copy_payload_file(
+                            &payload_file,
+                            &addin_dir,
+                            &mut rollback,
+                            events,
+                        ) {
+                            Ok(target_path) => installed_files.push(target_path),
+                            Err(e) => error_list.add_typed_error(RevError::export_copy(
+                                &payload_file.source,
+                                addin_dir.join(&payload_file.relative),
+                                e,
+                            )),
+                        }
+                    }
+                }
+                Err(e) => error_list.add_error(&format!("Error collecting files to export: {}", e)),
+            }
+            // Build the web apps and copy their static files into the addin directory. Their
+            // previous exports are kept in the backup until the export is committed
+            let csproj_dir = Path::new(&project_info.csproj_dir);
+            // An invalid web config is reported by export_frontends
+            let frontends = web_app::get_frontends(starting_dir, csproj_dir, &project_config.web)
+                .unwrap_or_default();
+            // Also cancelled along with the export, so checking it covers both
+            let web_build =
+                cancel.with_timeout("webBuild", project_config.timeouts.web_build_timeout());
+            if let Err(e) = web_app::check_destinations(&frontends, &addin_dir, &installed_files) {
+                error_list.add_error(&e);
+            } else {
+                for frontend in frontends {
+                    // A folder that cannot be moved is reported when the web app is copied
+                    let _ = rollback.prepare(&addin_dir.join(&frontend.destination));
+                }
+                installed_files.extend(
+                    web_app::export_frontends(
+                        starting_dir,
+                        csproj_dir,
+                        &addin_dir,
+                        &project_config.web,
+                        &mut error_list,
+                        events,
+                        &web_build,
+                    )
+                    .await,
+                );
+            }
+            if web_build.is_cancelled() {
+                return roll_back_export(error_list, rollback, &web_build, events);
+            }
+
+            error_list.add_warning(&format!(
+                "Exported {} files to {}",
+                installed_files.len(),
+                addin_dir.to_string_lossy()
+            ));
+
             // Copy the .addin file to the Revit addins directory
             let target_addin_file_path = destination_dir.join(format!("{}.addin", addin_name));
-            if let Err(e) = std::fs::copy(&addin_file_path, target_addin_file_path) {
-                error_list.add_error(&format!("Error copying .addin file: {}", e));
-            } else {
-                error_list.add_warning("Successfully copied .addin file");
+            match rollback
+                .prepare(&target_addin_file_path)
+                .and_then(|_| std::fs::copy(&addin_file_path, &target_addin_file_path))
+            {
+                Ok(bytes) => {
+                    events.emit(Event::FileCopied {
+                        source: PathBuf::from(&addin_file_path),
+                        destination: target_addin_file_path.clone(),
+                        bytes,
+                    });
+                    installed_files.push(target_addin_file_path);
+                }
+                Err(e) => error_list.add_typed_error(RevError::export_copy(
+                    &addin_file_path,
+                    &target_addin_file_path,
+                    e,
+                )),
             }
 
+            // Record what was installed so that the installation can be verified later. A receipt
+            // that cannot be moved to the backup is reported when it is written
+            let _ = rollback.prepare(&receipt::get_receipt_path(destination_dir, &addin_name));
+            match receipt::write_receipt(
+                destination_dir,
+                &addin_name,
+                &csproj_path.to_string_lossy(),
+                git::get_head_commit(&project_info.csproj_dir).await,
+                receipt::get_build_configuration(main_dll_path),
+                &installed_files,
+            ) {
+                Ok(receipt_path) => error_list.add_warning(&format!(
+                    "Wrote install receipt: {}",
+                    receipt_path.to_string_lossy()
+                )),
+                Err(e) => {
+                    error_list.add_warning(&format!("Could not write install receipt: {}", e))
+                }
+            }
+
+            rollback.commit();
+            finish_phase(Phase::Copy, &error_list);
             error_list.add_warning("Addin exported successfully");
         }
         Err(e) => {
             error_list.add_error(&format!("Error creating addin file: {}", e));
+            finish_phase(Phase::Manifest, &error_list);
         }
     }
     error_list
 }
 
+/// Removes the files a cancelled or timed out export wrote, and puts back the ones it replaced
+fn roll_back_export(
+    mut error_list: ErrorList,
+    rollback: Rollback,
+    cancel: &CancellationToken,
+    events: &dyn EventSink,
+) -> ErrorList {
+    error_list.add_typed_error(cancel.error());
+    if let Err(e) = rollback.rollback() {
+        error_list.add_error(&format!("Could not undo the partial export: {}", e));
+    }
+    events.emit(Event::PhaseFinished {
+        phase: Phase::Copy,
+        success: false,
+    });
+    error_list
+}
+
 /// Returns the path to the Revit addin folder or an error message if it cannot be found.
 ///
 /// Version should be the year of the Revit version, e.g. "2025"
-pub fn get_revit_addins_path(version: &str) -> Result<PathBuf, String> {
-    let appdata_roaming = dirs::data_dir().unwrap();
-    let directory = appdata_roaming
-        .join("Autodesk\\Revit\\Addins")
-        .join(version);
-    if directory.exists() {
-        Ok(directory)
-    } else {
-        Err(format!(
-            "Expected to find addin folder in {}",
-            directory.to_string_lossy().into_owned()
-        ))
-    }
+pub fn get_revit_addins_path(version: &str, scope: &InstallScope) -> Result<PathBuf, String> {
+    scope.addins_path(version)
 }
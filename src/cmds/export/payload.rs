@@ -0,0 +1,95 @@
+use glob::{MatchOptions, Pattern};
+use std::path::{Path, PathBuf};
+
+use crate::cmds::export::rollback::Rollback;
+use crate::events::{Event, EventSink};
+use crate::project_config::ExportConfig;
+use crate::utils;
+
+/// A file from the build output directory that should be exported with the addin
+#[derive(Debug, Clone)]
+pub struct PayloadFile {
+    pub source: PathBuf,
+    /// The path relative to the build output directory, which is kept when copying into the addin directory
+    pub relative: PathBuf,
+}
+
+/// Returns the files in the build output directory that match at least one include pattern
+/// and none of the exclude patterns. Returns an error if any of the patterns are invalid
+pub fn collect_payload_files(
+    output_dir: &Path,
+    config: &ExportConfig,
+) -> Result<Vec<PayloadFile>, String> {
+    let include = compile_patterns(&config.include)?;
+    let exclude = compile_patterns(&config.exclude)?;
+    if include.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut payload_files = Vec::new();
+    for source in utils::get_all_files_in_directory(output_dir) {
+        let Some(relative) = pathdiff::diff_paths(&source, output_dir) else {
+            continue;
+        };
+        let relative_str = to_glob_path(&relative);
+        if matches_any(&include, &relative_str) && !matches_any(&exclude, &relative_str) {
+            payload_files.push(PayloadFile { source, relative });
+        }
+    }
+    payload_files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(payload_files)
+}
+
+/// Copies the file into the addin directory, creating any folders it needs. The file it replaces
+/// is kept by `rollback`. Returns the path of the copied file
+pub fn copy_payload_file(
+    file: &PayloadFile,
+    addin_dir: &Path,
+    rollback: &mut Rollback,
+    events: &dyn EventSink,
+) -> Result<PathBuf, std::io::Error> {
+    let target_path = addin_dir.join(&file.relative);
+    rollback.prepare(&target_path)?;
+    let bytes = std::fs::copy(&file.source, &target_path)?;
+    events.emit(Event::FileCopied {
+        source: file.source.clone(),
+        destination: target_path.clone(),
+        bytes,
+    });
+    Ok(target_path)
+}
+
+pub fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let pattern = pattern.replace('\\', "/");
+            // A folder on its own means everything inside of it
+            let pattern = if pattern.ends_with('/') {
+                format!("{}**", pattern)
+            } else {
+                pattern
+            };
+            Pattern::new(&pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+        })
+        .collect()
+}
+
+pub fn matches_any(patterns: &[Pattern], path: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    patterns
+        .iter()
+        .any(|pattern| pattern.matches_with(path, options))
+}
+
+/// Converts a relative path to the '/' separated form that the patterns are written in
+pub fn to_glob_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::RevError;
+use crate::utils;
+
+/// Suffix of the receipt file that is written next to the '.addin' file on export
+pub const RECEIPT_FILE_SUFFIX: &str = ".rev-receipt.json";
+
+/// A record of everything that was installed by an export, used to detect installations that
+/// have since been modified or partially overwritten
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallReceipt {
+    pub addin_name: String,
+    /// The path to the csproj file that the addin was built from
+    pub source_project: String,
+    pub git_commit: Option<String>,
+    pub build_configuration: Option<String>,
+    pub installed_at: DateTime<Utc>,
+    pub files: Vec<ReceiptFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptFile {
+    /// The path of the file relative to the Revit addins directory, using '/' as the separator
+    pub path: String,
+    pub sha256: String,
+}
+
+/// The result of comparing an installation against its receipt. All paths are relative to the
+/// Revit addins directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn has_drift(&self) -> bool {
+        !self.modified.is_empty() || !self.missing.is_empty() || !self.extra.is_empty()
+    }
+}
+
+/// Returns the path of the receipt for the given addin
+///
+/// `destination_dir` is the Revit addins directory that the addin was exported to
+pub fn get_receipt_path(destination_dir: &Path, addin_name: &str) -> PathBuf {
+    destination_dir.join(format!("{}{}", addin_name, RECEIPT_FILE_SUFFIX))
+}
+
+/// Returns the lowercase hex SHA-256 of the file at the given path
+pub fn hash_file(path: &Path) -> Result<String, std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Returns the build configuration (e.g. "Debug" or "Release") from a path to a built DLL,
+/// based on the `bin/<Configuration>/` convention used by MSBuild
+pub fn get_build_configuration(dll_path: &Path) -> Option<String> {
+    let mut components = dll_path.components();
+    while let Some(component) = components.next() {
+        if component.as_os_str().eq_ignore_ascii_case("bin") {
+            return components
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned());
+        }
+    }
+    None
+}
+
+/// Hashes each of the installed files and writes the receipt next to the '.addin' file.
+/// Returns the path to the receipt
+///
+/// `installed_files` should be absolute paths inside of `destination_dir`
+pub fn write_receipt(
+    destination_dir: &Path,
+    addin_name: &str,
+    source_project: &str,
+    git_commit: Option<String>,
+    build_configuration: Option<String>,
+    installed_files: &[PathBuf],
+) -> Result<PathBuf, String> {
+    let mut files = Vec::new();
+    for installed_file in installed_files {
+        let sha256 = hash_file(installed_file)
+            .map_err(|e| format!("Could not hash {}: {}", installed_file.to_string_lossy(), e))?;
+        files.push(ReceiptFile {
+            path: to_receipt_path(destination_dir, installed_file),
+            sha256,
+        });
+    }
+
+    let receipt = InstallReceipt {
+        addin_name: addin_name.to_string(),
+        source_project: source_project.to_string(),
+        git_commit,
+        build_configuration,
+        installed_at: Utc::now(),
+        files,
+    };
+
+    let receipt_path = get_receipt_path(destination_dir, addin_name);
+    let receipt_file = File::create(&receipt_path).map_err(|e| e.to_string())?;
+    let mut writer = BufWriter::new(receipt_file);
+    serde_json::to_writer_pretty(&mut writer, &receipt).map_err(|e| e.to_string())?;
+    // Dropping the writer would flush it without reporting a failed write
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(receipt_path)
+}
+
+pub fn read_receipt(receipt_path: &Path) -> Result<InstallReceipt, RevError> {
+    let receipt_file = File::open(receipt_path).map_err(|e| RevError::io(receipt_path, e))?;
+    serde_json::from_reader(BufReader::new(receipt_file)).map_err(|e| {
+        if e.is_io() {
+            RevError::io(receipt_path, e.into())
+        } else {
+            RevError::InvalidReceipt {
+                path: receipt_path.to_path_buf(),
+                message: e.to_string(),
+            }
+        }
+    })
+}
+
+/// Re-hashes every file listed in the receipt and checks the addin folder for files that
+/// were not installed by the export
+pub fn verify_installation(receipt_path: &Path) -> Result<VerifyReport, RevError> {
+    let receipt = read_receipt(receipt_path)?;
+    let destination_dir = receipt_path.parent().ok_or_else(|| {
+        RevError::io(
+            receipt_path,
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the receipt has no parent directory",
+            ),
+        )
+    })?;
+
+    let mut report = VerifyReport::default();
+    let mut expected_files = HashSet::new();
+    for file in &receipt.files {
+        expected_files.insert(file.path.clone());
+        let installed_path = destination_dir.join(&file.path);
+        match hash_file(&installed_path) {
+            Ok(sha256) if sha256 == file.sha256 => (),
+            Ok(_) => report.modified.push(file.path.clone()),
+            Err(_) => report.missing.push(file.path.clone()),
+        }
+    }
+
+    let addin_dir = destination_dir.join(&receipt.addin_name);
+    for installed_file in utils::get_all_files_in_directory(&addin_dir) {
+        let path = to_receipt_path(destination_dir, &installed_file);
+        if !expected_files.contains(&path) {
+            report.extra.push(path);
+        }
+    }
+    report.extra.sort();
+
+    Ok(report)
+}
+
+/// Converts an installed file path to the '/' separated form stored in the receipt
+fn to_receipt_path(destination_dir: &Path, installed_file: &Path) -> String {
+    let relative = pathdiff::diff_paths(installed_file, destination_dir)
+        .unwrap_or_else(|| installed_file.to_path_buf());
+    relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
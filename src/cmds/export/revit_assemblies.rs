@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+/// An assembly that is installed with Revit and loaded by Revit itself
+struct RevitAssembly {
+    name: &'static str,
+    /// The first Revit version that ships the assembly
+    since: u32,
+    /// The last Revit version that ships the assembly, if it has been removed
+    until: Option<u32>,
+}
+
+impl RevitAssembly {
+    const fn new(name: &'static str, since: u32, until: Option<u32>) -> Self {
+        Self { name, since, until }
+    }
+}
+
+/// Assemblies that are loaded by Revit itself. Shipping a copy of these with an addin causes
+/// subtle load failures, so they are never exported
+const REVIT_ASSEMBLIES: &[RevitAssembly] = &[
+    RevitAssembly::new("RevitAPI", 2000, None),
+    RevitAssembly::new("RevitAPIUI", 2000, None),
+    RevitAssembly::new("RevitAPIIFC", 2000, None),
+    RevitAssembly::new("RevitAPIMacros", 2000, None),
+    RevitAssembly::new("RevitAddInUtility", 2000, None),
+    RevitAssembly::new("RevitAPISteel", 2020, None),
+    RevitAssembly::new("AdWindows", 2000, None),
+    RevitAssembly::new("UIFramework", 2000, None),
+    RevitAssembly::new("UIFrameworkServices", 2000, None),
+    RevitAssembly::new("CefSharp", 2019, None),
+    RevitAssembly::new("CefSharp.Core", 2019, None),
+    RevitAssembly::new("CefSharp.Wpf", 2019, None),
+];
+
+/// Returns true if the file name is an assembly that Revit provides, e.g. "RevitAPI.dll"
+///
+/// If `revit_version` is given, only assemblies that ship with that version of Revit are matched.
+/// Otherwise assemblies that ship with any version are matched
+pub fn is_revit_assembly(file_name: &str, revit_version: Option<u32>) -> bool {
+    let Some(assembly_name) = strip_dll_extension(file_name) else {
+        return false;
+    };
+    REVIT_ASSEMBLIES.iter().any(|revit_assembly| {
+        revit_assembly.name.eq_ignore_ascii_case(assembly_name)
+            && revit_version.is_none_or(|version| {
+                version >= revit_assembly.since
+                    && revit_assembly.until.is_none_or(|until| version <= until)
+            })
+    })
+}
+
+/// Returns the Revit assemblies that the build copied into the output directory
+pub fn find_revit_assemblies_in_output(
+    output_dir: &Path,
+    revit_version: Option<u32>,
+) -> Vec<PathBuf> {
+    let mut revit_assemblies = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(output_dir) {
+        for entry in entries.flatten() {
+            if is_revit_assembly(&entry.file_name().to_string_lossy(), revit_version) {
+                revit_assemblies.push(entry.path());
+            }
+        }
+    }
+    revit_assemblies.sort();
+    revit_assemblies
+}
+
+/// Explains why a Revit assembly ended up in the build output and how to stop it being copied
+pub fn get_copy_local_explanation(file_name: &str) -> String {
+    let assembly_name = strip_dll_extension(file_name).unwrap_or(file_name);
+    format!(
+        "{} ships with Revit and will not be exported. It was copied to the build output because its reference is copied locally. \
+        Set <Private>false</Private> on the <Reference Include=\"{}\"> in the .csproj file, \
+        or <ExcludeAssets>runtime</ExcludeAssets> on the <PackageReference> if it comes from a NuGet package",
+        file_name, assembly_name
+    )
+}
+
+/// Returns the Revit version for an addins directory such as '...\Addins\2025'
+pub fn get_revit_version_of_addins_dir(addins_dir: &Path) -> Option<u32> {
+    addins_dir.file_name()?.to_str()?.parse().ok()
+}
+
+fn strip_dll_extension(file_name: &str) -> Option<&str> {
+    let split = file_name.len().checked_sub(".dll".len())?;
+    if file_name.is_char_boundary(split) && file_name[split..].eq_ignore_ascii_case(".dll") {
+        Some(&file_name[..split])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_assemblies_by_the_versions_that_ship_them() {
+        assert!(is_revit_assembly("RevitAPI.dll", Some(2019)));
+        assert!(is_revit_assembly("revitapiui.DLL", None));
+        assert!(!is_revit_assembly("RevitAPISteel.dll", Some(2019)));
+        assert!(is_revit_assembly("RevitAPISteel.dll", Some(2020)));
+        assert!(is_revit_assembly("RevitAPISteel.dll", None));
+        assert!(!is_revit_assembly("CefSharp.dll", Some(2018)));
+        assert!(is_revit_assembly("CefSharp.Wpf.dll", Some(2025)));
+        assert!(!is_revit_assembly("Newtonsoft.Json.dll", None));
+        assert!(!is_revit_assembly("RevitAPI", None));
+    }
+}
@@ -0,0 +1,342 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// An export writes into the addin directory one file at a time, so stopping it part way leaves
+// a mix of old and new files that Revit may fail to load. Every file and folder an export is
+// about to write is recorded here first, so that a cancelled export can be undone
+
+/// The file in the backup folder that lists every change, one '<backup name>\t<path>' line each,
+/// so that the changes of a killed export can still be undone. Paths that did not exist before
+/// the export have `CREATED_MARKER` instead of a backup name
+const BACKUP_PATHS_FILE_NAME: &str = "paths.txt";
+const CREATED_MARKER: &str = "new";
+
+/// Records the changes an export makes to the addins directory. Files and folders that are
+/// replaced are moved to a backup folder next to the addin directory, which is deleted once the
+/// export is committed
+pub struct Rollback {
+    backup_dir: PathBuf,
+    changes: Vec<Change>,
+}
+
+enum Change {
+    /// A file or folder that did not exist before the export
+    Created(PathBuf),
+    /// A file or folder that was moved to `backup` so that it could be replaced
+    Replaced { path: PathBuf, backup: PathBuf },
+}
+
+impl Rollback {
+    /// Starts recording the changes to the addin named `addin_name` in `destination_dir`.
+    /// A backup left behind by an export that was killed is put back first, so that the
+    /// previous install is not lost. Returns an error if it cannot be put back
+    pub fn new(destination_dir: &Path, addin_name: &str) -> Result<Self, String> {
+        let backup_dir = destination_dir.join(format!(".{}.rev-backup", addin_name));
+        if backup_dir.exists() {
+            restore_backup(&backup_dir).map_err(|e| {
+                format!(
+                    "An export that was stopped left the previous install in {}, and it could not be put back: {}. Move its files back as listed in its {}, then delete it",
+                    backup_dir.to_string_lossy(),
+                    e,
+                    BACKUP_PATHS_FILE_NAME
+                )
+            })?;
+        }
+        Ok(Self {
+            backup_dir,
+            changes: Vec::new(),
+        })
+    }
+
+    /// Gets `path` ready to be written: an existing file or folder there is moved to the backup,
+    /// and the folders it is in are created
+    pub fn prepare(&mut self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(path).is_err() {
+            return self.record(Change::Created(path.to_path_buf()));
+        }
+        let backup = self.backup_dir.join(self.changes.len().to_string());
+        // Recorded before the move, so that the backup can always be put back
+        self.record(Change::Replaced {
+            path: path.to_path_buf(),
+            backup: backup.clone(),
+        })?;
+        if let Err(e) = fs::rename(path, &backup) {
+            // Nothing was moved, so rolling back must not delete what is there
+            self.changes.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Creates the folder and any missing folders it is in
+    pub fn create_dir_all(&mut self, dir: &Path) -> std::io::Result<()> {
+        let created = dir
+            .ancestors()
+            .take_while(|ancestor| !ancestor.exists())
+            .last()
+            .map(Path::to_path_buf);
+        if let Some(created) = created {
+            self.record(Change::Created(created))?;
+        }
+        fs::create_dir_all(dir)
+    }
+
+    /// Adds the change to the backup's list of paths, and to the changes to roll back
+    fn record(&mut self, change: Change) -> std::io::Result<()> {
+        let (backup_name, path) = match &change {
+            Change::Created(path) => (CREATED_MARKER.into(), path),
+            Change::Replaced { path, backup } => (
+                backup.file_name().unwrap_or_default().to_string_lossy(),
+                path,
+            ),
+        };
+        fs::create_dir_all(&self.backup_dir)?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.backup_dir.join(BACKUP_PATHS_FILE_NAME))
+            .and_then(|mut paths_file| {
+                writeln!(paths_file, "{}\t{}", backup_name, path.to_string_lossy())
+            })?;
+        self.changes.push(change);
+        Ok(())
+    }
+
+    /// Deletes everything the export wrote and moves the files and folders it replaced back.
+    /// Returns an error listing the paths that could not be restored
+    pub fn rollback(self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for change in self.changes.into_iter().rev() {
+            let result = match &change {
+                Change::Created(path) => remove_path(path),
+                Change::Replaced { path, backup } => {
+                    remove_path(path).and_then(|_| fs::rename(backup, path))
+                }
+            };
+            if let Err(e) = result {
+                let (Change::Created(path) | Change::Replaced { path, .. }) = change;
+                errors.push(format!("{}: {}", path.to_string_lossy(), e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(format!(
+                "{}. The replaced files are kept in {}",
+                errors.join(", "),
+                self.backup_dir.to_string_lossy()
+            ));
+        }
+        let _ = fs::remove_dir_all(&self.backup_dir);
+        Ok(())
+    }
+
+    /// Keeps the changes and deletes the backup. A file or folder that was moved to the backup
+    /// but never written again, e.g. the web app of a frontend that failed to build, is moved back
+    pub fn commit(self) {
+        // Without the list of paths, a commit that is killed part way is never undone
+        let _ = fs::remove_file(self.backup_dir.join(BACKUP_PATHS_FILE_NAME));
+        // The latest backup of a path that was prepared more than once is the one to keep
+        for change in self.changes.into_iter().rev() {
+            if let Change::Replaced { path, backup } = change
+                && fs::symlink_metadata(&path).is_err()
+            {
+                let _ = fs::rename(backup, path);
+            }
+        }
+        let _ = fs::remove_dir_all(&self.backup_dir);
+    }
+}
+
+/// Undoes the changes of a killed export from its backup: the files and folders it created are
+/// deleted and the ones it replaced are moved back, then the backup is deleted
+fn restore_backup(backup_dir: &Path) -> std::io::Result<()> {
+    let paths = match fs::read_to_string(backup_dir.join(BACKUP_PATHS_FILE_NAME)) {
+        Ok(paths) => paths,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    for (backup_name, path) in paths.lines().rev().filter_map(|line| line.split_once('\t')) {
+        if backup_name == CREATED_MARKER {
+            remove_path(Path::new(path))?;
+            continue;
+        }
+        let backup = backup_dir.join(backup_name);
+        // Backups that were already moved back, or never moved, are not in the folder
+        if fs::symlink_metadata(&backup).is_ok() {
+            remove_path(Path::new(path))?;
+            fs::rename(&backup, path)?;
+        }
+    }
+    // Deleting a folder the export created may have deleted the backup along with it
+    match fs::remove_dir_all(backup_dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes the file or folder, if it exists
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Returns every file and folder under `dir` with the contents of the files
+    fn snapshot(dir: &Path) -> BTreeMap<PathBuf, Option<Vec<u8>>> {
+        let mut entries = BTreeMap::new();
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                entries.insert(path.clone(), None);
+                entries.extend(snapshot(&path));
+            } else {
+                entries.insert(path.clone(), Some(fs::read(&path).unwrap()));
+            }
+        }
+        entries
+    }
+
+    /// Creates an installed addin in `destination_dir`: its folder with a DLL and a web app, and
+    /// its '.addin' file
+    fn install_previous_version(destination_dir: &Path) {
+        fs::create_dir_all(destination_dir.join("MyAddin/web")).unwrap();
+        fs::write(destination_dir.join("MyAddin/MyAddin.dll"), b"old dll").unwrap();
+        fs::write(destination_dir.join("MyAddin/web/index.html"), b"old web").unwrap();
+        fs::write(destination_dir.join("MyAddin.addin"), b"old manifest").unwrap();
+    }
+
+    /// Writes a new version over the installed addin the way an export does, preparing every
+    /// path first. The DLL is written twice, as when it is both the main DLL and a dependency
+    fn export_new_version(rollback: &mut Rollback, destination_dir: &Path) {
+        let addin_dir = destination_dir.join("MyAddin");
+        rollback.create_dir_all(&addin_dir).unwrap();
+        for _ in 0..2 {
+            rollback.prepare(&addin_dir.join("MyAddin.dll")).unwrap();
+            fs::write(addin_dir.join("MyAddin.dll"), b"new dll").unwrap();
+        }
+        rollback
+            .prepare(&addin_dir.join("runtimes/win/native.dll"))
+            .unwrap();
+        fs::write(addin_dir.join("runtimes/win/native.dll"), b"new native").unwrap();
+        rollback.prepare(&addin_dir.join("web")).unwrap();
+        fs::create_dir_all(addin_dir.join("web")).unwrap();
+        fs::write(addin_dir.join("web/index.html"), b"new web").unwrap();
+        rollback
+            .prepare(&destination_dir.join("MyAddin.addin"))
+            .unwrap();
+        fs::write(destination_dir.join("MyAddin.addin"), b"new manifest").unwrap();
+    }
+
+    #[test]
+    fn rollback_restores_the_previous_install() {
+        let destination = tempfile::tempdir().unwrap();
+        install_previous_version(destination.path());
+        let before = snapshot(destination.path());
+
+        let mut rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        export_new_version(&mut rollback, destination.path());
+        rollback.rollback().unwrap();
+        assert_eq!(snapshot(destination.path()), before);
+    }
+
+    #[test]
+    fn rollback_removes_a_new_install() {
+        let destination = tempfile::tempdir().unwrap();
+        let mut rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        export_new_version(&mut rollback, destination.path());
+        rollback.rollback().unwrap();
+        assert!(snapshot(destination.path()).is_empty());
+    }
+
+    #[test]
+    fn commit_keeps_the_new_install() {
+        let destination = tempfile::tempdir().unwrap();
+        install_previous_version(destination.path());
+        let addin_dir = destination.path().join("MyAddin");
+
+        let mut rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        for _ in 0..2 {
+            rollback.prepare(&addin_dir.join("MyAddin.dll")).unwrap();
+            fs::write(addin_dir.join("MyAddin.dll"), b"new dll").unwrap();
+        }
+        // Prepared twice but only written the first time, e.g. a web app that failed to build
+        rollback.prepare(&addin_dir.join("web")).unwrap();
+        fs::create_dir_all(addin_dir.join("web")).unwrap();
+        fs::write(addin_dir.join("web/index.html"), b"new web").unwrap();
+        rollback.prepare(&addin_dir.join("web")).unwrap();
+        rollback.commit();
+
+        assert_eq!(
+            snapshot(destination.path()),
+            BTreeMap::from([
+                (addin_dir.clone(), None),
+                (addin_dir.join("MyAddin.dll"), Some(b"new dll".to_vec())),
+                (addin_dir.join("web"), None),
+                (addin_dir.join("web/index.html"), Some(b"new web".to_vec())),
+                (
+                    destination.path().join("MyAddin.addin"),
+                    Some(b"old manifest".to_vec())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn next_export_undoes_a_killed_export() {
+        let destination = tempfile::tempdir().unwrap();
+        install_previous_version(destination.path());
+        let before = snapshot(destination.path());
+
+        let mut rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        export_new_version(&mut rollback, destination.path());
+        // The process is killed before it can roll back or commit
+        std::mem::forget(rollback);
+        assert!(destination.path().join(".MyAddin.rev-backup").is_dir());
+
+        let rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        assert_eq!(snapshot(destination.path()), before);
+        rollback.commit();
+        assert_eq!(snapshot(destination.path()), before);
+    }
+
+    #[test]
+    fn next_export_removes_a_killed_new_install() {
+        let destination = tempfile::tempdir().unwrap();
+        let mut rollback = Rollback::new(destination.path(), "MyAddin").unwrap();
+        export_new_version(&mut rollback, destination.path());
+        std::mem::forget(rollback);
+
+        Rollback::new(destination.path(), "MyAddin").unwrap();
+        assert!(snapshot(destination.path()).is_empty());
+    }
+
+    #[test]
+    fn keeps_a_backup_that_cannot_be_restored() {
+        let destination = tempfile::tempdir().unwrap();
+        let backup_dir = destination.path().join(".MyAddin.rev-backup");
+        fs::create_dir_all(&backup_dir).unwrap();
+        fs::write(backup_dir.join("0"), b"old dll").unwrap();
+        // The folder the backup belongs in is now a file
+        fs::write(destination.path().join("MyAddin"), b"").unwrap();
+        let lost_path = destination.path().join("MyAddin/MyAddin.dll");
+        fs::write(
+            backup_dir.join(BACKUP_PATHS_FILE_NAME),
+            format!("0\t{}\n", lost_path.to_string_lossy()),
+        )
+        .unwrap();
+
+        let error = Rollback::new(destination.path(), "MyAddin").err().unwrap();
+        assert!(error.contains(".MyAddin.rev-backup"));
+        assert_eq!(fs::read(backup_dir.join("0")).unwrap(), b"old dll");
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where an addin is installed. Each scope has its own folder for every Revit version
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum InstallScope {
+    /// The current user's addins folder in AppData\Roaming
+    #[default]
+    User,
+    /// The addins folder in ProgramData, which is loaded for all users of the machine
+    Machine,
+    /// A custom folder that contains a folder for each Revit version
+    Path(PathBuf),
+}
+
+impl InstallScope {
+    /// Returns the scopes in the order that Revit loads them. Revit reads the machine-wide
+    /// manifests before the current user's, so an AddInId in the user scope that is already
+    /// installed machine-wide will not be loaded
+    pub fn load_order() -> Vec<InstallScope> {
+        vec![InstallScope::Machine, InstallScope::User]
+    }
+
+    /// Returns the folder that contains the addin folders for every Revit version
+    pub fn addins_root(&self) -> Result<PathBuf, String> {
+        match self {
+            InstallScope::User => {
+                let appdata_roaming =
+                    dirs::data_dir().ok_or("Could not find the application data directory")?;
+                Ok(appdata_roaming.join("Autodesk\\Revit\\Addins"))
+            }
+            InstallScope::Machine => {
+                let program_data =
+                    std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+                Ok(PathBuf::from(program_data).join("Autodesk\\Revit\\Addins"))
+            }
+            InstallScope::Path(addins_root) => Ok(addins_root.clone()),
+        }
+    }
+
+    /// Returns the addins folder for the Revit version or an error message if it cannot be found.
+    ///
+    /// Version should be the year of the Revit version, e.g. "2025"
+    pub fn addins_path(&self, version: &str) -> Result<PathBuf, String> {
+        let directory = self.addins_root()?.join(version);
+        if directory.exists() {
+            Ok(directory)
+        } else {
+            Err(format!(
+                "Expected to find addin folder in {}",
+                directory.to_string_lossy().into_owned()
+            ))
+        }
+    }
+
+    /// Returns the addins folder to export to for the Revit version. The folder of a custom
+    /// root is created on the first export, while the user and machine folders must already
+    /// exist, since Revit creates them when it is installed
+    pub fn create_addins_path(&self, version: &str) -> Result<PathBuf, String> {
+        let InstallScope::Path(addins_root) = self else {
+            return self.addins_path(version);
+        };
+        let directory = addins_root.join(version);
+        std::fs::create_dir_all(&directory).map_err(|e| {
+            format!(
+                "Could not create the addin folder {}: {}",
+                directory.to_string_lossy(),
+                e
+            )
+        })?;
+        Ok(directory)
+    }
+}
+
+/// Returns the other scopes, in load order, that have a manifest named '<addin_name>.addin'
+/// installed for the Revit version
+pub fn find_other_installations(
+    addin_name: &str,
+    version: &str,
+    scope: &InstallScope,
+) -> Vec<InstallScope> {
+    InstallScope::load_order()
+        .into_iter()
+        .filter(|other| other != scope)
+        .filter(|other| {
+            other
+                .addins_path(version)
+                .is_ok_and(|dir| dir.join(format!("{}.addin", addin_name)).exists())
+        })
+        .collect()
+}
+
+impl FromStr for InstallScope {
+    type Err = String;
+
+    /// Parses "user", "machine" or "path=<dir>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "user" => Ok(InstallScope::User),
+            "machine" => Ok(InstallScope::Machine),
+            s => match s.strip_prefix("path=") {
+                Some(path) if !path.is_empty() => Ok(InstallScope::Path(PathBuf::from(path))),
+                _ => Err(format!(
+                    "Invalid scope '{}'. Expected user, machine or path=<dir>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl Display for InstallScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallScope::User => write!(f, "user"),
+            InstallScope::Machine => write!(f, "machine"),
+            InstallScope::Path(path) => write!(f, "path={}", path.to_string_lossy()),
+        }
+    }
+}
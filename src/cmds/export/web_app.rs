@@ -1,71 +1,442 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
+use glob::Pattern;
 use path_absolutize::Absolutize;
 
-use crate::external_cmds::yarn;
+use crate::cancel::CancellationToken;
+use crate::cmds::web::gen_types;
+use crate::events::{Event, EventSink, Phase};
+use crate::external_cmds::package_manager::{self, PackageManager};
+use crate::external_cmds::shell;
+use crate::project_config::WebConfig;
 use crate::utils;
+use crate::utils::error_list::ErrorList;
 
-// Checks for the presence of a web app (Right now we should only support Next.js + yarn) and will generate
-// the static files for the app, rename the bundle to 'web' and then bundle that folder with the assets of the
-// Revit add in
+// Checks for the presence of web apps (Next.js, Vite, Angular or anything else with a build script) and will
+// generate the static files for each app, then bundle them with the assets of the Revit add in
 
-/// Builds the web project if it exists. Logs any errors
+/// The folder inside of the addin directory that the web app is copied to when none is configured
+pub const WEB_DIR_NAME: &str = "web";
+
+/// The folders that common frameworks write their static files to, in the order they are checked:
+/// Next.js uses 'out', Vite and Angular use 'dist', and Create React App uses 'build'
+const OUTPUT_DIR_NAMES: [&str; 3] = ["out", "dist", "build"];
+
+/// A frontend to build and copy into the addin directory
+#[derive(Debug, Clone)]
+pub struct Frontend {
+    /// The absolute path to the folder containing the frontend's package.json
+    pub dir: PathBuf,
+    pub build_command: Option<String>,
+    /// The folder the build writes the static files to. Detected after the build when not set
+    pub output_dir: Option<PathBuf>,
+    /// The folder inside of the addin directory that the static files are copied to
+    pub destination: String,
+    pub package_manager: PackageManager,
+}
+
+/// Returns the frontends configured for the project, or the web app found below `starting_dir`
+/// if none are configured. Returns an error if a configured frontend is invalid
+///
+/// Configured frontend folders are relative to `csproj_dir`
+pub fn get_frontends(
+    starting_dir: &str,
+    csproj_dir: &Path,
+    web_config: &WebConfig,
+) -> Result<Vec<Frontend>, String> {
+    if web_config.frontends.is_empty() {
+        return Ok(find_web_app_dir(starting_dir, &web_config.exclude)
+            .map(|dir| Frontend {
+                package_manager: web_config
+                    .package_manager
+                    .unwrap_or_else(|| package_manager::detect(&dir)),
+                dir,
+                build_command: None,
+                output_dir: None,
+                destination: WEB_DIR_NAME.to_string(),
+            })
+            .into_iter()
+            .collect());
+    }
+
+    let mut destinations: Vec<(String, &str)> = Vec::new();
+    let mut frontends = Vec::new();
+    for frontend_config in &web_config.frontends {
+        let dir = csproj_dir
+            .join(&frontend_config.dir)
+            .absolutize()
+            .map_err(|e| e.to_string())?
+            .into_owned();
+        if !dir.join("package.json").is_file() {
+            return Err(format!(
+                "The frontend folder {} does not contain a package.json",
+                dir.to_string_lossy()
+            ));
+        }
+        // The destination is deleted before each export, so it must stay inside of the addin directory
+        let Some(destination) = normalize_destination(&frontend_config.destination) else {
+            return Err(format!(
+                "The destination '{}' of the frontend in {} must be a folder inside of the addin directory",
+                frontend_config.destination, frontend_config.dir
+            ));
+        };
+        // Exporting a frontend would also delete the files of a frontend exported inside of it
+        if let Some((_, other)) = destinations
+            .iter()
+            .find(|(other, _)| destinations_overlap(&destination, other))
+        {
+            return Err(format!(
+                "The frontends exported to '{}' and '{}' overlap. Each frontend needs its own folder",
+                other, frontend_config.destination
+            ));
+        }
+        destinations.push((destination.clone(), &frontend_config.destination));
+        frontends.push(Frontend {
+            package_manager: frontend_config
+                .package_manager
+                .or(web_config.package_manager)
+                .unwrap_or_else(|| package_manager::detect(&dir)),
+            output_dir: frontend_config
+                .output_dir
+                .as_ref()
+                .map(|output_dir| dir.join(output_dir)),
+            build_command: frontend_config.build_command.clone(),
+            destination,
+            dir,
+        });
+    }
+    Ok(frontends)
+}
+
+/// Returns the destination with '/' separators and without '.' components, or None if it is
+/// not a folder inside of the addin directory
+fn normalize_destination(destination: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(&destination.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Returns true if one destination is the same folder as the other or inside of it. Paths are
+/// compared regardless of case, like Windows does
+fn destinations_overlap(a: &str, b: &str) -> bool {
+    let a: Vec<String> = a.split('/').map(str::to_lowercase).collect();
+    let b: Vec<String> = b.split('/').map(str::to_lowercase).collect();
+    a.starts_with(&b) || b.starts_with(&a)
+}
+
+/// Returns an error if a frontend would be exported to a folder that contains files already
+/// copied with the add-in, e.g. 'runtimes' or 'en-US', since the folder is replaced by the web app
+pub fn check_destinations(
+    frontends: &[Frontend],
+    addin_dir: &Path,
+    installed_files: &[PathBuf],
+) -> Result<(), String> {
+    for frontend in frontends {
+        let installed_file = installed_files.iter().find(|file| {
+            file.strip_prefix(addin_dir).is_ok_and(|relative| {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                destinations_overlap(&relative, &frontend.destination)
+            })
+        });
+        if let Some(installed_file) = installed_file {
+            return Err(format!(
+                "The frontend in {} is exported to '{}', which would replace {} that is exported with the add-in. Choose another destination",
+                frontend.dir.to_string_lossy(),
+                frontend.destination,
+                installed_file.to_string_lossy()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Generates the TypeScript message types, if configured, then builds every frontend of the project
+/// and copies its static files into its destination folder in the addin directory, replacing the files
+/// from any previous export. Errors are added to the error list, except for the cancellation
+/// of `cancel`, which the caller reports
 ///
 /// `addin_dir` should be the directory that contains the DLL file for your addin,
 /// not the outer Revit addins directory
-pub async fn build_if_exists(addin_dir: &Path) {
-    if let Some(node_modules) = find_node_modules_path(".") {
-        println!("Exporting static assets for web app. Please wait...");
-        match create_static_export(&node_modules).await {
-            Ok(_) => {
-                let node_modules_path = Path::new(&node_modules);
-                let parent = node_modules_path.parent().unwrap();
-                let out_dir = parent.join("out");
-
-                if !Path::exists(&out_dir) {
-                    println!(
-                        "Web app was exported, and expected to find static files at {}, but they were not found",
-                        out_dir.to_string_lossy()
-                    );
-                    return;
-                }
-                let new_out_path = addin_dir.join("web");
-                std::fs::create_dir_all(new_out_path.clone()).unwrap();
-                println!("{}", out_dir.to_string_lossy());
-                println!("{}", new_out_path.to_string_lossy());
-                std::fs::copy(out_dir, new_out_path).unwrap();
+///
+/// Returns the paths of the files that were copied
+pub async fn export_frontends(
+    starting_dir: &str,
+    csproj_dir: &Path,
+    addin_dir: &Path,
+    web_config: &WebConfig,
+    error_list: &mut ErrorList,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Vec<PathBuf> {
+    let frontends = match get_frontends(starting_dir, csproj_dir, web_config) {
+        Ok(frontends) => frontends,
+        Err(e) => {
+            error_list.add_error(&format!("Error reading frontend config: {}", e));
+            return Vec::new();
+        }
+    };
+    if let Some(types_config) = &web_config.types {
+        error_list.extend(&gen_types::generate_types(csproj_dir, types_config));
+    }
+    let mut copied_files = Vec::new();
+    for frontend in &frontends {
+        copied_files.extend(export_frontend(frontend, addin_dir, error_list, events, cancel).await);
+    }
+    copied_files
+}
+
+/// Builds the frontend and copies its static files into its destination folder in the addin
+/// directory. Errors are added to the error list, except for the cancellation of `cancel`
+///
+/// Returns the paths of the files that were copied
+pub async fn export_frontend(
+    frontend: &Frontend,
+    addin_dir: &Path,
+    error_list: &mut ErrorList,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Vec<PathBuf> {
+    events.emit(Event::PhaseStarted(Phase::WebBuild));
+    let mut frontend_errors = ErrorList::new();
+    let copied_files =
+        build_and_copy_frontend(frontend, addin_dir, &mut frontend_errors, events, cancel).await;
+    events.emit(Event::PhaseFinished {
+        phase: Phase::WebBuild,
+        success: !frontend_errors.has_errors() && !cancel.is_cancelled(),
+    });
+    error_list.extend(&frontend_errors);
+    copied_files
+}
+
+async fn build_and_copy_frontend(
+    frontend: &Frontend,
+    addin_dir: &Path,
+    error_list: &mut ErrorList,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Vec<PathBuf> {
+    let name = frontend
+        .dir
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    if needs_install(&frontend.dir, frontend.package_manager) {
+        events.emit(Event::Message(format!(
+            "Installing dependencies for {}. Please wait...",
+            name
+        )));
+        if let Err(e) =
+            package_manager::install(frontend.package_manager, &frontend.dir, events, cancel).await
+        {
+            // The command failed because it was killed
+            if cancel.is_cancelled() {
+                return Vec::new();
             }
-            Err(err) => {
-                println!("Error exporting web app: {}", err)
+            error_list.add_error(&format!(
+                "Error installing dependencies for {} with {}: {}",
+                name, frontend.package_manager, e
+            ));
+            return Vec::new();
+        }
+    }
+
+    events.emit(Event::Message(format!(
+        "Exporting static assets for {}. Please wait...",
+        name
+    )));
+    if let Err(e) = create_static_export(frontend, events, cancel).await {
+        if cancel.is_cancelled() {
+            return Vec::new();
+        }
+        error_list.add_error(&format!("Error exporting {}: {}", name, e));
+        return Vec::new();
+    }
+
+    let out_dir = match &frontend.output_dir {
+        Some(output_dir) => output_dir.clone(),
+        None => match find_output_dir(&frontend.dir) {
+            Some(output_dir) => output_dir,
+            None => {
+                error_list.add_error(&format!(
+                    "{} was built, but no static files were found in {}. Set \"outputDir\" for the frontend in rev.json",
+                    name,
+                    OUTPUT_DIR_NAMES.join(", ")
+                ));
+                return Vec::new();
             }
+        },
+    };
+    if !out_dir.is_dir() {
+        error_list.add_error(&format!(
+            "{} was built, and expected to find static files at {}, but they were not found",
+            name,
+            out_dir.to_string_lossy()
+        ));
+        return Vec::new();
+    }
+
+    // Remove the previous export so that files deleted from the web app do not linger
+    let web_dir = addin_dir.join(&frontend.destination);
+    if web_dir.exists()
+        && let Err(e) = std::fs::remove_dir_all(&web_dir)
+    {
+        error_list.add_error(&format!(
+            "Error removing previous web app export at {}: {}",
+            web_dir.to_string_lossy(),
+            e
+        ));
+        return Vec::new();
+    }
+    match utils::copy_dir_recursive(&out_dir, &web_dir, events) {
+        Ok(copied_files) => {
+            error_list.add_warning(&format!(
+                "Successfully copied {} to {}: {} files",
+                name,
+                frontend.destination,
+                copied_files.len()
+            ));
+            copied_files
+        }
+        Err(e) => {
+            error_list.add_error(&format!("Error copying {}: {}", name, e));
+            Vec::new()
         }
-    } else {
-        println!("No web app found");
     }
 }
 
-/// Returns the absolute path to the node modules directory
-fn find_node_modules_path(starting_dir: &str) -> Option<PathBuf> {
-    utils::recursively_check_for_file(
-        starting_dir,
-        "node_modules",
-        3,
-        utils::SearchDirection::Child,
-    )
-    .map(|x| {
-        let path = Path::new(&x);
-        let absolute = path.absolutize().unwrap();
-        absolute.into_owned()
-    })
+/// Returns the folder with the static files of a built frontend. Angular writes them to
+/// 'dist/<app>/browser' and older versions to 'dist/<app>', so the folder containing
+/// 'index.html' is used when the output folder itself does not have one
+pub fn find_output_dir(frontend_dir: &Path) -> Option<PathBuf> {
+    let output_dir = OUTPUT_DIR_NAMES
+        .iter()
+        .map(|name| frontend_dir.join(name))
+        .find(|path| path.is_dir())?;
+    if output_dir.join("index.html").is_file() {
+        return Some(output_dir);
+    }
+    let mut index_dirs: Vec<PathBuf> = utils::get_all_files_in_directory(&output_dir)
+        .into_iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == "index.html"))
+        .filter_map(|path| path.parent().map(Path::to_path_buf))
+        .collect();
+    // Prefer the shallowest folder, which is the root of the app rather than one of its routes
+    index_dirs.sort_by_key(|dir| dir.components().count());
+    Some(index_dirs.into_iter().next().unwrap_or(output_dir))
 }
 
-async fn create_static_export(node_modules_path: &Path) -> Result<(), String> {
-    let parent = node_modules_path.parent().unwrap();
+/// Returns the absolute path to the first directory with a package.json that has a build script,
+/// searching up to 3 levels below `starting_dir`
+///
+/// Folders matching `exclude` are skipped
+pub fn find_web_app_dir(starting_dir: &str, exclude: &[String]) -> Option<PathBuf> {
+    let exclude: Vec<Pattern> = exclude
+        .iter()
+        .filter_map(|pattern| Pattern::new(pattern.trim_end_matches('/')).ok())
+        .collect();
+    let root = Path::new(starting_dir).absolutize().ok()?.into_owned();
+
+    let mut current_level = vec![root.clone()];
+    for _ in 0..=3 {
+        let mut next_level = Vec::new();
+        for dir in current_level {
+            if has_build_script(&dir) {
+                return Some(dir);
+            }
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut subdirs: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+                .map(|entry| entry.path())
+                .filter(|path| !is_excluded(path, &root, &exclude))
+                .collect();
+            subdirs.sort();
+            next_level.extend(subdirs);
+        }
+        current_level = next_level;
+    }
+    None
+}
+
+fn is_excluded(dir: &Path, root: &Path, exclude: &[Pattern]) -> bool {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    let relative = dir
+        .strip_prefix(root)
+        .unwrap_or(dir)
+        .to_string_lossy()
+        .replace('\\', "/");
+    exclude
+        .iter()
+        .any(|pattern| pattern.matches(&name) || pattern.matches(&relative))
+}
+
+fn has_build_script(dir: &Path) -> bool {
+    let Ok(contents) = std::fs::read_to_string(dir.join("package.json")) else {
+        return false;
+    };
+    serde_json::from_str::<serde_json::Value>(&contents)
+        .ok()
+        .and_then(|package_json| package_json.get("scripts")?.get("build").cloned())
+        .is_some_and(|build| build.is_string())
+}
 
-    // Run yarn build in the parent directory
-    match yarn::build(parent.to_str().unwrap()).await {
-        Ok(_) => Ok(()),
-        Err(yarn::YarnError::NotFound) => Err("Yarn is not installed on this system".to_string()),
-        Err(yarn::YarnError::Output(e)) => Err(format!("Failed to run yarn build: {}", e)),
+/// Returns true if the dependencies are not installed, or the lockfile has changed since they were
+fn needs_install(web_app_dir: &Path, package_manager: PackageManager) -> bool {
+    let node_modules = web_app_dir.join("node_modules");
+    let Ok(installed_at) = std::fs::metadata(&node_modules).and_then(|m| m.modified()) else {
+        return true;
+    };
+    // Package managers record the last install inside of node_modules, which is more reliable
+    // than the folder itself
+    let installed_at = [
+        ".package-lock.json",
+        ".yarn-integrity",
+        ".yarn-state.yml",
+        ".modules.yaml",
+    ]
+    .iter()
+    .filter_map(|marker| {
+        std::fs::metadata(node_modules.join(marker))
+            .ok()?
+            .modified()
+            .ok()
+    })
+    .fold(installed_at, |latest, modified| latest.max(modified));
+    package_manager::find_lockfile(package_manager, web_app_dir)
+        .and_then(|lockfile| std::fs::metadata(lockfile).ok()?.modified().ok())
+        .is_some_and(|locked_at| locked_at > installed_at)
+}
+
+async fn create_static_export(
+    frontend: &Frontend,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<(), String> {
+    if let Some(build_command) = &frontend.build_command {
+        return shell::run(build_command, &frontend.dir, events, cancel)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to run {}: {}", build_command, e));
     }
+    let package_manager = frontend.package_manager;
+    package_manager::run_script(package_manager, &frontend.dir, "build", events, cancel)
+        .await
+        .map(|_| ())
+        .map_err(|e| match e {
+            package_manager::PackageManagerError::NotFound(_) => e.to_string(),
+            package_manager::PackageManagerError::Output(output) => {
+                format!("Failed to run {} build: {}", package_manager, output)
+            }
+        })
 }
@@ -0,0 +1,195 @@
+use crate::cmds::export::addin_file;
+use crate::cmds::export::scope::InstallScope;
+use crate::utils::error_list::ErrorList;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// An addin entry found in one of the Revit addins directories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledAddin {
+    /// The scope the addin was found in, e.g. "user" or "machine"
+    pub scope: String,
+    pub revit_version: String,
+    pub manifest_path: PathBuf,
+    pub addin_type: String,
+    pub name: String,
+    pub vendor_id: String,
+    pub addin_id: String,
+    pub full_class_name: String,
+    /// The assembly as written in the manifest
+    pub assembly: String,
+    /// The assembly resolved against the directory of the manifest
+    pub assembly_path: PathBuf,
+    pub assembly_exists: bool,
+}
+
+/// Prints every addin installed for any Revit version
+///
+/// `scope` limits the search to a single scope. Otherwise every scope is searched in the order
+/// that Revit loads them, and addins that are installed in more than one scope are reported.
+/// `vendor` only shows addins whose vendor ID matches, ignoring case.
+/// If `json` is true, the addins are printed as a JSON array instead
+pub fn execute(json: bool, vendor: Option<&str>, scope: Option<&InstallScope>) {
+    let scopes = match scope {
+        Some(scope) => vec![scope.clone()],
+        None => InstallScope::load_order(),
+    };
+    let mut error_list = ErrorList::new();
+    let mut addins = Vec::new();
+    for scope in &scopes {
+        addins.extend(find_installed_addins(scope, &mut error_list));
+    }
+    for duplicate in find_addins_in_multiple_scopes(&addins) {
+        error_list.add_warning(&duplicate);
+    }
+    if let Some(vendor) = vendor {
+        addins.retain(|addin| addin.vendor_id.eq_ignore_ascii_case(vendor));
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&addins).unwrap());
+        return;
+    }
+
+    if addins.is_empty() {
+        println!("No addins found");
+    }
+    let mut current_group = None;
+    for addin in &addins {
+        let group = (&addin.scope, &addin.revit_version);
+        if current_group != Some(group) {
+            println!("Revit {} ({}):", addin.revit_version, addin.scope);
+            current_group = Some(group);
+        }
+        println!(
+            "  {} [{}] vendor: {}, id: {}",
+            addin.name, addin.addin_type, addin.vendor_id, addin.addin_id
+        );
+        println!(
+            "    {}{}",
+            addin.assembly_path.to_string_lossy(),
+            if addin.assembly_exists {
+                ""
+            } else {
+                " (assembly not found)"
+            }
+        );
+    }
+    for warning in error_list.view_warnings() {
+        println!("Warning: {}", warning);
+    }
+}
+
+/// Returns a message for every AddInId that is installed in more than one scope for the same
+/// Revit version. Only the installation in the scope that Revit loads first will be used
+pub fn find_addins_in_multiple_scopes(addins: &[InstalledAddin]) -> Vec<String> {
+    let mut scopes_by_addin: HashMap<(String, String), Vec<&InstalledAddin>> = HashMap::new();
+    for addin in addins {
+        let key = (
+            addin.revit_version.clone(),
+            addin_file::normalize_addin_id(&addin.addin_id),
+        );
+        let installs = scopes_by_addin.entry(key).or_default();
+        if !installs.iter().any(|other| other.scope == addin.scope) {
+            installs.push(addin);
+        }
+    }
+
+    let mut messages: Vec<String> = scopes_by_addin
+        .into_iter()
+        .filter(|((_, addin_id), installs)| !addin_id.is_empty() && installs.len() > 1)
+        .map(|((revit_version, _), installs)| {
+            let scopes = installs
+                .iter()
+                .map(|addin| addin.scope.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "{} is installed for Revit {} in more than one scope ({}). Only the {} installation will be loaded",
+                installs[0].name, revit_version, scopes, installs[0].scope
+            )
+        })
+        .collect();
+    messages.sort();
+    messages
+}
+
+/// Returns the Revit version directories in the addins root, sorted by year.
+/// Each entry is the version and the path to its directory
+pub fn get_revit_version_dirs(addins_root: &Path) -> Vec<(String, PathBuf)> {
+    let mut version_dirs = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(addins_root) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.path().is_dir() && name.len() == 4 && name.parse::<u32>().is_ok() {
+                version_dirs.push((name, entry.path()));
+            }
+        }
+    }
+    version_dirs.sort();
+    version_dirs
+}
+
+/// Returns the '.addin' files directly inside of a Revit version directory
+pub fn get_manifest_paths(version_dir: &Path) -> Vec<PathBuf> {
+    let mut manifest_paths = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(version_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("addin"))
+            {
+                manifest_paths.push(path);
+            }
+        }
+    }
+    manifest_paths.sort();
+    manifest_paths
+}
+
+/// Parses every manifest for every Revit version in the scope.
+/// Manifests that cannot be read are added to the error list as warnings
+pub fn find_installed_addins(
+    scope: &InstallScope,
+    error_list: &mut ErrorList,
+) -> Vec<InstalledAddin> {
+    let mut addins = Vec::new();
+    let addins_root = match scope.addins_root() {
+        Ok(addins_root) => addins_root,
+        Err(e) => {
+            error_list.add_warning(&format!("Could not search the {} scope: {}", scope, e));
+            return addins;
+        }
+    };
+    for (revit_version, version_dir) in get_revit_version_dirs(&addins_root) {
+        for manifest_path in get_manifest_paths(&version_dir) {
+            match addin_file::get_addin_manifest_entries(&manifest_path.to_string_lossy()) {
+                Ok(entries) => {
+                    for entry in entries {
+                        let assembly_path =
+                            addin_file::resolve_assembly_path(&manifest_path, &entry.assembly);
+                        addins.push(InstalledAddin {
+                            scope: scope.to_string(),
+                            revit_version: revit_version.clone(),
+                            manifest_path: manifest_path.clone(),
+                            addin_type: entry.addin_type,
+                            name: entry.name,
+                            vendor_id: entry.vendor_id,
+                            addin_id: entry.addin_id,
+                            full_class_name: entry.full_class_name,
+                            assembly: entry.assembly,
+                            assembly_exists: assembly_path.is_file(),
+                            assembly_path,
+                        });
+                    }
+                }
+                Err(e) => error_list.add_warning(&e.to_string()),
+            }
+        }
+    }
+    addins
+}
@@ -27,7 +27,7 @@ fn get_csproj_path(starting_dir: &str) -> Option<String> {
     utils::recursively_check_for_file(starting_dir, "*.csproj", 3, utils::SearchDirection::Child)
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct ProjectInfo {
     /// The name of the project + .csproj
     pub full_project_name: String,
@@ -61,8 +61,7 @@ pub fn get_project_info(starting_dir: &str) -> Result<ProjectInfo, String> {
 pub fn get_main_dll_path(absolute: bool, starting_dir: &str) -> Result<String, String> {
     let proj_info = get_project_info(starting_dir);
     if let Ok(proj_info) = proj_info {
-        let name = proj_info.project_name;
-        get_project_dll_path(absolute, name, starting_dir)
+        find_project_dll(absolute, &proj_info.project_name, &proj_info)
     } else {
         Err("No csproj file found".to_string())
     }
@@ -81,44 +80,49 @@ pub fn get_project_dll_path(
 ) -> Result<String, String> {
     let proj_info = get_project_info(starting_dir);
     if let Ok(proj_info) = proj_info {
-        let csproj_dir = proj_info.csproj_dir;
+        find_project_dll(absolute, &name, &proj_info)
+    } else {
+        Err("Could not find csproj file".to_string())
+    }
+}
 
-        let dll_name = name + ".dll";
-        let dll_path = utils::recursively_check_for_file(
-            &csproj_dir,
-            &dll_name,
-            3,
-            utils::SearchDirection::Child,
-        );
-        if let Some(dll_path) = dll_path {
-            if absolute {
-                // Get absolute path without the \\?\ prefix
-                match Path::new(&dll_path).absolutize() {
-                    Ok(abs_path) => Ok(abs_path.to_string_lossy().into_owned()),
-                    Err(_) => Ok(dll_path),
-                }
-            } else {
-                // Convert absolute path to relative path from the csproj directory
-                if let Ok(absolute_path) = Path::new(&dll_path).absolutize() {
-                    if let Ok(csproj_absolute) = Path::new(&csproj_dir).absolutize() {
-                        if let Some(relative_path) =
-                            pathdiff::diff_paths(absolute_path, csproj_absolute)
-                        {
-                            return Ok(relative_path.to_string_lossy().into_owned());
-                        }
+/// Same as `get_project_dll_path`, for a project that has already been found
+pub fn find_project_dll(
+    absolute: bool,
+    name: &str,
+    proj_info: &ProjectInfo,
+) -> Result<String, String> {
+    let csproj_dir = &proj_info.csproj_dir;
+
+    let dll_name = format!("{}.dll", name);
+    let dll_path =
+        utils::recursively_check_for_file(csproj_dir, &dll_name, 3, utils::SearchDirection::Child);
+    if let Some(dll_path) = dll_path {
+        if absolute {
+            // Get absolute path without the \\?\ prefix
+            match Path::new(&dll_path).absolutize() {
+                Ok(abs_path) => Ok(abs_path.to_string_lossy().into_owned()),
+                Err(_) => Ok(dll_path),
+            }
+        } else {
+            // Convert absolute path to relative path from the csproj directory
+            if let Ok(absolute_path) = Path::new(&dll_path).absolutize() {
+                if let Ok(csproj_absolute) = Path::new(csproj_dir).absolutize() {
+                    if let Some(relative_path) =
+                        pathdiff::diff_paths(absolute_path, csproj_absolute)
+                    {
+                        return Ok(relative_path.to_string_lossy().into_owned());
                     }
                 }
-                // Fall back to original path if conversion fails
-                Ok(dll_path)
             }
-        } else {
-            Err(format!(
-                "Could not find dll for project. Expected to find {}",
-                dll_name
-            ))
+            // Fall back to original path if conversion fails
+            Ok(dll_path)
         }
     } else {
-        Err("Could not find csproj file".to_string())
+        Err(format!(
+            "Could not find dll for project. Expected to find {}",
+            dll_name
+        ))
     }
 }
 
@@ -126,14 +130,18 @@ pub fn get_project_dll_path(
 ///
 /// This function will find all .dll files in the project directory and its subdirectories
 /// up to 3 levels deep, and return their absolute paths.
+/// The result includes any Revit assemblies copied into the build output; see `revit_assemblies::is_revit_assembly`
 pub fn get_all_project_dll_paths(starting_dir: &str) -> Result<Vec<String>, String> {
     let proj_info = get_project_info(starting_dir)?;
-    let csproj_dir = proj_info.csproj_dir;
+    Ok(find_all_project_dlls(&proj_info))
+}
 
+/// Same as `get_all_project_dll_paths`, for a project that has already been found
+pub fn find_all_project_dlls(proj_info: &ProjectInfo) -> Vec<String> {
     let mut absolute_dll_paths = Vec::new();
 
     // Walk through the project directory and find all .dll files
-    if let Ok(entries) = std::fs::read_dir(&csproj_dir) {
+    if let Ok(entries) = std::fs::read_dir(&proj_info.csproj_dir) {
         for entry in entries.flatten() {
             if let Ok(file_type) = entry.file_type() {
                 if file_type.is_file() {
@@ -165,7 +173,7 @@ pub fn get_all_project_dll_paths(starting_dir: &str) -> Result<Vec<String>, Stri
         }
     }
 
-    Ok(absolute_dll_paths)
+    absolute_dll_paths
 }
 
 /// Helper function to recursively find all DLL files in a directory
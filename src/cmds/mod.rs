@@ -1,3 +1,12 @@
+pub mod audit;
 pub mod build;
+pub mod check;
+pub mod config;
+pub mod conflicts;
 pub mod export;
-pub mod locate;
\ No newline at end of file
+pub mod list;
+pub mod locate;
+pub mod profile;
+pub mod uninstall;
+pub mod verify;
+pub mod web;
\ No newline at end of file
@@ -0,0 +1,153 @@
+use crate::cmds::config;
+use crate::cmds::locate;
+use crate::error::RevError;
+use crate::project_config::{self, ProjectConfig};
+use crate::state::{self, VendorProfile};
+
+// Consultants build add-ins for several clients, each with its own vendor details. A profile holds
+// one client's details, and the profile selected for a project pre-fills its '.addin' manifest
+
+/// Returns the name and details of the profile for the project in `starting_dir`: `profile_override`
+/// if given, e.g. from `--profile`, otherwise the profile in the project config. Returns None if
+/// neither selects a profile, and an error if the selected profile does not exist
+pub fn get_active_profile(
+    starting_dir: &str,
+    profile_override: Option<&str>,
+) -> Result<Option<(String, VendorProfile)>, RevError> {
+    let name = match profile_override {
+        Some(name) => name.to_string(),
+        None => {
+            let Ok(project_info) = locate::get_project_info(starting_dir) else {
+                return Ok(None);
+            };
+            match project_config::get_project_config(&project_info.csproj_dir)?.profile {
+                Some(name) => name,
+                None => return Ok(None),
+            }
+        }
+    };
+    get_profile(&name).map(Some).map_err(RevError::config)
+}
+
+/// Same as `get_active_profile`, for a project whose config has already been read
+pub fn get_project_profile(
+    project_config: &ProjectConfig,
+    profile_override: Option<&str>,
+) -> Result<Option<(String, VendorProfile)>, RevError> {
+    match profile_override.or(project_config.profile.as_deref()) {
+        Some(name) => get_profile(name).map(Some).map_err(RevError::config),
+        None => Ok(None),
+    }
+}
+
+/// Returns the saved name and details of the profile, or an error if it does not exist
+pub fn get_profile(name: &str) -> Result<(String, VendorProfile), String> {
+    let state = state::get_state_or_default();
+    match find_profile(&state.profiles, name) {
+        Some((name, profile)) => Ok((name.clone(), profile.clone())),
+        None => Err(format!(
+            "The profile '{}' does not exist. Create it with 'rev profile set {}'",
+            name, name
+        )),
+    }
+}
+
+/// Returns the profile with the name, matched regardless of case
+pub fn find_profile<'a>(
+    profiles: &'a std::collections::BTreeMap<String, VendorProfile>,
+    name: &str,
+) -> Option<(&'a String, &'a VendorProfile)> {
+    profiles
+        .iter()
+        .find(|(profile_name, _)| profile_name.eq_ignore_ascii_case(name))
+}
+
+/// Prints every profile with its vendor details
+pub fn execute_list() {
+    let state = state::get_state_or_default();
+    if state.profiles.is_empty() {
+        println!("No profiles. Create one with 'rev profile set <name> --vendor-id <id>'");
+        return;
+    }
+    for (name, profile) in &state.profiles {
+        println!("{}:", name);
+        println!("  Vendor ID:   {}", profile.vendor_id);
+        println!("  Description: {}", profile.vendor_description);
+        println!("  Email:       {}", profile.email_address);
+    }
+}
+
+/// Creates a profile, or changes the given fields of an existing one. Returns false if the
+/// profile was not saved
+pub async fn execute_set(
+    name: &str,
+    vendor_id: Option<&str>,
+    vendor_description: Option<&str>,
+    email_address: Option<&str>,
+) -> bool {
+    if name.trim().is_empty() {
+        println!("Error: the profile needs a name");
+        return false;
+    }
+    if let Some(email_address) = email_address
+        && !config::is_email_address(email_address)
+    {
+        println!("Error: '{}' is not an email address", email_address);
+        return false;
+    }
+    // An existing profile keeps the case of its name
+    let mut saved_name = name.trim().to_string();
+    let result = state::update_state(|state| {
+        if let Some((existing, _)) = find_profile(&state.profiles, &saved_name) {
+            saved_name = existing.clone();
+        }
+        let profile = state.profiles.entry(saved_name.clone()).or_default();
+        if let Some(vendor_id) = vendor_id {
+            profile.vendor_id = vendor_id.to_string();
+        }
+        if let Some(vendor_description) = vendor_description {
+            profile.vendor_description = vendor_description.to_string();
+        }
+        if let Some(email_address) = email_address {
+            profile.email_address = email_address.to_string();
+        }
+    })
+    .await;
+    match result {
+        Ok(_) => {
+            println!("Saved profile {}", saved_name);
+            true
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// Deletes a profile. Returns false if it does not exist or could not be deleted
+pub async fn execute_remove(name: &str) -> bool {
+    let mut removed = false;
+    let result = state::update_state(|state| {
+        if let Some((existing, _)) = find_profile(&state.profiles, name) {
+            let existing = existing.clone();
+            state.profiles.remove(&existing);
+            removed = true;
+        }
+    })
+    .await;
+    match result {
+        Ok(_) if removed => {
+            println!("Removed profile {}", name);
+            true
+        }
+        Ok(_) => {
+            println!("Error: the profile '{}' does not exist", name);
+            false
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cmds::export::scope::{self, InstallScope};
+use crate::cmds::export::{self, receipt};
+use crate::cmds::locate;
+
+/// Removes the current project's addin from the Revit addins directory for `for_version` in `scope`.
+///
+/// This removes the '.addin' file, the addin folder and the install receipt
+pub fn execute(starting_dir: &str, for_version: &str, scope: &InstallScope) {
+    let project_info = match locate::get_project_info(starting_dir) {
+        Ok(project_info) => project_info,
+        Err(e) => {
+            println!("Error getting project info: {}", e);
+            return;
+        }
+    };
+    let addins_dir = match export::get_revit_addins_path(for_version, scope) {
+        Ok(addins_dir) => addins_dir,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+
+    let addin_name = &project_info.project_name;
+    let addin_file_path = addins_dir.join(format!("{}.addin", addin_name));
+    let addin_dir = addins_dir.join(addin_name);
+    let receipt_path = receipt::get_receipt_path(&addins_dir, addin_name);
+
+    let mut removed_anything = false;
+    for file_path in [&addin_file_path, &receipt_path] {
+        if file_path.exists() {
+            match std::fs::remove_file(file_path) {
+                Ok(_) => {
+                    println!("Removed {}", file_path.to_string_lossy());
+                    removed_anything = true;
+                }
+                Err(e) => println!("Error removing {}: {}", file_path.to_string_lossy(), e),
+            }
+        }
+    }
+    if addin_dir.exists() {
+        match std::fs::remove_dir_all(&addin_dir) {
+            Ok(_) => {
+                println!("Removed {}", addin_dir.to_string_lossy());
+                removed_anything = true;
+            }
+            Err(e) => println!("Error removing {}: {}", addin_dir.to_string_lossy(), e),
+        }
+    }
+    if !removed_anything {
+        println!(
+            "{} is not installed in the {} scope for Revit {}",
+            addin_name, scope, for_version
+        );
+    }
+
+    for other in scope::find_other_installations(addin_name, for_version, scope) {
+        println!(
+            "Warning: {} is still installed in the {} scope for Revit {}",
+            addin_name, other, for_version
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::cmds::export::scope::InstallScope;
+use crate::cmds::export::{self, receipt};
+use crate::cmds::locate;
+use std::path::{Path, PathBuf};
+
+/// Re-hashes the current project's addin in the Revit addins directory for `for_version` in
+/// `scope`, and prints any files that have drifted from its install receipt. Returns true if the
+/// installation matches the receipt
+pub fn execute(starting_dir: &str, for_version: &str, scope: &InstallScope) -> bool {
+    match get_project_receipt_path(starting_dir, for_version, scope) {
+        Ok(receipt_path) => execute_receipt(&receipt_path),
+        Err(e) => {
+            println!("Could not find install receipt: {}", e);
+            false
+        }
+    }
+}
+
+/// Same as `execute`, for the installation recorded in the receipt at `receipt_path`
+pub fn execute_receipt(receipt_path: &Path) -> bool {
+    let report = match receipt::verify_installation(receipt_path) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("Could not verify installation: {}", e);
+            return false;
+        }
+    };
+
+    for path in &report.modified {
+        println!("Modified: {}", path);
+    }
+    for path in &report.missing {
+        println!("Missing: {}", path);
+    }
+    for path in &report.extra {
+        println!("Extra: {}", path);
+    }
+    if report.has_drift() {
+        println!(
+            "Installation has drifted from its receipt: {} modified, {} missing, {} extra",
+            report.modified.len(),
+            report.missing.len(),
+            report.extra.len()
+        );
+        false
+    } else {
+        println!("Installation matches its receipt");
+        true
+    }
+}
+
+fn get_project_receipt_path(
+    starting_dir: &str,
+    for_version: &str,
+    scope: &InstallScope,
+) -> Result<PathBuf, String> {
+    let project_info = locate::get_project_info(starting_dir)?;
+    let addins_dir = export::get_revit_addins_path(for_version, scope)?;
+    Ok(receipt::get_receipt_path(
+        &addins_dir,
+        &project_info.project_name,
+    ))
+}
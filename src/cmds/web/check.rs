@@ -0,0 +1,369 @@
+use crate::cmds::export::web_app;
+use crate::cmds::locate;
+use crate::project_config;
+use crate::utils;
+use crate::utils::error_list::ErrorList;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// WebView2 loads the exported UI from disk, so anything that needs a web server, or that uses a URL
+// starting with '/', fails at runtime inside of Revit. These checks look for the known causes in the
+// frontend config, its source files and the exported HTML
+
+const NEXT_CONFIG_FILES: [&str; 4] = [
+    "next.config.js",
+    "next.config.mjs",
+    "next.config.cjs",
+    "next.config.ts",
+];
+const VITE_CONFIG_FILES: [&str; 6] = [
+    "vite.config.js",
+    "vite.config.mjs",
+    "vite.config.cjs",
+    "vite.config.ts",
+    "vite.config.mts",
+    "vite.config.cts",
+];
+const SOURCE_EXTENSIONS: [&str; 8] = ["js", "jsx", "mjs", "ts", "tsx", "mts", "vue", "svelte"];
+/// Folders that contain dependencies or build output rather than the frontend's own source
+pub const NON_SOURCE_DIRS: [&str; 6] = ["node_modules", ".next", "out", "dist", "build", ".git"];
+
+/// Checks every frontend of the project and prints the problems found, with how to fix them.
+/// Returns true if no errors were found
+pub fn execute(starting_dir: &str) -> bool {
+    let csproj_dir = locate::get_project_info(starting_dir)
+        .map(|project_info| project_info.csproj_dir)
+        .unwrap_or_else(|_| starting_dir.to_string());
+    let project_config = match project_config::get_project_config(&csproj_dir) {
+        Ok(project_config) => project_config,
+        Err(e) => {
+            println!("Error reading project config: {}", e);
+            return false;
+        }
+    };
+    let frontends =
+        match web_app::get_frontends(starting_dir, Path::new(&csproj_dir), &project_config.web) {
+            Ok(frontends) => frontends,
+            Err(e) => {
+                println!("Error reading frontend config: {}", e);
+                return false;
+            }
+        };
+    if frontends.is_empty() {
+        println!("No web app found");
+        return true;
+    }
+
+    let mut success = true;
+    for frontend in &frontends {
+        println!("{}:", frontend.dir.to_string_lossy());
+        let errors = check_web_app(&frontend.dir, frontend.output_dir.as_deref());
+        for error in errors.view_errors() {
+            println!("  Error: {}", error);
+        }
+        for warning in errors.view_warnings() {
+            println!("  Warning: {}", warning);
+        }
+        if !errors.has_errors() && !errors.has_warnings() {
+            println!("  No problems found");
+        }
+        success &= !errors.has_errors();
+    }
+    success
+}
+
+/// Checks a frontend for anything that stops it from working when loaded from disk by WebView2.
+/// Errors will break the UI inside of Revit, while warnings are likely to
+///
+/// `output_dir` is the folder the build writes static files to. It is detected when not given,
+/// and the exported HTML is only checked if the frontend has been built
+pub fn check_web_app(web_app_dir: &Path, output_dir: Option<&Path>) -> ErrorList {
+    let mut error_list = ErrorList::new();
+    let dependencies = get_dependencies(web_app_dir);
+    let source_files = get_source_files(web_app_dir);
+
+    if dependencies.iter().any(|name| name == "next") {
+        check_next_app(web_app_dir, &source_files, &mut error_list);
+    }
+    if dependencies.iter().any(|name| name == "vite") {
+        check_vite_app(web_app_dir, &mut error_list);
+    }
+    check_client_routing(web_app_dir, &source_files, &mut error_list);
+
+    let output_dir = output_dir
+        .map(Path::to_path_buf)
+        .or_else(|| web_app::find_output_dir(web_app_dir));
+    match output_dir {
+        Some(output_dir) if output_dir.is_dir() => check_exported_html(&output_dir, &mut error_list),
+        _ => error_list.add_warning(
+            "No exported files were found, so the HTML was not checked. Build the web app and run the check again",
+        ),
+    }
+    error_list
+}
+
+fn check_next_app(web_app_dir: &Path, source_files: &[PathBuf], error_list: &mut ErrorList) {
+    let Some((config_name, config)) = read_config(web_app_dir, &NEXT_CONFIG_FILES) else {
+        error_list.add_error(
+            "There is no next.config.js, so `next build` does not write static files. Create one with `output: 'export'`, `assetPrefix: './'` and `images: { unoptimized: true }`",
+        );
+        return;
+    };
+
+    if !has_setting(&config, "output", &["export"]) {
+        error_list.add_error(&format!(
+            "{} does not set `output: 'export'`, so `next build` does not write static files. Add `output: 'export'`",
+            config_name
+        ));
+    }
+    if !has_setting(&config, "assetPrefix", &["./", "."]) {
+        error_list.add_error(&format!(
+            "{} does not set `assetPrefix: './'`, so pages load their scripts from absolute /_next/ URLs that WebView2 resolves to the root of the drive. Add `assetPrefix: './'`",
+            config_name
+        ));
+    }
+    let uses_next_image = source_files.iter().any(|path| {
+        std::fs::read_to_string(path).is_ok_and(|source| source.contains("next/image"))
+    });
+    if uses_next_image && !has_setting(&config, "unoptimized", &["true"]) {
+        error_list.add_error(&format!(
+            "next/image is used, but image optimization needs the Next.js server. Add `images: {{ unoptimized: true }}` to {}",
+            config_name
+        ));
+    }
+    for server_feature in ["rewrites", "redirects", "headers"] {
+        if config.contains(&format!("{}(", server_feature))
+            || config.contains(&format!("{}:", server_feature))
+        {
+            error_list.add_warning(&format!(
+                "{} sets `{}`, which need the Next.js server and are ignored by a static export",
+                config_name, server_feature
+            ));
+        }
+    }
+
+    let mut pages = 0;
+    for path in source_files {
+        let relative = path
+            .strip_prefix(web_app_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let relative = relative.strip_prefix("src/").unwrap_or(&relative);
+        let file_stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        if relative.starts_with("pages/api/")
+            || (relative.starts_with("app/") && file_stem == "route")
+        {
+            error_list.add_error(&format!(
+                "{} is an API route, which needs the Next.js server. Move the logic into the add-in and call it through the WebView2 message bridge",
+                relative
+            ));
+        } else if !relative.contains('/') && file_stem == "middleware" {
+            error_list.add_error(&format!(
+                "{} needs the Next.js server and does not run in a static export. Remove it",
+                relative
+            ));
+        } else if (relative.starts_with("app/") && file_stem == "page")
+            || (relative.starts_with("pages/") && !file_stem.starts_with('_'))
+        {
+            pages += 1;
+            if relative.contains('[') {
+                error_list.add_warning(&format!(
+                    "{} is a dynamic route. Only the paths returned by generateStaticParams are exported, and the others will not load",
+                    relative
+                ));
+            }
+        }
+    }
+    if pages > 1 {
+        error_list.add_warning(&format!(
+            "The app has {} pages. Links between them use absolute URLs, which do not resolve when the UI is loaded from disk. Keep the UI on one page and switch views with state, or serve the folder through a WebView2 virtual host mapping",
+            pages
+        ));
+    }
+}
+
+fn check_vite_app(web_app_dir: &Path, error_list: &mut ErrorList) {
+    match read_config(web_app_dir, &VITE_CONFIG_FILES) {
+        Some((config_name, config)) => {
+            if !has_setting(&config, "base", &["./", ""]) {
+                error_list.add_error(&format!(
+                    "{} does not set `base: './'`, so the built HTML loads its assets from absolute URLs that WebView2 resolves to the root of the drive. Add `base: './'`",
+                    config_name
+                ));
+            }
+        }
+        None => error_list.add_error(
+            "There is no vite.config, so Vite uses `base: '/'` and the built HTML loads its assets from absolute URLs. Create a vite.config.ts with `base: './'`",
+        ),
+    }
+}
+
+/// Routers that change the path of the URL need a server to answer for every route
+fn check_client_routing(web_app_dir: &Path, source_files: &[PathBuf], error_list: &mut ErrorList) {
+    let history_routers = [
+        ("createBrowserRouter", "createHashRouter"),
+        ("<BrowserRouter", "<HashRouter>"),
+        ("createWebHistory(", "createWebHashHistory()"),
+        ("createBrowserHistory(", "createHashHistory()"),
+    ];
+    for path in source_files {
+        let Ok(source) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(web_app_dir).unwrap_or(path);
+        for (history_router, hash_router) in history_routers {
+            if source.contains(history_router) {
+                error_list.add_warning(&format!(
+                    "{} uses {}, which changes the URL path and breaks on reload when the UI is loaded from disk. Use {} instead",
+                    relative.to_string_lossy(),
+                    history_router.trim_start_matches('<').trim_end_matches('('),
+                    hash_router
+                ));
+            }
+        }
+        if source.contains("provideRouter(") && !source.contains("withHashLocation") {
+            error_list.add_warning(&format!(
+                "{} sets up the Angular router without hash locations, which breaks on reload when the UI is loaded from disk. Add withHashLocation() to provideRouter",
+                relative.to_string_lossy()
+            ));
+        }
+    }
+}
+
+/// Reports URLs in the exported HTML that start with '/', which WebView2 resolves to the root of
+/// the drive when the page is loaded from disk
+fn check_exported_html(output_dir: &Path, error_list: &mut ErrorList) {
+    // Grouped so that an export with many pages reports each kind of problem once
+    let mut absolute_urls: BTreeMap<&str, (usize, String, PathBuf)> = BTreeMap::new();
+    for path in utils::get_all_files_in_directory(output_dir) {
+        if !path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("html"))
+        {
+            continue;
+        }
+        let Ok(html) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for url in find_absolute_urls(&html) {
+            let kind = if url.starts_with("/_next/") {
+                "next"
+            } else if html.contains(&format!("<base href=\"{}\"", url)) {
+                "base"
+            } else {
+                "other"
+            };
+            let relative = path.strip_prefix(output_dir).unwrap_or(&path).to_path_buf();
+            let entry = absolute_urls.entry(kind).or_insert((0, url, relative));
+            entry.0 += 1;
+        }
+    }
+
+    for (kind, (count, example, file)) in absolute_urls {
+        let file = file.to_string_lossy();
+        let message = match kind {
+            "next" => format!(
+                "The exported HTML loads {} assets from absolute /_next/ URLs, e.g. {} in {}. Add `assetPrefix: './'` to next.config.js",
+                count, example, file
+            ),
+            "base" => format!(
+                "{} sets <base href=\"{}\">, which makes every relative URL absolute. Build with `--base-href ./` for Angular, or remove the tag",
+                file, example
+            ),
+            _ => format!(
+                "The exported HTML has {} absolute URLs, e.g. {} in {}. Use relative URLs, such as `base: './'` for Vite",
+                count, example, file
+            ),
+        };
+        error_list.add_error(&message);
+    }
+}
+
+/// Returns the values of `src` and `href` attributes that start with a single '/'
+fn find_absolute_urls(html: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    for attribute in ["src=", "href="] {
+        for (index, _) in html.match_indices(attribute) {
+            let rest = &html[index + attribute.len()..];
+            let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                continue;
+            };
+            let value = rest[1..].split(quote).next().unwrap_or_default();
+            // '//' is a protocol relative URL to another host, which is not a file on disk
+            if value.starts_with('/') && !value.starts_with("//") {
+                urls.push(value.to_string());
+            }
+        }
+    }
+    urls
+}
+
+/// Returns the names of the dependencies and dev dependencies in package.json
+fn get_dependencies(web_app_dir: &Path) -> Vec<String> {
+    let Ok(contents) = std::fs::read_to_string(web_app_dir.join("package.json")) else {
+        return Vec::new();
+    };
+    let Ok(package_json) = serde_json::from_str::<serde_json::Value>(&contents) else {
+        return Vec::new();
+    };
+    ["dependencies", "devDependencies"]
+        .iter()
+        .filter_map(|key| package_json.get(key)?.as_object())
+        .flat_map(|dependencies| dependencies.keys().cloned())
+        .collect()
+}
+
+/// Returns the first config file that exists, with its contents stripped of whitespace and line
+/// comments so that settings can be matched regardless of formatting
+fn read_config(web_app_dir: &Path, file_names: &[&str]) -> Option<(String, String)> {
+    file_names.iter().find_map(|file_name| {
+        let contents = std::fs::read_to_string(web_app_dir.join(file_name)).ok()?;
+        let config: String = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("//"))
+            .flat_map(|line| line.chars())
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        Some((file_name.to_string(), config))
+    })
+}
+
+/// Returns true if the config sets `key` to one of `values`, written as a string or a literal
+fn has_setting(config: &str, key: &str, values: &[&str]) -> bool {
+    values.iter().any(|value| {
+        ["'", "\"", "`", ""].iter().any(|quote| {
+            // Unquoted values only make sense for literals such as true
+            (!quote.is_empty() || !value.is_empty())
+                && config.contains(&format!("{}:{}{}{}", key, quote, value, quote))
+        })
+    })
+}
+
+/// Returns the frontend's own source files, skipping dependencies and build output
+fn get_source_files(web_app_dir: &Path) -> Vec<PathBuf> {
+    let mut source_files = Vec::new();
+    let mut dirs = vec![web_app_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                if !NON_SOURCE_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|extension| {
+                SOURCE_EXTENSIONS.contains(&extension.to_string_lossy().as_ref())
+            }) && !name.contains(".config.")
+            {
+                source_files.push(path);
+            }
+        }
+    }
+    source_files.sort();
+    source_files
+}
@@ -0,0 +1,421 @@
+use crate::cmds::export::payload;
+use crate::cmds::locate;
+use crate::project_config::{self, PropertyNaming, TypesConfig};
+use crate::utils::csharp_types::{self, CSharpClass, CSharpDeclaration, CSharpEnum, TypeReference};
+use crate::utils::error_list::ErrorList;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+// The addin and its web UI exchange JSON messages whose shapes are declared as C# classes. The
+// TypeScript types are generated from those declarations so that both sides stay in sync
+
+/// Folders below the csproj directory that never contain the project's own source files
+const SKIPPED_DIRS: [&str; 5] = ["bin", "obj", "node_modules", ".git", ".vs"];
+
+/// Generates the TypeScript types configured for the project and prints the result.
+/// Returns true if no errors were found
+pub fn execute(starting_dir: &str) -> bool {
+    let project_info = match locate::get_project_info(starting_dir) {
+        Ok(project_info) => project_info,
+        Err(e) => {
+            println!("Could not find project: {}", e);
+            return false;
+        }
+    };
+    let project_config = match project_config::get_project_config(&project_info.csproj_dir) {
+        Ok(project_config) => project_config,
+        Err(e) => {
+            println!("Error reading project config: {}", e);
+            return false;
+        }
+    };
+    let Some(types_config) = project_config.web.types else {
+        println!(
+            "No types are configured. Add \"types\" with the C# \"sources\" and the TypeScript \"output\" to the \"web\" section of {}",
+            project_config::PROJECT_CONFIG_FILE_NAME
+        );
+        return false;
+    };
+
+    let errors = generate_types(Path::new(&project_info.csproj_dir), &types_config);
+    for error in errors.view_errors() {
+        println!("Error: {}", error);
+    }
+    for warning in errors.view_warnings() {
+        println!("Warning: {}", warning);
+    }
+    !errors.has_errors()
+}
+
+/// Reads the C# files matched by the config and writes the TypeScript types to its output file.
+/// The file is only written when its contents change, so that web app dev servers do not reload
+///
+/// Properties with types that have no TypeScript equivalent are written as `unknown`, with a warning
+pub fn generate_types(csproj_dir: &Path, types_config: &TypesConfig) -> ErrorList {
+    let mut error_list = ErrorList::new();
+    if types_config.output.is_empty() {
+        error_list.add_error("The \"output\" file for the generated types is not set");
+        return error_list;
+    }
+    let source_files = match find_source_files(csproj_dir, &types_config.sources) {
+        Ok(source_files) => source_files,
+        Err(e) => {
+            error_list.add_error(&e);
+            return error_list;
+        }
+    };
+    if source_files.is_empty() {
+        error_list.add_error(&format!(
+            "No C# files match the type sources: {}",
+            types_config.sources.join(", ")
+        ));
+        return error_list;
+    }
+
+    let mut declarations = Vec::new();
+    let mut declared_in: HashMap<String, PathBuf> = HashMap::new();
+    for source_file in &source_files {
+        let relative = pathdiff::diff_paths(source_file, csproj_dir).unwrap_or(source_file.clone());
+        let parsed = std::fs::read_to_string(source_file)
+            .map_err(|e| e.to_string())
+            .and_then(|source| csharp_types::parse_declarations(&source));
+        match parsed {
+            Ok(parsed) => {
+                for declaration in parsed {
+                    if let Some(other) =
+                        declared_in.insert(declaration.name().to_string(), relative.clone())
+                    {
+                        error_list.add_error(&format!(
+                            "{} is declared in both {} and {}. TypeScript types need unique names",
+                            declaration.name(),
+                            other.to_string_lossy(),
+                            relative.to_string_lossy()
+                        ));
+                    }
+                    declarations.push(declaration);
+                }
+            }
+            Err(e) => error_list.add_error(&format!(
+                "Could not read the types in {}: {}",
+                relative.to_string_lossy(),
+                e
+            )),
+        }
+    }
+    if error_list.has_errors() {
+        return error_list;
+    }
+
+    let typescript = generate_typescript(&declarations, types_config, &mut error_list);
+    let output_path = csproj_dir.join(&types_config.output);
+    if std::fs::read_to_string(&output_path).is_ok_and(|existing| existing == typescript) {
+        error_list.add_warning(&format!("{} is up to date", output_path.to_string_lossy()));
+        return error_list;
+    }
+    let written = output_path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&output_path, &typescript));
+    match written {
+        Ok(_) => error_list.add_warning(&format!(
+            "Wrote {} types to {}",
+            declarations.len(),
+            output_path.to_string_lossy()
+        )),
+        Err(e) => error_list.add_error(&format!(
+            "Error writing {}: {}",
+            output_path.to_string_lossy(),
+            e
+        )),
+    }
+    error_list
+}
+
+/// Returns the TypeScript file for the declarations: an interface for every class, record,
+/// struct and interface, and an enum or a union of names for every enum
+pub fn generate_typescript(
+    declarations: &[CSharpDeclaration],
+    types_config: &TypesConfig,
+    error_list: &mut ErrorList,
+) -> String {
+    let known_types: HashMap<&str, &CSharpDeclaration> = declarations
+        .iter()
+        .map(|declaration| (declaration.name(), declaration))
+        .collect();
+    let mut typescript = format!(
+        "// Generated by `rev web gen-types` from {}. Do not edit this file by hand\n",
+        types_config.sources.join(", ")
+    );
+    for declaration in declarations {
+        typescript.push('\n');
+        match declaration {
+            CSharpDeclaration::Class(class) => typescript.push_str(&generate_interface(
+                class,
+                &known_types,
+                types_config.property_naming,
+                error_list,
+            )),
+            CSharpDeclaration::Enum(csharp_enum) => typescript.push_str(&generate_enum(
+                csharp_enum,
+                types_config.enums_as_strings || csharp_enum.serialized_as_string,
+            )),
+        }
+    }
+    typescript
+}
+
+fn generate_interface(
+    class: &CSharpClass,
+    known_types: &HashMap<&str, &CSharpDeclaration>,
+    property_naming: PropertyNaming,
+    error_list: &mut ErrorList,
+) -> String {
+    let mut interface = format!("export interface {}", class.name);
+    if !class.type_parameters.is_empty() {
+        interface.push_str(&format!("<{}>", class.type_parameters.join(", ")));
+    }
+    // Interfaces from outside of the sources, e.g. IDisposable, add nothing to the JSON
+    let mut unknown_types = BTreeSet::new();
+    let base_types: Vec<String> = class
+        .base_types
+        .iter()
+        .filter(|base_type| {
+            matches!(base_type, TypeReference::Named { name, .. }
+                if matches!(known_types.get(name.as_str()), Some(CSharpDeclaration::Class(_))))
+        })
+        .map(|base_type| {
+            to_typescript_type(
+                base_type,
+                known_types,
+                &class.type_parameters,
+                &mut unknown_types,
+            )
+        })
+        .collect();
+    if !base_types.is_empty() {
+        interface.push_str(&format!(" extends {}", base_types.join(", ")));
+    }
+    interface.push_str(" {\n");
+
+    for property in &class.properties {
+        let name = match (&property.json_name, property_naming) {
+            (Some(json_name), _) => json_name.clone(),
+            (None, PropertyNaming::Preserve) => property.name.clone(),
+            (None, PropertyNaming::CamelCase) => to_camel_case(&property.name),
+        };
+        let typescript_type = to_typescript_type(
+            &property.type_reference,
+            known_types,
+            &class.type_parameters,
+            &mut unknown_types,
+        );
+        interface.push_str(&format!(
+            "  {}: {};\n",
+            to_property_key(&name),
+            typescript_type
+        ));
+    }
+    interface.push_str("}\n");
+
+    for unknown_type in unknown_types {
+        error_list.add_warning(&format!(
+            "{} uses {}, which has no JSON equivalent and is not declared in the type sources. It was written as 'unknown'",
+            class.name, unknown_type
+        ));
+    }
+    interface
+}
+
+fn generate_enum(csharp_enum: &CSharpEnum, as_strings: bool) -> String {
+    if as_strings {
+        let names: Vec<String> = csharp_enum
+            .members
+            .iter()
+            .map(|member| {
+                serde_json::to_string(member.json_name.as_ref().unwrap_or(&member.name)).unwrap()
+            })
+            .collect();
+        let union = if names.is_empty() {
+            "never".to_string()
+        } else {
+            names.join(" | ")
+        };
+        return format!("export type {} = {};\n", csharp_enum.name, union);
+    }
+    let mut typescript = format!("export enum {} {{\n", csharp_enum.name);
+    for member in &csharp_enum.members {
+        match &member.value {
+            Some(value) => typescript.push_str(&format!("  {} = {},\n", member.name, value)),
+            None => typescript.push_str(&format!("  {},\n", member.name)),
+        }
+    }
+    typescript.push_str("}\n");
+    typescript
+}
+
+/// Returns the TypeScript for a C# type, as System.Text.Json serializes it. Types that are not
+/// declared in the sources and have no JSON equivalent are added to `unknown_types`
+fn to_typescript_type(
+    type_reference: &TypeReference,
+    known_types: &HashMap<&str, &CSharpDeclaration>,
+    type_parameters: &[String],
+    unknown_types: &mut BTreeSet<String>,
+) -> String {
+    let mut convert = |type_reference: &TypeReference| {
+        to_typescript_type(type_reference, known_types, type_parameters, unknown_types)
+    };
+    match type_reference {
+        TypeReference::Nullable(inner) => format!("{} | null", convert(inner)),
+        TypeReference::Array(element) => to_array_type(&convert(element)),
+        TypeReference::Tuple(_) => {
+            // Tuples store their values in fields, which System.Text.Json does not serialize
+            unknown_types.insert("a tuple".to_string());
+            "unknown".to_string()
+        }
+        TypeReference::Named { name, arguments } => match (name.as_str(), arguments.as_slice()) {
+            (name, []) if type_parameters.iter().any(|parameter| parameter == name) => {
+                name.to_string()
+            }
+            (
+                "string" | "String" | "char" | "Char" | "Guid" | "DateTime" | "DateTimeOffset"
+                | "DateOnly" | "TimeOnly" | "TimeSpan" | "Uri" | "Version",
+                [],
+            ) => "string".to_string(),
+            (
+                "byte" | "sbyte" | "short" | "ushort" | "int" | "uint" | "long" | "ulong" | "float"
+                | "double" | "decimal" | "Byte" | "SByte" | "Int16" | "UInt16" | "Int32" | "UInt32"
+                | "Int64" | "UInt64" | "Single" | "Double" | "Decimal",
+                [],
+            ) => "number".to_string(),
+            ("bool" | "Boolean", []) => "boolean".to_string(),
+            ("object" | "Object" | "dynamic" | "JsonElement" | "JsonNode" | "JsonDocument", []) => {
+                "unknown".to_string()
+            }
+            ("JsonObject", []) => "Record<string, unknown>".to_string(),
+            ("JsonArray", []) => "unknown[]".to_string(),
+            ("Nullable", [inner]) => format!("{} | null", convert(inner)),
+            (
+                "List"
+                | "IList"
+                | "IEnumerable"
+                | "ICollection"
+                | "IReadOnlyList"
+                | "IReadOnlyCollection"
+                | "Collection"
+                | "ReadOnlyCollection"
+                | "ObservableCollection"
+                | "HashSet"
+                | "ISet"
+                | "IReadOnlySet"
+                | "SortedSet"
+                | "Queue"
+                | "Stack"
+                | "LinkedList"
+                | "ImmutableArray"
+                | "ImmutableList"
+                | "ImmutableHashSet"
+                | "IImmutableList"
+                | "IAsyncEnumerable",
+                [element],
+            ) => to_array_type(&convert(element)),
+            (
+                "Dictionary"
+                | "IDictionary"
+                | "IReadOnlyDictionary"
+                | "SortedDictionary"
+                | "ConcurrentDictionary"
+                | "ImmutableDictionary"
+                | "IImmutableDictionary"
+                | "ReadOnlyDictionary",
+                [_, value],
+            ) => format!("Record<string, {}>", convert(value)),
+            (name, arguments) if known_types.contains_key(name) => {
+                if arguments.is_empty() {
+                    name.to_string()
+                } else {
+                    let arguments: Vec<String> = arguments.iter().map(&mut convert).collect();
+                    format!("{}<{}>", name, arguments.join(", "))
+                }
+            }
+            (name, _) => {
+                unknown_types.insert(name.to_string());
+                "unknown".to_string()
+            }
+        },
+    }
+}
+
+fn to_array_type(element: &str) -> String {
+    if element.contains(' ') {
+        format!("({})[]", element)
+    } else {
+        format!("{}[]", element)
+    }
+}
+
+/// Converts a property name the way JsonNamingPolicy.CamelCase does: the leading run of capitals
+/// is lowered, except for the last one when it starts the next word, e.g. "URLValue" to "urlValue"
+fn to_camel_case(name: &str) -> String {
+    let mut chars: Vec<char> = name.chars().collect();
+    for i in 0..chars.len() {
+        if i == 1 && !chars[i].is_uppercase() {
+            break;
+        }
+        let has_next = i + 1 < chars.len();
+        if i > 0 && has_next && !chars[i + 1].is_uppercase() {
+            if chars[i + 1] == ' ' {
+                chars[i] = chars[i].to_lowercase().next().unwrap_or(chars[i]);
+            }
+            break;
+        }
+        chars[i] = chars[i].to_lowercase().next().unwrap_or(chars[i]);
+    }
+    chars.into_iter().collect()
+}
+
+/// Quotes a property name that is not a valid TypeScript identifier
+fn to_property_key(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap()
+    }
+}
+
+/// Returns the C# files below `csproj_dir` matching the patterns, sorted by path. Build output and
+/// dependency folders are skipped
+fn find_source_files(csproj_dir: &Path, sources: &[String]) -> Result<Vec<PathBuf>, String> {
+    let patterns = payload::compile_patterns(sources)?;
+    let mut source_files = Vec::new();
+    let mut dirs = vec![csproj_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if let Some(relative) = pathdiff::diff_paths(&path, csproj_dir)
+                && payload::matches_any(&patterns, &payload::to_glob_path(&relative))
+            {
+                source_files.push(path);
+            }
+        }
+    }
+    source_files.sort();
+    Ok(source_files)
+}
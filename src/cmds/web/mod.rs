@@ -0,0 +1,3 @@
+pub mod check;
+pub mod gen_types;
+pub mod serve;
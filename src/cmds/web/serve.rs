@@ -0,0 +1,538 @@
+use crate::cancel::CancellationToken;
+use crate::cmds::export::get_revit_addins_path;
+use crate::cmds::export::scope::InstallScope;
+use crate::cmds::export::web_app::{self, Frontend};
+use crate::cmds::locate;
+use crate::cmds::web::check::NON_SOURCE_DIRS;
+use crate::events::ConsoleSink;
+use crate::project_config;
+use crate::utils::error_list::ErrorList;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// WebView2 loads the exported UI from the addin directory with a file:// URL. The preview server
+// mounts the addin directory at '/<addin>/', so relative URLs resolve the same way they do inside
+// of Revit, while URLs starting with '/' escape the addin folder and fail just like they would there
+
+/// How often the frontend source files are checked for changes when watching
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// The largest request head that is read before the connection is dropped
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Serves the exported web UI of the project from the Revit addins directory until the process
+/// is stopped. Returns false if the server could not be started
+///
+/// With `watch`, every frontend is exported first and then again whenever its source files change
+pub async fn execute(
+    starting_dir: &str,
+    for_version: &str,
+    scope: &InstallScope,
+    port: u16,
+    watch: bool,
+) -> bool {
+    let project_info = match locate::get_project_info(starting_dir) {
+        Ok(project_info) => project_info,
+        Err(e) => {
+            println!("Could not find project: {}", e);
+            return false;
+        }
+    };
+    let project_config = match project_config::get_project_config(&project_info.csproj_dir) {
+        Ok(project_config) => project_config,
+        Err(e) => {
+            println!("Error reading project config: {}", e);
+            return false;
+        }
+    };
+    let frontends = match web_app::get_frontends(
+        starting_dir,
+        Path::new(&project_info.csproj_dir),
+        &project_config.web,
+    ) {
+        Ok(frontends) => frontends,
+        Err(e) => {
+            println!("Error reading frontend config: {}", e);
+            return false;
+        }
+    };
+    if frontends.is_empty() {
+        println!("No web app found");
+        return false;
+    }
+    let addin_dir = match get_revit_addins_path(for_version, scope) {
+        Ok(addins_path) => addins_path.join(&project_info.project_name),
+        Err(e) => {
+            println!("Error: {}", e);
+            return false;
+        }
+    };
+
+    if watch {
+        for frontend in &frontends {
+            rebuild(frontend, &addin_dir).await;
+        }
+    }
+    for frontend in &frontends {
+        if !addin_dir
+            .join(&frontend.destination)
+            .join("index.html")
+            .is_file()
+        {
+            println!(
+                "Warning: {} has no index.html. Run 'rev export' or 'rev web serve --watch' to export the web app first",
+                addin_dir.join(&frontend.destination).to_string_lossy()
+            );
+        }
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("Could not listen on port {}: {}", port, e);
+            return false;
+        }
+    };
+    let server = StaticServer::new(
+        &project_info.project_name,
+        &addin_dir,
+        &frontends[0].destination,
+    );
+    println!(
+        "Serving {} at http://localhost:{}{}",
+        addin_dir.to_string_lossy(),
+        port,
+        server.url_prefix
+    );
+    for frontend in &frontends {
+        println!(
+            "  {}: http://localhost:{}{}{}/index.html",
+            frontend.destination, port, server.url_prefix, frontend.destination
+        );
+    }
+    let server = tokio::spawn(serve(listener, Arc::new(server)));
+
+    if watch {
+        println!("Watching the web app source files for changes. Press Ctrl+C to stop");
+        watch_frontends(&frontends, &addin_dir).await;
+    } else {
+        println!("Press Ctrl+C to stop");
+    }
+    server.await.is_ok()
+}
+
+/// Serves the files in an addin directory under '/<addin>/', rejecting everything outside of it
+#[derive(Debug, Clone)]
+pub struct StaticServer {
+    /// The URL path the addin directory is mounted at, e.g. '/MyAddin/'
+    pub url_prefix: String,
+    pub addin_dir: PathBuf,
+    /// The folder of the frontend that '/' redirects to
+    pub default_destination: String,
+}
+
+/// The result of resolving a request against the addin directory
+#[derive(Debug, PartialEq)]
+pub enum Resolved {
+    File(PathBuf),
+    Redirect(String),
+    /// The file does not exist in the addin directory
+    NotFound,
+    /// The URL points outside of the addin directory, which only works when served from a root
+    OutsideAddin,
+}
+
+impl StaticServer {
+    pub fn new(addin_name: &str, addin_dir: &Path, default_destination: &str) -> StaticServer {
+        StaticServer {
+            url_prefix: format!("/{}/", addin_name),
+            addin_dir: addin_dir.to_path_buf(),
+            default_destination: default_destination.to_string(),
+        }
+    }
+
+    /// Maps the path of a request to a file in the addin directory. Folders do not fall back to
+    /// their index.html, since a file:// URL does not either
+    pub fn resolve(&self, request_path: &str) -> Resolved {
+        let path = request_path.split(['?', '#']).next().unwrap_or_default();
+        let path = percent_decode(path);
+        if path == "/" {
+            return Resolved::Redirect(format!(
+                "{}{}/index.html",
+                self.url_prefix, self.default_destination
+            ));
+        }
+        let Some(relative) = path.strip_prefix(&self.url_prefix) else {
+            return Resolved::OutsideAddin;
+        };
+        // Windows also splits paths at backslashes, so they must not hide a '..'
+        let relative = relative.replace('\\', "/");
+        let relative = Path::new(&relative);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Resolved::OutsideAddin;
+        }
+        let file_path = self.addin_dir.join(relative);
+        if file_path.is_file() {
+            Resolved::File(file_path)
+        } else {
+            Resolved::NotFound
+        }
+    }
+}
+
+/// Accepts connections until the listener fails, answering each request with a file from the addin directory
+pub async fn serve(listener: TcpListener, server: Arc<StaticServer>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &server).await {
+                        println!("Error handling request: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                println!("Error accepting connection: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, server: &StaticServer) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() > MAX_REQUEST_SIZE {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    if method != "GET" && method != "HEAD" {
+        println!("405 {} {}", method, path);
+        return write_response(&mut stream, "405 Method Not Allowed", &[], b"", false).await;
+    }
+    match server.resolve(path) {
+        Resolved::File(file_path) => {
+            let body = tokio::fs::read(&file_path).await?;
+            println!("200 {}", path);
+            write_response(
+                &mut stream,
+                "200 OK",
+                &[("Content-Type", get_content_type(&file_path))],
+                &body,
+                method == "HEAD",
+            )
+            .await
+        }
+        Resolved::Redirect(location) => {
+            println!("302 {} -> {}", path, location);
+            write_response(
+                &mut stream,
+                "302 Found",
+                &[("Location", &location)],
+                b"",
+                false,
+            )
+            .await
+        }
+        Resolved::NotFound => {
+            println!("404 {}", path);
+            write_response(&mut stream, "404 Not Found", &[], b"Not found", false).await
+        }
+        Resolved::OutsideAddin => {
+            println!(
+                "404 {} is outside of {}. Inside of Revit this URL resolves to the root of the drive, so use a relative URL instead",
+                path, server.url_prefix
+            );
+            write_response(
+                &mut stream,
+                "404 Not Found",
+                &[],
+                b"Not found: the URL is outside of the add-in folder",
+                false,
+            )
+            .await
+        }
+    }
+}
+
+async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    head_only: bool,
+) -> std::io::Result<()> {
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    if !head_only {
+        stream.write_all(body).await?;
+    }
+    stream.shutdown().await
+}
+
+/// Returns the MIME type for a file. WebView2 picks the type from the extension as well
+pub fn get_content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decodes '%XX' escapes in a URL path. Invalid escapes are kept as they are
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Polls the source files of every frontend and exports a frontend again once its files have
+/// changed and then stayed the same for one interval, so that a save of several files builds once
+async fn watch_frontends(frontends: &[Frontend], addin_dir: &Path) {
+    let mut snapshots: Vec<_> = frontends.iter().map(get_source_snapshot).collect();
+    loop {
+        tokio::time::sleep(WATCH_INTERVAL).await;
+        for (frontend, snapshot) in frontends.iter().zip(snapshots.iter_mut()) {
+            let mut current = get_source_snapshot(frontend);
+            if current == *snapshot {
+                continue;
+            }
+            loop {
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                let next = get_source_snapshot(frontend);
+                if next == current {
+                    break;
+                }
+                current = next;
+            }
+            *snapshot = current;
+            println!("Changes detected in {}", frontend.dir.to_string_lossy());
+            rebuild(frontend, addin_dir).await;
+        }
+    }
+}
+
+async fn rebuild(frontend: &Frontend, addin_dir: &Path) {
+    let mut error_list = ErrorList::new();
+    web_app::export_frontend(
+        frontend,
+        addin_dir,
+        &mut error_list,
+        &ConsoleSink,
+        &CancellationToken::new(),
+    )
+    .await;
+    for error in error_list.view_errors() {
+        println!("Error: {}", error);
+    }
+    for warning in error_list.view_warnings() {
+        println!("{}", warning);
+    }
+}
+
+/// Returns every source file of the frontend with the time it was last modified, sorted by path.
+/// Dependencies and build output are skipped, since the build itself writes to them
+fn get_source_snapshot(frontend: &Frontend) -> Vec<(PathBuf, SystemTime)> {
+    let mut snapshot = Vec::new();
+    let mut dirs = vec![frontend.dir.clone()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !NON_SOURCE_DIRS.contains(&name.as_str())
+                    && frontend.output_dir.as_ref() != Some(&path)
+                {
+                    dirs.push(path);
+                }
+            } else if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                snapshot.push((path, modified));
+            }
+        }
+    }
+    snapshot.sort();
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_server() -> (tempfile::TempDir, StaticServer) {
+        let addin_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(addin_dir.path().join("app/assets")).unwrap();
+        std::fs::write(addin_dir.path().join("app/index.html"), "<html></html>").unwrap();
+        std::fs::write(addin_dir.path().join("app/assets/my file.js"), "").unwrap();
+        let server = StaticServer::new("MyAddin", addin_dir.path(), "app");
+        (addin_dir, server)
+    }
+
+    #[test]
+    fn redirects_the_root_to_the_default_frontend() {
+        let (_addin_dir, server) = test_server();
+        let redirect = Resolved::Redirect("/MyAddin/app/index.html".to_string());
+        assert_eq!(server.resolve("/"), redirect);
+        assert_eq!(server.resolve("/?reload=1"), redirect);
+    }
+
+    #[test]
+    fn resolves_files_in_the_addin_dir() {
+        let (addin_dir, server) = test_server();
+        let index = Resolved::File(addin_dir.path().join("app/index.html"));
+        assert_eq!(server.resolve("/MyAddin/app/index.html"), index);
+        assert_eq!(server.resolve("/MyAddin/app/index.html?v=2#top"), index);
+        assert_eq!(server.resolve("/MyAddin/app/index.html#top"), index);
+        assert_eq!(
+            server.resolve("/MyAddin/app/assets/my%20file.js"),
+            Resolved::File(addin_dir.path().join("app/assets/my file.js"))
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/app/missing.js"),
+            Resolved::NotFound
+        );
+        // Folders are not served as their index.html
+        assert_eq!(server.resolve("/MyAddin/app"), Resolved::NotFound);
+        assert_eq!(server.resolve("/MyAddin/app/"), Resolved::NotFound);
+    }
+
+    #[test]
+    fn rejects_urls_outside_the_addin_dir() {
+        let (_addin_dir, server) = test_server();
+        assert_eq!(server.resolve("/app/index.html"), Resolved::OutsideAddin);
+        assert_eq!(server.resolve("/MyAddin"), Resolved::OutsideAddin);
+        assert_eq!(
+            server.resolve("/MyAddinOther/index.html"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/../secret.txt"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/app/../../secret.txt"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/%2e%2e/secret.txt"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/%2E%2E%2Fsecret.txt"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/app%5c..%5c..%5csecret.txt"),
+            Resolved::OutsideAddin
+        );
+        assert_eq!(
+            server.resolve("/MyAddin/./app/index.html"),
+            Resolved::OutsideAddin
+        );
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("/a%20b"), "/a b");
+        assert_eq!(percent_decode("%2e%2E"), "..");
+        assert_eq!(percent_decode("%5c"), "\\");
+        assert_eq!(percent_decode("%C3%A9"), "\u{e9}");
+        // Invalid escapes are kept
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%%41"), "%A");
+    }
+
+    #[test]
+    fn picks_the_content_type_from_the_extension() {
+        assert_eq!(
+            get_content_type(Path::new("index.html")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("app.MJS")),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("app.js.map")),
+            "application/json"
+        );
+        assert_eq!(get_content_type(Path::new("icon.SVG")), "image/svg+xml");
+        assert_eq!(
+            get_content_type(Path::new("module.wasm")),
+            "application/wasm"
+        );
+        assert_eq!(
+            get_content_type(Path::new("LICENSE")),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            get_content_type(Path::new("data.bin")),
+            "application/octet-stream"
+        );
+    }
+}
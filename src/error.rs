@@ -0,0 +1,371 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An error returned by the rev library
+///
+/// `code` returns a stable identifier for each kind of error, so that tools embedding rev can
+/// branch on it instead of on the message. Codes are never changed or reused once released.
+/// `Display` gives a complete message for the user, including the underlying error where there
+/// is one. It is not returned again from `source`, so error reporters do not print it twice
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum RevError {
+    /// The C# project, or a file it needs, could not be found
+    Discovery(DiscoveryError),
+    /// Neither MSBuild nor the dotnet SDK is installed
+    ToolchainNotFound { tried: Vec<Toolchain> },
+    /// The build ran and failed
+    Build(BuildError),
+    /// A '.addin' manifest could not be read
+    Manifest(ManifestError),
+    /// A file or folder could not be read or written
+    Io {
+        path: PathBuf,
+        source: Arc<std::io::Error>,
+    },
+    /// A file could not be copied into the addin directory while exporting
+    ExportCopy {
+        source_path: PathBuf,
+        destination_path: PathBuf,
+        source: Arc<std::io::Error>,
+    },
+    /// A DLL is not a .NET assembly, or its metadata could not be read
+    InvalidAssembly { path: PathBuf, message: String },
+    /// An install receipt is not valid JSON, or is missing fields
+    InvalidReceipt { path: PathBuf, message: String },
+    /// A setting, profile or the project's rev.json is not valid
+    Config { message: String },
+    /// Another rev process kept the global settings locked for too long. `lock_path` can be
+    /// deleted if no other rev process is running
+    StateLocked { lock_path: PathBuf },
+    /// The operation was stopped with its cancellation token
+    Cancelled,
+    /// A step ran longer than its timeout in rev.json. `step` is the name of the timeout
+    /// setting, e.g. "build"
+    TimedOut { step: &'static str, after: Duration },
+}
+
+impl RevError {
+    /// Returns the stable machine-readable code of the error, e.g. "build.failed"
+    pub fn code(&self) -> &'static str {
+        match self {
+            RevError::Discovery(e) => e.code(),
+            RevError::ToolchainNotFound { .. } => "toolchain.not_found",
+            RevError::Build(_) => "build.failed",
+            RevError::Manifest(e) => e.code(),
+            RevError::Io { .. } => "io.failed",
+            RevError::ExportCopy { .. } => "export.copy_failed",
+            RevError::InvalidAssembly { .. } => "assembly.invalid",
+            RevError::InvalidReceipt { .. } => "receipt.invalid",
+            RevError::Config { .. } => "config.invalid",
+            RevError::StateLocked { .. } => "state.locked",
+            RevError::Cancelled => "operation.cancelled",
+            RevError::TimedOut { .. } => "operation.timed_out",
+        }
+    }
+
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        RevError::Io {
+            path: path.into(),
+            source: Arc::new(source),
+        }
+    }
+
+    pub fn export_copy(
+        source_path: impl Into<PathBuf>,
+        destination_path: impl Into<PathBuf>,
+        source: std::io::Error,
+    ) -> Self {
+        RevError::ExportCopy {
+            source_path: source_path.into(),
+            destination_path: destination_path.into(),
+            source: Arc::new(source),
+        }
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        RevError::Config {
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for RevError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevError::Discovery(e) => e.fmt(f),
+            RevError::ToolchainNotFound { tried } => {
+                let tried: Vec<String> = tried.iter().map(Toolchain::to_string).collect();
+                write!(
+                    f,
+                    "Could not find a build tool. Tried {}. Install the .NET SDK or the Visual Studio Build Tools",
+                    tried.join(" and ")
+                )
+            }
+            RevError::Build(e) => e.fmt(f),
+            RevError::Manifest(e) => e.fmt(f),
+            RevError::Io { path, source } => {
+                write!(f, "Could not access {}: {}", path.to_string_lossy(), source)
+            }
+            RevError::ExportCopy {
+                source_path,
+                destination_path,
+                source,
+            } => write!(
+                f,
+                "Could not copy {} to {}: {}",
+                source_path.to_string_lossy(),
+                destination_path.to_string_lossy(),
+                source
+            ),
+            RevError::InvalidAssembly { path, message } => {
+                write!(f, "Could not read {}: {}", path.to_string_lossy(), message)
+            }
+            RevError::InvalidReceipt { path, message } => write!(
+                f,
+                "{} is not a valid install receipt: {}",
+                path.to_string_lossy(),
+                message
+            ),
+            RevError::Config { message } => write!(f, "{}", message),
+            RevError::StateLocked { lock_path } => write!(
+                f,
+                "Timed out waiting for another rev process to save its settings. Delete {} if no other rev process is running",
+                lock_path.to_string_lossy()
+            ),
+            RevError::Cancelled => write!(f, "The operation was cancelled"),
+            RevError::TimedOut { step, after } => write!(
+                f,
+                "The {} step was stopped after {} seconds. Change the limit with 'rev config set timeouts.{} <seconds>'",
+                step,
+                after.as_secs(),
+                step
+            ),
+        }
+    }
+}
+
+impl Error for RevError {}
+
+impl From<DiscoveryError> for RevError {
+    fn from(e: DiscoveryError) -> Self {
+        RevError::Discovery(e)
+    }
+}
+
+impl From<BuildError> for RevError {
+    fn from(e: BuildError) -> Self {
+        RevError::Build(e)
+    }
+}
+
+impl From<ManifestError> for RevError {
+    fn from(e: ManifestError) -> Self {
+        RevError::Manifest(e)
+    }
+}
+
+/// Something rev needs that could not be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiscoveryError {
+    /// No .csproj file was found in or below the starting directory
+    Project { starting_dir: PathBuf },
+    /// The project has not been built, or its DLL is not where the build writes it
+    BuildOutput {
+        dll_name: String,
+        csproj_dir: PathBuf,
+    },
+    /// The Revit addins directory for the version and scope does not exist
+    AddinsDirectory {
+        revit_version: String,
+        message: String,
+    },
+}
+
+impl DiscoveryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiscoveryError::Project { .. } => "discovery.project_not_found",
+            DiscoveryError::BuildOutput { .. } => "discovery.build_output_not_found",
+            DiscoveryError::AddinsDirectory { .. } => "discovery.addins_directory_not_found",
+        }
+    }
+}
+
+impl Display for DiscoveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscoveryError::Project { starting_dir } => write!(
+                f,
+                "No .csproj file found in {} or the folders below it",
+                starting_dir.to_string_lossy()
+            ),
+            DiscoveryError::BuildOutput {
+                dll_name,
+                csproj_dir,
+            } => write!(
+                f,
+                "Could not find {} below {}. Build the project first",
+                dll_name,
+                csproj_dir.to_string_lossy()
+            ),
+            DiscoveryError::AddinsDirectory {
+                revit_version,
+                message,
+            } => write!(
+                f,
+                "Could not find the addins directory for Revit {}: {}",
+                revit_version, message
+            ),
+        }
+    }
+}
+
+impl Error for DiscoveryError {}
+
+/// The tools rev builds projects with, in the order they are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolchain {
+    MsBuild,
+    Dotnet,
+}
+
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Toolchain::MsBuild => write!(f, "MSBuild"),
+            Toolchain::Dotnet => write!(f, "dotnet"),
+        }
+    }
+}
+
+/// A failed build, with the errors and warnings the compiler reported
+#[derive(Debug, Clone)]
+pub struct BuildError {
+    pub toolchain: Toolchain,
+    /// The diagnostics found in the build output, without the duplicates MSBuild repeats in its summary
+    pub diagnostics: Vec<BuildDiagnostic>,
+    /// Everything the build tool wrote
+    pub output: String,
+}
+
+impl BuildError {
+    /// Returns the diagnostics that failed the build
+    pub fn errors(&self) -> impl Iterator<Item = &BuildDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+    }
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<&BuildDiagnostic> = self.errors().collect();
+        if errors.is_empty() {
+            return write!(
+                f,
+                "The build with {} failed: {}",
+                self.toolchain,
+                self.output.trim()
+            );
+        }
+        write!(
+            f,
+            "The build with {} failed with {} error{}:",
+            self.toolchain,
+            errors.len(),
+            if errors.len() == 1 { "" } else { "s" }
+        )?;
+        for error in errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for BuildError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// An error or warning from the compiler, e.g. "Command.cs(12,5): error CS0103: The name 'x' does not exist"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// The compiler code, e.g. "CS0103" or "MSB3027"
+    pub code: String,
+    pub message: String,
+    /// The source file the diagnostic is in, if it is in one
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl Display for BuildDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file)?;
+            if let (Some(line), Some(column)) = (self.line, self.column) {
+                write!(f, "({},{})", line, column)?;
+            }
+            write!(f, ": ")?;
+        }
+        let severity = match self.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+        };
+        write!(f, "{} {}: {}", severity, self.code, self.message)
+    }
+}
+
+/// A '.addin' manifest that could not be read
+#[derive(Debug, Clone)]
+pub struct ManifestError {
+    pub path: PathBuf,
+    pub kind: ManifestErrorKind,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ManifestErrorKind {
+    NotFound,
+    Unreadable(Arc<std::io::Error>),
+    /// The file is not valid XML, or is not a RevitAddIns manifest
+    InvalidXml(String),
+    /// The manifest does not contain an AddIn element
+    NoAddIn,
+}
+
+impl ManifestError {
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            ManifestErrorKind::NotFound => "manifest.not_found",
+            ManifestErrorKind::Unreadable(_) => "manifest.unreadable",
+            ManifestErrorKind::InvalidXml(_) => "manifest.invalid_xml",
+            ManifestErrorKind::NoAddIn => "manifest.no_addin",
+        }
+    }
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.to_string_lossy();
+        match &self.kind {
+            ManifestErrorKind::NotFound => write!(f, "The manifest {} does not exist", path),
+            ManifestErrorKind::Unreadable(e) => write!(f, "Could not read {}: {}", path, e),
+            ManifestErrorKind::InvalidXml(e) => {
+                write!(f, "{} is not a valid .addin manifest: {}", path, e)
+            }
+            ManifestErrorKind::NoAddIn => write!(f, "{} does not contain an AddIn element", path),
+        }
+    }
+}
+
+impl Error for ManifestError {}
@@ -0,0 +1,148 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use crate::error::BuildDiagnostic;
+
+// Building and exporting can take minutes, so they report what they are doing as it happens.
+// Library users pass an EventSink to show progress in their own UI, and the CLI prints the same
+// events with ConsoleSink
+
+/// A stage of building or exporting an add-in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Finding the C# project
+    Discover,
+    /// Building the C# project with MSBuild or dotnet
+    Build,
+    /// Installing the dependencies of a web app, building it and copying its static files
+    WebBuild,
+    /// Copying the add-in's files into the addin directory. The web apps are built during this
+    /// phase, since their files are copied into the same directory
+    Copy,
+    /// Creating or reading the project's '.addin' file and checking it against the built DLL
+    Manifest,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Discover => write!(f, "discover"),
+            Phase::Build => write!(f, "build"),
+            Phase::WebBuild => write!(f, "web build"),
+            Phase::Copy => write!(f, "copy"),
+            Phase::Manifest => write!(f, "manifest"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Something that happened while building or exporting an add-in
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    PhaseStarted(Phase),
+    /// A phase ended. `success` is false if it added an error. Exporting to several directories
+    /// runs the copy phases once for each of them
+    PhaseFinished {
+        phase: Phase,
+        success: bool,
+    },
+    /// A line written by a build tool or package manager, without its line ending
+    ProcessOutput {
+        /// The name of the program, e.g. "msbuild" or "yarn"
+        program: String,
+        stream: OutputStream,
+        line: String,
+    },
+    FileCopied {
+        source: PathBuf,
+        destination: PathBuf,
+        bytes: u64,
+    },
+    /// An error or warning from the compiler, reported when the build finishes
+    Diagnostic(BuildDiagnostic),
+    /// A status update for the user, e.g. that MSBuild was not found and dotnet is used instead
+    Message(String),
+}
+
+/// Receives the events of an operation as they happen
+///
+/// Implemented for closures taking an `Event`, and for the senders of std and tokio channels.
+/// Events are emitted from the task running the operation, so slow sinks slow it down
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: Event);
+}
+
+impl<F: Fn(Event) + Send + Sync> EventSink for F {
+    fn emit(&self, event: Event) {
+        self(event)
+    }
+}
+
+impl EventSink for std::sync::mpsc::Sender<Event> {
+    fn emit(&self, event: Event) {
+        // The receiver being dropped only means nobody is listening anymore
+        let _ = self.send(event);
+    }
+}
+
+impl EventSink for tokio::sync::mpsc::UnboundedSender<Event> {
+    fn emit(&self, event: Event) {
+        let _ = self.send(event);
+    }
+}
+
+/// Ignores every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoEvents;
+
+impl EventSink for NoEvents {
+    fn emit(&self, _event: Event) {}
+}
+
+/// Prints the events the way the CLI does. The output of build tools is not printed, since the
+/// compiler's errors and warnings in it are printed from their `Diagnostic` events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleSink;
+
+impl EventSink for ConsoleSink {
+    fn emit(&self, event: Event) {
+        match event {
+            Event::PhaseStarted(phase) => println!("Starting the {} phase", phase),
+            Event::PhaseFinished {
+                phase,
+                success: true,
+            } => println!("Finished the {} phase", phase),
+            Event::PhaseFinished {
+                phase,
+                success: false,
+            } => println!("The {} phase failed", phase),
+            Event::Diagnostic(diagnostic) => println!("{}", diagnostic),
+            Event::Message(message) => println!("{}", message),
+            Event::FileCopied {
+                destination, bytes, ..
+            } => println!(
+                "Copied {} ({})",
+                destination
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                format_bytes(bytes)
+            ),
+            _ => {}
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
@@ -1,36 +1,41 @@
 use tokio::process::Command;
 
+use crate::cancel::CancellationToken;
+use crate::events::EventSink;
+use crate::external_cmds::process;
+
+#[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 pub async fn check_if_exists() -> bool {
-    let result = Command::new("dotnet")
-        .arg("--version")
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .await;
-    result.is_ok()
+    let mut command = Command::new("dotnet");
+    command.arg("--version");
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    command.output().await.is_ok()
 }
 
 pub enum DotnetError {
     NotFound,
     Output(String),
 }
-pub async fn build_project(project_path: &str) -> Result<String, DotnetError> {
+pub async fn build_project(
+    project_path: &str,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, DotnetError> {
     if !check_if_exists().await {
         return Err(DotnetError::NotFound);
     }
-    let result = Command::new("dotnet")
-        .arg("build")
-        .arg(project_path)
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .await;
+    let mut command = Command::new("dotnet");
+    command.arg("build").arg(project_path);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let result = process::output(&mut command, events, cancel).await;
     match result {
         Ok(output) => {
             if !output.status.success() {
-                return Err(DotnetError::Output(
-                    String::from_utf8_lossy(&output.stderr).to_string(),
-                ));
+                return Err(DotnetError::Output(get_failed_output(&output)));
             }
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         }
@@ -40,3 +45,12 @@ pub async fn build_project(project_path: &str) -> Result<String, DotnetError> {
         ))),
     }
 }
+
+/// Returns everything a failed build wrote. The compiler errors are written to stdout
+fn get_failed_output(output: &std::process::Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
@@ -0,0 +1,26 @@
+use tokio::process::Command;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Returns the commit hash of HEAD for the repository containing `working_dir`,
+/// or `None` if git is not installed or the directory is not inside a repository
+pub async fn get_head_commit(working_dir: &str) -> Option<String> {
+    let mut command = Command::new("git");
+    command
+        .arg("rev-parse")
+        .arg("HEAD")
+        .current_dir(working_dir);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if commit.is_empty() {
+        None
+    } else {
+        Some(commit)
+    }
+}
@@ -1,3 +1,7 @@
 pub mod dotnet;
+pub mod git;
 pub mod msbuild;
-pub mod yarn;
\ No newline at end of file
+pub mod package_manager;
+pub mod process;
+pub mod registry;
+pub mod shell;
\ No newline at end of file
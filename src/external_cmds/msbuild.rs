@@ -1,6 +1,11 @@
 use std::path::Path;
 use tokio::process::Command;
 
+use crate::cancel::CancellationToken;
+use crate::events::{Event, EventSink};
+use crate::external_cmds::process;
+
+#[cfg(windows)]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
 const VS_MSBUILD_PATH: &str =
@@ -10,38 +15,42 @@ pub enum MsBuildError {
     NotFound,
     Output(String),
 }
-pub async fn build_project(project_path: &str) -> Result<String, MsBuildError> {
+pub async fn build_project(
+    project_path: &str,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, MsBuildError> {
     // Try system-wide msbuild first
-    let result = Command::new("msbuild")
-        .arg(project_path)
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .await;
+    let mut command = Command::new("msbuild");
+    command.arg(project_path);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let result = process::output(&mut command, events, cancel).await;
 
     match result {
         Ok(output) => {
             if !output.status.success() {
-                return Err(MsBuildError::Output(
-                    String::from_utf8_lossy(&output.stderr).to_string(),
-                ));
+                return Err(MsBuildError::Output(get_failed_output(&output)));
             }
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         }
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+            Err(MsBuildError::Output(e.to_string()))
+        }
         Err(_) => {
             // If system-wide fails, try Visual Studio path
-            println!("System-wide MSBuild not found, trying Visual Studio path...");
+            events.emit(Event::Message(
+                "System-wide MSBuild not found, trying Visual Studio path...".to_string(),
+            ));
             if Path::new(VS_MSBUILD_PATH).exists() {
-                match Command::new(VS_MSBUILD_PATH)
-                    .arg(project_path)
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .output()
-                    .await
-                {
+                let mut command = Command::new(VS_MSBUILD_PATH);
+                command.arg(project_path);
+                #[cfg(windows)]
+                command.creation_flags(CREATE_NO_WINDOW);
+                match process::output(&mut command, events, cancel).await {
                     Ok(output) => {
                         if !output.status.success() {
-                            return Err(MsBuildError::Output(
-                                String::from_utf8_lossy(&output.stderr).to_string(),
-                            ));
+                            return Err(MsBuildError::Output(get_failed_output(&output)));
                         }
                         Ok(String::from_utf8_lossy(&output.stdout).to_string())
                     }
@@ -56,3 +65,12 @@ pub async fn build_project(project_path: &str) -> Result<String, MsBuildError> {
         }
     }
 }
+
+/// Returns everything a failed build wrote. The compiler errors are written to stdout
+fn get_failed_output(output: &std::process::Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
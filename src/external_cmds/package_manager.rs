@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::process::Command;
+
+use crate::cancel::CancellationToken;
+use crate::events::{Event, EventSink};
+use crate::external_cmds::process;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// The JavaScript package managers that can build a web app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+    Bun,
+}
+
+impl PackageManager {
+    pub const ALL: [PackageManager; 4] = [
+        PackageManager::Npm,
+        PackageManager::Yarn,
+        PackageManager::Pnpm,
+        PackageManager::Bun,
+    ];
+
+    /// The name of the executable, which is also how the package manager is written in config
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    /// The arguments that install the dependencies of a web app. A frozen install fails instead
+    /// of updating the lockfile, so that the build uses exactly the locked versions
+    pub fn install_args(&self, frozen: bool) -> Vec<&'static str> {
+        match (self, frozen) {
+            (PackageManager::Npm, true) => vec!["ci"],
+            (_, true) => vec!["install", "--frozen-lockfile"],
+            (_, false) => vec!["install"],
+        }
+    }
+
+    /// The lockfiles that each package manager writes, used to tell which one a project uses
+    fn lockfiles(&self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Npm => &["package-lock.json"],
+            PackageManager::Yarn => &["yarn.lock"],
+            PackageManager::Pnpm => &["pnpm-lock.yaml"],
+            PackageManager::Bun => &["bun.lockb", "bun.lock"],
+        }
+    }
+}
+
+impl Display for PackageManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for PackageManager {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PackageManager::ALL
+            .into_iter()
+            .find(|package_manager| package_manager.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown package manager '{}'. Expected npm, yarn, pnpm or bun",
+                    s
+                )
+            })
+    }
+}
+
+/// Returns the lockfile that the package manager wrote in `project_dir`, if there is one
+pub fn find_lockfile(package_manager: PackageManager, project_dir: &Path) -> Option<PathBuf> {
+    package_manager
+        .lockfiles()
+        .iter()
+        .map(|lockfile| project_dir.join(lockfile))
+        .find(|path| path.is_file())
+}
+
+/// Returns the package manager that the web app in `project_dir` uses.
+///
+/// The `packageManager` field of package.json is used first, e.g. "pnpm@9.1.0", then the
+/// lockfile in the directory. Defaults to npm, which ships with Node.js
+pub fn detect(project_dir: &Path) -> PackageManager {
+    if let Some(package_manager) = get_package_json_package_manager(project_dir) {
+        return package_manager;
+    }
+    PackageManager::ALL
+        .into_iter()
+        .find(|package_manager| {
+            package_manager
+                .lockfiles()
+                .iter()
+                .any(|lockfile| project_dir.join(lockfile).is_file())
+        })
+        .unwrap_or(PackageManager::Npm)
+}
+
+fn get_package_json_package_manager(project_dir: &Path) -> Option<PackageManager> {
+    let contents = std::fs::read_to_string(project_dir.join("package.json")).ok()?;
+    let package_json: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let field = package_json.get("packageManager")?.as_str()?;
+    field.split('@').next()?.parse().ok()
+}
+
+/// Returns the path to the package manager's executable, searching PATH first and then the
+/// folders that the Node.js installer and `npm install -g` use on Windows
+pub fn find_executable(package_manager: PackageManager) -> Option<PathBuf> {
+    let file_names: Vec<String> = if cfg!(windows) {
+        // Package managers installed through npm are '.cmd' shims, while bun is an '.exe'
+        let path_extensions =
+            std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        path_extensions
+            .split(';')
+            .filter(|extension| !extension.is_empty())
+            .map(|extension| format!("{}{}", package_manager.name(), extension.to_lowercase()))
+            .collect()
+    } else {
+        vec![package_manager.name().to_string()]
+    };
+
+    let mut search_dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if cfg!(windows) {
+        if let Ok(app_data) = std::env::var("APPDATA") {
+            search_dirs.push(Path::new(&app_data).join("npm"));
+        }
+        search_dirs.push(PathBuf::from(r"C:\Program Files\nodejs"));
+        search_dirs.push(PathBuf::from(r"C:\Program Files (x86)\nodejs"));
+    }
+
+    search_dirs
+        .iter()
+        .flat_map(|dir| file_names.iter().map(move |file_name| dir.join(file_name)))
+        .find(|path| path.is_file())
+}
+
+pub enum PackageManagerError {
+    NotFound(PackageManager),
+    Output(String),
+}
+
+impl Display for PackageManagerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackageManagerError::NotFound(package_manager) => {
+                write!(f, "{} is not installed on this system", package_manager)
+            }
+            PackageManagerError::Output(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Runs a script from package.json, e.g. "build", with the package manager
+pub async fn run_script(
+    package_manager: PackageManager,
+    working_dir: &Path,
+    script: &str,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, PackageManagerError> {
+    run(
+        package_manager,
+        working_dir,
+        &["run", script],
+        events,
+        cancel,
+    )
+    .await
+}
+
+/// Installs the dependencies of the web app in `working_dir`. Uses a frozen install when the
+/// project has a lockfile
+pub async fn install(
+    package_manager: PackageManager,
+    working_dir: &Path,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, PackageManagerError> {
+    let frozen = find_lockfile(package_manager, working_dir).is_some();
+    run(
+        package_manager,
+        working_dir,
+        &package_manager.install_args(frozen),
+        events,
+        cancel,
+    )
+    .await
+}
+
+async fn run(
+    package_manager: PackageManager,
+    working_dir: &Path,
+    args: &[&str],
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, PackageManagerError> {
+    let executable =
+        find_executable(package_manager).ok_or(PackageManagerError::NotFound(package_manager))?;
+
+    events.emit(Event::Message(format!(
+        "Using {} from: {}",
+        package_manager,
+        executable.to_string_lossy()
+    )));
+    events.emit(Event::Message(format!(
+        "Running in directory: {}",
+        working_dir.to_string_lossy()
+    )));
+
+    let mut command = Command::new(executable);
+    command.args(args).current_dir(working_dir);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let result = process::output(&mut command, events, cancel).await;
+
+    match result {
+        Ok(output) => {
+            if !output.status.success() {
+                return Err(PackageManagerError::Output(
+                    String::from_utf8_lossy(&output.stderr).to_string(),
+                ));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Err(e) => Err(PackageManagerError::Output(format!(
+            "Failed to run {} {}: {}",
+            package_manager,
+            args.join(" "),
+            e
+        ))),
+    }
+}
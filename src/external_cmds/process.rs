@@ -0,0 +1,113 @@
+use std::path::Path;
+use std::process::{Output, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::cancel::CancellationToken;
+use crate::events::{Event, EventSink, OutputStream};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Runs the command until it exits and returns its output, like `Command::output`, while
+/// emitting every line it writes as soon as it is written
+///
+/// When `cancel` is cancelled, the process and every process it started are killed, and an
+/// error with the kind `Interrupted` is returned
+pub async fn output(
+    command: &mut Command,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> std::io::Result<Output> {
+    if cancel.is_cancelled() {
+        return Err(cancelled_error());
+    }
+    let program = Path::new(command.as_std().get_program())
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    // Its own process group lets the process be killed with everything it started
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let finished = tokio::select! {
+        finished = async {
+            tokio::join!(
+                read_lines(stdout, &program, OutputStream::Stdout, events),
+                read_lines(stderr, &program, OutputStream::Stderr, events),
+                child.wait()
+            )
+        } => Some(finished),
+        _ = cancel.cancelled() => None,
+    };
+    let Some((stdout, stderr, status)) = finished else {
+        kill_process_tree(&mut child).await;
+        return Err(cancelled_error());
+    };
+    Ok(Output {
+        status: status?,
+        stdout: stdout?,
+        stderr: stderr?,
+    })
+}
+
+fn cancelled_error() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Interrupted, "The command was cancelled")
+}
+
+/// Kills the process and every process it started. Build tools run compilers and package
+/// managers run node, which would keep running if only the process itself was killed
+async fn kill_process_tree(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        #[cfg(windows)]
+        let _ = Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .await;
+        #[cfg(not(windows))]
+        let _ = Command::new("kill")
+            .args(["-s", "KILL", "--", &format!("-{}", pid)])
+            .output()
+            .await;
+    }
+    let _ = child.kill().await;
+}
+
+/// Reads the stream to the end, emitting each line. Returns everything that was read
+async fn read_lines(
+    stream: Option<impl AsyncRead + Unpin>,
+    program: &str,
+    output_stream: OutputStream,
+    events: &dyn EventSink,
+) -> std::io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    let Some(stream) = stream else {
+        return Ok(contents);
+    };
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // Build tools do not always write UTF-8, so lines are read as bytes
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(contents);
+        }
+        contents.extend_from_slice(&line);
+        events.emit(Event::ProcessOutput {
+            program: program.to_string(),
+            stream: output_stream,
+            line: String::from_utf8_lossy(&line)
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+        });
+    }
+}
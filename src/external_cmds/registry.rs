@@ -0,0 +1,42 @@
+use tokio::process::Command;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// The registry key that every Revit installation adds a subkey to, named after its year
+/// or e.g. "Autodesk Revit 2024"
+const REVIT_REGISTRY_KEY: &str = r"HKLM\SOFTWARE\Autodesk\Revit";
+
+/// Returns the years of the Revit versions installed on this machine, sorted, or None if the
+/// registry could not be read, which is always the case outside of Windows
+pub async fn get_installed_revit_versions() -> Option<Vec<u32>> {
+    if cfg!(not(windows)) {
+        return None;
+    }
+    let mut command = Command::new("reg");
+    command.arg("query").arg(REVIT_REGISTRY_KEY);
+    #[cfg(windows)]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output().await.ok()?;
+    if !output.status.success() {
+        // The key does not exist when no version of Revit is installed
+        return Some(Vec::new());
+    }
+    let mut versions: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().rsplit('\\').next())
+        .filter_map(get_year)
+        .collect();
+    versions.sort();
+    versions.dedup();
+    Some(versions)
+}
+
+/// Returns the year in a Revit subkey name, e.g. 2024 for "2024" or "Autodesk Revit 2024"
+fn get_year(subkey: &str) -> Option<u32> {
+    subkey
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| part.len() == 4)
+        .filter_map(|part| part.parse().ok())
+        .find(|year| (2000..2100).contains(year))
+}
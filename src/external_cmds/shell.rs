@@ -0,0 +1,51 @@
+use std::path::Path;
+use tokio::process::Command;
+
+use crate::cancel::CancellationToken;
+use crate::events::{Event, EventSink};
+use crate::external_cmds::process;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Runs a command line with the system shell, 'cmd' on Windows and 'sh' elsewhere, so that
+/// commands such as "npx ng build --configuration production" can be configured as one string.
+/// Returns the output, or the error output if the command fails
+pub async fn run(
+    command_line: &str,
+    working_dir: &Path,
+    events: &dyn EventSink,
+    cancel: &CancellationToken,
+) -> Result<String, String> {
+    events.emit(Event::Message(format!("Running: {}", command_line)));
+    events.emit(Event::Message(format!(
+        "Running in directory: {}",
+        working_dir.to_string_lossy()
+    )));
+
+    // cmd has its own quoting rules, so the command line is passed to it as is
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command
+            .arg("/C")
+            .raw_arg(command_line)
+            .creation_flags(CREATE_NO_WINDOW);
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    };
+    command.current_dir(working_dir);
+    let output = process::output(&mut command, events, cancel)
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", command_line, e))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -1,82 +1,75 @@
+mod cancel;
 mod cmds;
+mod error;
+mod events;
 mod external_cmds;
+mod project;
+mod project_config;
+mod revit_version;
 mod state;
 mod utils;
 
-use std::fmt::Display;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub use cmds::build;
 
-use crate::cmds::{export, locate};
+use crate::cmds::{audit, check, config, conflicts, export, list, profile, web};
+pub use crate::cancel::CancellationToken;
+pub use crate::error::{
+    BuildDiagnostic, BuildError, DiagnosticSeverity, DiscoveryError, ManifestError,
+    ManifestErrorKind, RevError, Toolchain,
+};
+pub use crate::events::{ConsoleSink, Event, EventSink, NoEvents, OutputStream, Phase};
+pub use crate::project::Project;
 pub use crate::utils::error_list::ErrorList;
 
 pub const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-/// Builds the project in the given directory. Returns the output from the build command if it was successful, or an error message.
-pub async fn build_project(starting_dir: &str) -> Result<String, String> {
-    build::build_csharp_project(starting_dir).await
+/// Builds the project in the given directory. Returns the output from the build command if it was successful, or an error with
+/// the diagnostics the compiler reported.
+pub async fn build_project(starting_dir: &str) -> Result<String, RevError> {
+    Project::discover(starting_dir)?.build().await
 }
 /// Exports the addin to the given destination directories. Returns an error list if any errors occur.
 ///
 /// `starting_dir` is the directory that contains the C# project.
-/// `extra_dlls` are any additional DLLs that need to be exported.
+/// `extra_dlls` are any additional DLLs that need to be exported. Dependencies of the addin are found automatically, so this is usually empty.
 /// `destination_directories` are the directories to export the addin to.
 pub async fn export_addin_multiple(
     starting_dir: &str,
     extra_dlls: &[&str],
     destination_directories: &[&Path],
 ) -> ErrorList {
-    if let Err(e) = build_project(starting_dir).await {
-        let mut error_list = ErrorList::new();
-        error_list.add_error(&e);
-        return error_list;
+    match Project::discover(starting_dir) {
+        Ok(project) => {
+            project
+                .export_with_dlls(destination_directories, extra_dlls)
+                .await
+        }
+        Err(e) => {
+            let mut error_list = ErrorList::new();
+            error_list.add_typed_error(e);
+            error_list
+        }
     }
-    let mut error_list = ErrorList::new();
-    for destination_dir in destination_directories {
-        error_list.extend(&export::execute(starting_dir, extra_dlls, destination_dir).await);
-    }
-    error_list
 }
 
 /// Builds the addin, then exports the addin to the given destination directory. Returns an error list if any errors occur.
 ///
 /// `starting_dir` is the directory that contains the C# project.
-/// `extra_dlls` are any additional DLLs that need to be exported.
+/// `extra_dlls` are any additional DLLs that need to be exported. Dependencies of the addin are found automatically, so this is usually empty.
 /// `destination_dir` is the directory to export the addin to.
 pub async fn export_addin(starting_dir: &str, extra_dlls: &[&str], destination_dir: &Path) -> ErrorList {
-    if let Err(e) = build_project(starting_dir).await {
-        let mut error_list = ErrorList::new();
-        error_list.add_error(&e);
-        return error_list;
-    }
-    export::execute(starting_dir, extra_dlls, destination_dir).await
-}
-
-#[derive(Debug, Clone)]
-pub enum GetAddinFileInfoError {
-    FileNotFound,
-    AddinFileError(export::addin_file::GetAddinFileInfoError),
-}
-impl Display for GetAddinFileInfoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
+    export_addin_multiple(starting_dir, extra_dlls, &[destination_dir]).await
 }
 
 /// Gets the addin file info from the given path. Returns an error if the file is not found or if the file is not a valid addin file. Otherwise, returns the contents of the '.addin' file.
 ///
-/// `starting_dir` is the directory that contains the C# project. This is the directory that contains the '.addin' file as well.
+/// `starting_dir` is the directory that contains the C# project. The '.addin' file is next to the .csproj file.
 pub fn get_addin_file_info(
     starting_dir: &str,
-) -> Result<export::addin_file::AddinFileInfo, GetAddinFileInfoError> {
-    let project_name =
-        get_project_name(starting_dir).map_err(|_| GetAddinFileInfoError::FileNotFound)?;
-    let parent_dir = Path::new(starting_dir);
-
-    let addin_file_path = parent_dir.join(format!("{}.addin", project_name));
-    export::addin_file::get_addin_file_info(&addin_file_path.to_string_lossy())
-        .map_err(GetAddinFileInfoError::AddinFileError)
+) -> Result<export::addin_file::AddinFileInfo, RevError> {
+    Project::discover(starting_dir)?.manifest()
 }
 
 /// Gets the addin file info from the given path. Returns an error if the file is not found or if the file is not a valid addin file. Otherwise, returns the contents of the '.addin' file.
@@ -84,72 +77,263 @@ pub fn get_addin_file_info(
 /// `addin_file_path` is the path to the '.addin' file.
 pub fn get_addin_file_info_from_file(
     addin_file_path: &str,
-) -> Result<export::addin_file::AddinFileInfo, GetAddinFileInfoError> {
-    export::addin_file::get_addin_file_info(addin_file_path)
-        .map_err(GetAddinFileInfoError::AddinFileError)
-}
-
-#[derive(Debug, Clone)]
-pub enum CreateAddinFileError {
-    FileNotFound,
-    AddinFileError(String),
-}
-impl Display for CreateAddinFileError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
+) -> Result<export::addin_file::AddinFileInfo, RevError> {
+    Ok(export::addin_file::get_addin_file_info(addin_file_path)?)
 }
 
 /// Creates an addin file for the project. Returns an error if the file is not found or if the file is not a valid addin file. Will not overwrite an existing addin file.
 ///
-/// `starting_dir` is the directory that contains the C# project. This is the directory that contains the '.addin' file as well.
-/// `addin_info` is the information to write to the '.addin' file.
+/// `starting_dir` is the directory that contains the C# project. The '.addin' file is written next to the .csproj file.
+/// `addin_info` is the information to write to the '.addin' file. Empty vendor fields are filled from the project's vendor profile.
 ///
-/// Returns the path to the addin file if it was created successfully, or an error if the file is not found or if the file is not a valid addin file.
+/// Returns the path to the addin file if it was created successfully, or an error if the project is not found or the file could not be written.
 pub fn create_addin_file_for_project(
     starting_dir: &str,
-    addin_info: export::addin_file::AddinFileInfo,
-) -> Result<String, CreateAddinFileError> {
-    let project_name =
-        get_project_name(starting_dir).map_err(|_| CreateAddinFileError::FileNotFound)?;
-    // The starting directory is the directory that contains the C# project. It is also the parent in this scenario
-    let parent_dir = Path::new(starting_dir);
-
-    let addin_file_path = parent_dir.join(format!("{}.addin", project_name));
+    mut addin_info: export::addin_file::AddinFileInfo,
+) -> Result<String, RevError> {
+    let project = Project::discover(starting_dir)?;
+    if let Some(profile) = project.config()?.profile {
+        let (_, profile) = profile::get_profile(&profile).map_err(RevError::config)?;
+        export::addin_file::apply_profile(&mut addin_info, &profile);
+    }
+
+    let addin_file_path = project.manifest_path();
     export::addin_file::create_addin_file(&addin_file_path, addin_info)
-        .map_err(|e| CreateAddinFileError::AddinFileError(e.to_string()))?;
+        .map_err(|e| RevError::io(&addin_file_path, e))?;
     Ok(addin_file_path.to_string_lossy().to_string())
 }
 
 /// Gets all the DLLs in the project. Returns an error if no DLLs are found.
 ///
 /// `starting_dir` is the directory that contains the C# project.
-pub fn get_project_dlls(starting_dir: &str) -> Result<Vec<String>, String> {
-    locate::get_all_project_dll_paths(starting_dir)
+pub fn get_project_dlls(starting_dir: &str) -> Result<Vec<String>, RevError> {
+    Ok(Project::discover(starting_dir)?
+        .dlls()
+        .iter()
+        .map(|dll_path| dll_path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Removes the assemblies that ship with Revit, such as 'RevitAPI.dll', from a list of DLL paths. These should never be exported with an addin.
+///
+/// `dll_paths` are paths to DLLs, e.g. from `get_project_dlls`.
+/// `revit_version` is the year of the Revit version, e.g. 2025. If it is `None`, assemblies that ship with any version of Revit are removed.
+pub fn filter_revit_assemblies(dll_paths: Vec<String>, revit_version: Option<u32>) -> Vec<String> {
+    dll_paths
+        .into_iter()
+        .filter(|dll_path| {
+            let file_name = Path::new(dll_path).file_name().unwrap_or_default();
+            !export::revit_assemblies::is_revit_assembly(
+                &file_name.to_string_lossy(),
+                revit_version,
+            )
+        })
+        .collect()
 }
 
 /// Gets the name of the project. Returns an error if no project name is found.
 ///
 /// `starting_dir` is the directory that contains the C# project.
-pub fn get_project_name(starting_dir: &str) -> Result<String, String> {
-    let csproj_path = utils::recursively_check_for_file(
-        starting_dir,
-        "*.csproj",
-        3,
-        utils::SearchDirection::Child,
+pub fn get_project_name(starting_dir: &str) -> Result<String, RevError> {
+    Ok(Project::discover(starting_dir)?.name().to_string())
+}
+
+/// Re-hashes an installed addin and compares it against the receipt written when it was exported.
+/// Returns an error if the receipt cannot be read.
+///
+/// `receipt_path` is the path to the '.rev-receipt.json' file next to the installed '.addin' file.
+pub fn verify_installation(receipt_path: &Path) -> Result<VerifyReport, RevError> {
+    export::receipt::verify_installation(receipt_path)
+}
+
+/// Finds every addin installed for any Revit version in the scope. Manifests that cannot be parsed are returned as warnings.
+///
+/// `scope` is where to look. Use `InstallScope::Path` for a folder that contains a folder for each Revit version.
+pub fn list_installed_addins(scope: &InstallScope) -> (Vec<InstalledAddin>, ErrorList) {
+    let mut error_list = ErrorList::new();
+    let addins = list::find_installed_addins(scope, &mut error_list);
+    (addins, error_list)
+}
+
+/// Returns the Revit addins directory for the given version and scope, or an error message if it does not exist.
+///
+/// `version` is the year of the Revit version, e.g. "2025".
+pub fn get_revit_addins_path(version: &str, scope: &InstallScope) -> Result<PathBuf, RevError> {
+    export::get_revit_addins_path(version, scope).map_err(|message| {
+        DiscoveryError::AddinsDirectory {
+            revit_version: version.to_string(),
+            message,
+        }
+        .into()
+    })
+}
+
+/// Audits the installed manifests for every Revit version, such as duplicate AddInIds, missing assemblies or orphaned folders.
+///
+/// `scope` is where to look. Use `InstallScope::Path` to audit a folder copied from another machine.
+pub fn audit_installed_addins(scope: &InstallScope) -> Vec<AuditFinding> {
+    audit::audit_scope(scope)
+}
+
+/// Reads the metadata of a built addin DLL and checks that it can be loaded by the given Revit version,
+/// e.g. that it targets the right .NET runtime and does not reference a newer Revit API.
+///
+/// `revit_version` is the year of the Revit version, e.g. 2025.
+pub fn check_addin_compatibility(dll_path: &Path, revit_version: u32) -> ErrorList {
+    check::check_compatibility(dll_path, revit_version)
+}
+
+/// Reads the name, version, target framework and references of a .NET assembly without loading it.
+pub fn read_assembly_metadata(dll_path: &Path) -> Result<AssemblyMetadata, RevError> {
+    utils::assembly_metadata::read_assembly_metadata(dll_path).map_err(|message| {
+        RevError::InvalidAssembly {
+            path: dll_path.to_path_buf(),
+            message,
+        }
+    })
+}
+
+/// Finds the classes in a built addin DLL that Revit can load, with the manifest `Type` needed to register each of them.
+pub fn get_addin_entry_points(dll_path: &Path) -> Result<Vec<AddinEntryPoint>, RevError> {
+    let metadata = read_assembly_metadata(dll_path)?;
+    Ok(export::addin_file::find_addin_entry_points(&metadata))
+}
+
+/// Finds the assemblies that the project would export in a different version than another addin installed for the Revit version.
+///
+/// `starting_dir` is the directory that contains the C# project, which must already be built.
+/// `scopes` are searched for installed addins. Use `InstallScope::load_order()` to search every scope.
+pub fn find_assembly_conflicts(
+    starting_dir: &str,
+    revit_version: &str,
+    scopes: &[InstallScope],
+) -> Result<(Vec<AssemblyConflict>, ErrorList), RevError> {
+    let project = Project::discover(starting_dir)?;
+    let main_dll_path = project.locate()?;
+    let project_config = project.config()?;
+
+    let mut error_list = ErrorList::new();
+    let payload_assemblies = conflicts::get_payload_assemblies(
+        &project.csproj_path(),
+        &main_dll_path,
+        &project_config.export,
+        &mut error_list,
     );
-    if let Some(csproj_path) = csproj_path {
-        let project_name = Path::new(&csproj_path)
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .strip_suffix(".csproj")
-            .unwrap();
-        Ok(project_name.to_string())
-    } else {
-        Err("No csproj file found".to_string())
-    }
+    let installed_assemblies =
+        conflicts::get_installed_assemblies(revit_version, project.name(), scopes, &mut error_list);
+    let conflicts = conflicts::find_assembly_conflicts(
+        project.name(),
+        &payload_assemblies,
+        &installed_assemblies,
+    );
+    Ok((conflicts, error_list))
+}
+
+/// Checks a web UI for problems that break it when WebView2 loads it from disk, such as absolute asset URLs or API routes.
+/// Errors will break the UI inside of Revit, while warnings are likely to.
+///
+/// `web_app_dir` is the folder containing the package.json. `output_dir` is the folder the build writes static files to,
+/// and is detected from the common Next.js, Vite and Angular output folders when not given.
+pub fn check_web_app(web_app_dir: &Path, output_dir: Option<&Path>) -> ErrorList {
+    web::check::check_web_app(web_app_dir, output_dir)
+}
+
+/// Generates TypeScript interfaces from the C# message classes matched by `types_config.sources` and writes them to
+/// `types_config.output`. Both paths are relative to `csproj_dir`. The file is only written if its contents change.
+pub fn generate_web_types(csproj_dir: &Path, types_config: &TypesConfig) -> ErrorList {
+    web::gen_types::generate_types(csproj_dir, types_config)
+}
+
+/// Returns the value of a global or project setting by its key, e.g. "revitVersion" or "web.packageManager",
+/// or None if it is not set. Project settings are read from the rev.json of the project in `starting_dir`.
+/// Keys used in both scopes refer to the global setting unless `scope` is the project.
+pub fn get_setting(
+    starting_dir: &str,
+    key: &str,
+    scope: Option<ConfigScope>,
+) -> Result<Option<serde_json::Value>, RevError> {
+    let setting = config::find_setting(key, scope).map_err(RevError::config)?;
+    config::get_value(starting_dir, setting)
+}
+
+/// Validates and saves a global or project setting. The value is given as it would be on the command line,
+/// e.g. "2025" or "Messages/**/*.cs, Models/*.cs".
+pub async fn set_setting(
+    starting_dir: &str,
+    key: &str,
+    value: &str,
+    scope: Option<ConfigScope>,
+) -> Result<(), RevError> {
+    let setting = config::find_setting(key, scope).map_err(RevError::config)?;
+    let value = config::parse_value(starting_dir, setting, value)
+        .await
+        .map_err(RevError::config)?;
+    config::set_value(starting_dir, setting, Some(value)).await
+}
+
+/// Removes a global or project setting, so that its default is used.
+pub async fn unset_setting(
+    starting_dir: &str,
+    key: &str,
+    scope: Option<ConfigScope>,
+) -> Result<(), RevError> {
+    let setting = config::find_setting(key, scope).map_err(RevError::config)?;
+    config::set_value(starting_dir, setting, None).await
+}
+
+/// Returns the Revit version for the project in `starting_dir` and where it was set: `version_override`, then the
+/// `REV_REVIT_VERSION` environment variable, the "revitVersion" in the project's rev.json and the global setting.
+/// Returns None if none of them is set. Never prompts.
+pub fn resolve_revit_version(
+    starting_dir: &str,
+    version_override: Option<&str>,
+) -> Result<Option<(String, RevitVersionSource)>, RevError> {
+    revit_version::resolve_revit_version(starting_dir, version_override)
+}
+
+/// Returns every vendor profile, keyed by name.
+pub fn list_profiles() -> std::collections::BTreeMap<String, VendorProfile> {
+    state::get_state_or_default().profiles
+}
+
+/// Returns the name and details of the vendor profile used for the project in `starting_dir`: `profile_override` if given,
+/// otherwise the "profile" in its rev.json. Returns None if neither selects a profile, or an error if it does not exist.
+pub fn get_active_profile(
+    starting_dir: &str,
+    profile_override: Option<&str>,
+) -> Result<Option<(String, VendorProfile)>, RevError> {
+    profile::get_active_profile(starting_dir, profile_override)
+}
+
+/// Serves an addin directory on localhost the way WebView2 loads it inside of Revit, until the listener fails.
+///
+/// The directory is served under `/<addin_name>/`, so relative URLs work and URLs starting with '/' do not.
+/// `/` redirects to the index.html of `web_dir`, the folder inside of the addin directory that holds the web UI.
+pub async fn serve_addin_dir(
+    addin_name: &str,
+    addin_dir: &Path,
+    web_dir: &str,
+    port: u16,
+) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    let server = web::serve::StaticServer::new(addin_name, addin_dir, web_dir);
+    web::serve::serve(listener, std::sync::Arc::new(server)).await;
+    Ok(())
 }
 
-pub use cmds::export::addin_file::AddinFileInfo;
+pub use cmds::audit::{AuditFinding, AuditFindingKind};
+pub use cmds::check::{FrameworkKind, TargetFramework};
+pub use cmds::config::{ConfigScope, SETTINGS, Setting, SettingKind};
+pub use cmds::conflicts::{AssemblyConflict, ShippedAssembly};
+pub use cmds::export::addin_file::{AddinEntryPoint, AddinFileInfo};
+pub use cmds::export::receipt::{InstallReceipt, ReceiptFile, VerifyReport};
+pub use cmds::export::scope::InstallScope;
+pub use cmds::list::InstalledAddin;
+pub use project_config::{
+    ExportConfig, FrontendConfig, ProjectConfig, PropertyNaming, TypesConfig, WebConfig,
+};
+pub use revit_version::RevitVersionSource;
+pub use state::VendorProfile;
+pub use utils::assembly_metadata::{
+    AssemblyMetadata, AssemblyReference, AssemblyType, AssemblyVersion,
+};
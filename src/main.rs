@@ -1,8 +1,13 @@
-use std::io::stdin;
-
 use clap::Parser;
+use cmds::config::ConfigScope;
+use cmds::export::scope::InstallScope;
+mod cancel;
 mod cmds;
+mod error;
+mod events;
 mod external_cmds;
+mod project_config;
+mod revit_version;
 mod state;
 mod utils;
 
@@ -16,6 +21,26 @@ struct Args {
     /// Build the project
     #[command(subcommand)]
     command: Commands,
+
+    /// Where add-ins are installed: user, machine or path=<dir>
+    ///
+    /// 'user' is the current user's AppData folder and 'machine' is the all-users ProgramData
+    /// folder. 'path=<dir>' is a custom folder containing a folder for each Revit version.
+    /// Export, uninstall and verify default to 'user'. List and audit search every scope by default.
+    #[arg(long, global = true)]
+    scope: Option<InstallScope>,
+
+    /// The vendor profile that pre-fills a new .addin file, instead of the project's profile
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// The Revit version (year) to use, instead of the selected one
+    ///
+    /// Without it, the version is read from the REV_REVIT_VERSION environment variable, then the
+    /// "revitVersion" pinned in the project's rev.json, then the global setting. rev only asks
+    /// for a version when none is set and it is running in a terminal.
+    #[arg(long, global = true)]
+    revit_version: Option<String>,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -35,87 +60,425 @@ enum Commands {
 
     /// Display the currently configured Revit version
     ///
-    /// Shows which version of Revit (year) the CLI is currently configured to work with.
-    /// This affects where add-ins are exported to.
+    /// Shows which version of Revit (year) the CLI is currently configured to work with, and
+    /// where it was set. This affects where add-ins are exported to.
     RevitVersion,
 
     /// Change the target Revit version
     ///
     /// Updates which version of Revit (year) to target when exporting add-ins.
-    /// Valid versions are from 2019 to 2025.
+    /// Valid versions are from 2019 to 2025. Projects that pin a version in rev.json keep it.
     ChangeRevitVersion,
 
     /// Locate the project DLL
     ///
     /// Prints out the full path to the project DLL
     Locate,
+
+    /// Check that the built add-in can be loaded by the target Revit version
+    ///
+    /// Builds the project and reads the metadata of the DLL to compare its target framework
+    /// and Revit API references against the target version. Exits with a non-zero code on errors.
+    Check,
+
+    /// Remove the add-in from Revit's add-in directory
+    ///
+    /// Deletes the '.addin' file, the add-in folder and the install receipt for the
+    /// current project from the selected scope.
+    Uninstall,
+
+    /// Verify an installed add-in against its install receipt
+    ///
+    /// Re-hashes every file recorded when the add-in was exported and reports files that
+    /// were modified, are missing, or were added since. Exits with a non-zero code on drift.
+    Verify {
+        /// Path to a '.rev-receipt.json' file. Defaults to the receipt for the current project
+        #[arg(long)]
+        receipt: Option<String>,
+    },
+
+    /// List the add-ins installed for every Revit version
+    ///
+    /// Shows the name, type, vendor, AddInId and assembly of every entry in every '.addin'
+    /// manifest, and whether the assembly actually exists.
+    List {
+        /// Print the add-ins as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Only show add-ins with this vendor ID
+        #[arg(long)]
+        vendor: Option<String>,
+    },
+
+    /// Check the installed add-in manifests for problems
+    ///
+    /// Reports duplicate AddInIds, missing assemblies, unparsable or template manifests,
+    /// disabled manifests and add-in folders that no manifest loads from.
+    /// Exits with a non-zero code if any problems are found.
+    Audit {
+        /// The folder containing a folder for each Revit version. Defaults to the current
+        /// user's Revit add-ins folder, but can point at a copy taken from another machine
+        #[arg(long)]
+        root: Option<String>,
+
+        /// Print the problems as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find dependencies that other installed add-ins ship in a different version
+    ///
+    /// Revit loads every add-in into one process and only the first version of each assembly
+    /// to load is used. Compares the assemblies the project would export against the other
+    /// add-ins installed for the target Revit version and reports which add-in ships which
+    /// version. Exits with a non-zero code if any conflicts are found.
+    Conflicts {
+        /// Print the conflicts as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Work with the add-in's web UI
+    Web {
+        #[command(subcommand)]
+        command: WebCommands,
+    },
+
+    /// Read and change the global and project settings
+    ///
+    /// Global settings, such as the Revit version, apply to every project. Project settings are
+    /// saved in the rev.json file next to the .csproj file. Run 'rev config list' to see every setting.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    /// Manage the vendor profiles that pre-fill the .addin file
+    ///
+    /// A profile holds the vendor ID, description and email of a client. Select it for a project
+    /// with 'rev config set profile <name>', or for one export with --profile.
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ProfileCommands {
+    /// Print every profile
+    List,
+    /// Create a profile, or change the given details of an existing one
+    Set {
+        name: String,
+        /// The vendor ID written to the .addin file
+        #[arg(long)]
+        vendor_id: Option<String>,
+        /// The description of the vendor
+        #[arg(long)]
+        description: Option<String>,
+        /// The vendor's email address
+        #[arg(long)]
+        email: Option<String>,
+    },
+    /// Delete a profile
+    Remove { name: String },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigCommands {
+    /// Print the value of a setting
+    Get {
+        key: String,
+        #[command(flatten)]
+        scope: ConfigScopeArgs,
+    },
+    /// Validate and save the value of a setting
+    ///
+    /// Lists can be given as a JSON array or separated by commas.
+    Set {
+        key: String,
+        value: String,
+        #[command(flatten)]
+        scope: ConfigScopeArgs,
+    },
+    /// Print every setting and its value
+    List {
+        #[command(flatten)]
+        scope: ConfigScopeArgs,
+    },
+    /// Remove a setting so that its default is used
+    Unset {
+        key: String,
+        #[command(flatten)]
+        scope: ConfigScopeArgs,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct ConfigScopeArgs {
+    /// Only use the global settings
+    #[arg(long, conflicts_with = "project")]
+    global: bool,
+    /// Only use the settings of the current project
+    #[arg(long)]
+    project: bool,
+}
+
+impl ConfigScopeArgs {
+    fn to_scope(&self) -> Option<ConfigScope> {
+        if self.global {
+            Some(ConfigScope::Global)
+        } else if self.project {
+            Some(ConfigScope::Project)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum WebCommands {
+    /// Check the web UI for problems that break it when loaded from disk by WebView2
+    ///
+    /// Inspects the Next.js or Vite config, the source files and the exported HTML for absolute
+    /// asset URLs, a missing static export, image optimization, API routes and routers that need
+    /// a server. Exits with a non-zero code if any errors are found.
+    Check,
+    /// Generate TypeScript types for the messages exchanged with the web UI
+    ///
+    /// Reads the C# classes, records and enums matched by "web.types.sources" in rev.json and
+    /// writes matching TypeScript interfaces to "web.types.output". The types are also generated
+    /// when the add-in is exported.
+    GenTypes,
+    /// Serve the exported web UI from the addin directory to preview it without Revit
+    ///
+    /// The addin directory is served under /<addin>/, so relative URLs load the same files they
+    /// would inside of Revit and URLs starting with '/' fail the same way. With --watch, the web
+    /// apps are exported again whenever their source files change.
+    Serve {
+        /// The port to listen on
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+        /// Export the web apps again when their source files change
+        #[arg(long)]
+        watch: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    let scope = args.scope.clone().unwrap_or_default();
+    let revit_version = async || get_revit_version(args.revit_version.as_deref()).await;
 
     match args.command {
         Commands::Build => {
-            let _ = ensure_revit_version_is_set();
-            cmds::build::execute(DEFAULT_STARTING_DIR).await
+            // Building does not need the version, so it is only asked for, never required
+            let _ = revit_version::ensure_revit_version_is_set(
+                DEFAULT_STARTING_DIR,
+                args.revit_version.as_deref(),
+            )
+            .await;
+            cmds::build::execute(DEFAULT_STARTING_DIR, &cancel_on_ctrl_c()).await
         }
         Commands::Export => {
             cmds::export::execute_auto(
                 DEFAULT_STARTING_DIR,
-                ensure_revit_version_is_set().as_str(),
-                &["RealRevitPlugin"],
+                revit_version().await.as_str(),
+                &[],
+                &scope,
+                args.profile.as_deref(),
+                &cancel_on_ctrl_c(),
             )
             .await
         }
         Commands::RevitVersion => {
-            let revit_version = ensure_revit_version_is_set();
-            println!(
-                "Current Revit version: {}. Use change-revit-version if you want to select a different one.",
-                revit_version
-            );
+            let revit_version = revit_version().await;
+            match revit_version::resolve_revit_version(
+                DEFAULT_STARTING_DIR,
+                args.revit_version.as_deref(),
+            ) {
+                Ok(Some((_, source))) if source != revit_version::RevitVersionSource::Global => {
+                    println!(
+                        "Current Revit version: {}, set by {}.",
+                        revit_version, source
+                    )
+                }
+                _ => println!(
+                    "Current Revit version: {}. Use change-revit-version if you want to select a different one.",
+                    revit_version
+                ),
+            }
         }
         Commands::ChangeRevitVersion => {
-            let revit_version = prompt_for_revit_version();
-            let state = state::get_state_or_default();
-            state::save_state(&state::State {
-                revit_version: revit_version.to_string(),
-                ..state
-            });
+            let revit_version = match revit_version::prompt_for_revit_version() {
+                Ok(revit_version) => revit_version,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) =
+                state::update_state(|state| state.revit_version = revit_version.clone()).await
+            {
+                println!("Warning: could not save the Revit version: {}", e);
+            }
+            if let Ok(Some((pinned, source))) =
+                revit_version::resolve_revit_version(DEFAULT_STARTING_DIR, None)
+                && pinned != revit_version
+            {
+                println!(
+                    "Warning: {} is still used here, because it is set by {}",
+                    pinned, source
+                );
+            }
         }
         Commands::Locate => cmds::locate::execute(DEFAULT_STARTING_DIR).await,
+        Commands::Check => {
+            if !cmds::check::execute(DEFAULT_STARTING_DIR, revit_version().await.as_str()).await {
+                std::process::exit(1);
+            }
+        }
+        Commands::Verify { receipt } => {
+            // A receipt given by its path does not need the Revit version
+            let matches = match receipt {
+                Some(receipt) => cmds::verify::execute_receipt(std::path::Path::new(&receipt)),
+                None => cmds::verify::execute(
+                    DEFAULT_STARTING_DIR,
+                    revit_version().await.as_str(),
+                    &scope,
+                ),
+            };
+            if !matches {
+                std::process::exit(1);
+            }
+        }
+        Commands::Uninstall => {
+            cmds::uninstall::execute(DEFAULT_STARTING_DIR, revit_version().await.as_str(), &scope)
+        }
+        Commands::List { json, vendor } => {
+            cmds::list::execute(json, vendor.as_deref(), args.scope.as_ref())
+        }
+        Commands::Audit { root, json } => {
+            if !cmds::audit::execute(root.as_deref(), json, args.scope.as_ref()) {
+                std::process::exit(1);
+            }
+        }
+        Commands::Web { command } => match command {
+            WebCommands::Check => {
+                if !cmds::web::check::execute(DEFAULT_STARTING_DIR) {
+                    std::process::exit(1);
+                }
+            }
+            WebCommands::GenTypes => {
+                if !cmds::web::gen_types::execute(DEFAULT_STARTING_DIR) {
+                    std::process::exit(1);
+                }
+            }
+            WebCommands::Serve { port, watch } => {
+                if !cmds::web::serve::execute(
+                    DEFAULT_STARTING_DIR,
+                    revit_version().await.as_str(),
+                    &scope,
+                    port,
+                    watch,
+                )
+                .await
+                {
+                    std::process::exit(1);
+                }
+            }
+        },
+        Commands::Config { command } => {
+            let success = match command {
+                ConfigCommands::Get { key, scope } => {
+                    cmds::config::execute_get(DEFAULT_STARTING_DIR, &key, scope.to_scope())
+                }
+                ConfigCommands::Set { key, value, scope } => {
+                    cmds::config::execute_set(DEFAULT_STARTING_DIR, &key, &value, scope.to_scope())
+                        .await
+                }
+                ConfigCommands::List { scope } => {
+                    cmds::config::execute_list(DEFAULT_STARTING_DIR, scope.to_scope())
+                }
+                ConfigCommands::Unset { key, scope } => {
+                    cmds::config::execute_unset(DEFAULT_STARTING_DIR, &key, scope.to_scope()).await
+                }
+            };
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Commands::Profile { command } => {
+            let success = match command {
+                ProfileCommands::List => {
+                    cmds::profile::execute_list();
+                    true
+                }
+                ProfileCommands::Set {
+                    name,
+                    vendor_id,
+                    description,
+                    email,
+                } => {
+                    cmds::profile::execute_set(
+                        &name,
+                        vendor_id.as_deref(),
+                        description.as_deref(),
+                        email.as_deref(),
+                    )
+                    .await
+                }
+                ProfileCommands::Remove { name } => cmds::profile::execute_remove(&name).await,
+            };
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Commands::Conflicts { json } => {
+            if !cmds::conflicts::execute(
+                DEFAULT_STARTING_DIR,
+                revit_version().await.as_str(),
+                json,
+                args.scope.as_ref(),
+            )
+            .await
+            {
+                std::process::exit(1);
+            }
+        }
     }
 }
 
-pub fn ensure_revit_version_is_set() -> String {
-    if let Some(state) = state::get_state() {
-        state.revit_version
-    } else {
-        let revit_version = prompt_for_revit_version();
-        let state = state::get_state_or_default();
-        state::save_state(&state::State {
-            revit_version: revit_version.to_string(),
-            ..state
-        });
-        revit_version
-    }
+/// Returns a token that is cancelled when Ctrl-C is pressed, so that build tools are stopped and a
+/// partial export is rolled back before exiting. A second Ctrl-C exits straight away
+fn cancel_on_ctrl_c() -> cancel::CancellationToken {
+    let cancel = cancel::CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        println!("Cancelling. Press Ctrl-C again to quit immediately");
+        token.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    cancel
 }
-fn prompt_for_revit_version() -> String {
-    println!("Enter the Revit version you want to use:");
-    let mut revit_version = String::new();
-    stdin().read_line(&mut revit_version).unwrap();
-    let revit_version = revit_version.trim().to_string();
-    if let Ok(revit_version) = revit_version.parse::<i32>() {
-        if (2019..=2025).contains(&revit_version) {
-            return revit_version.to_string();
-        }
-        println!("Invalid Revit version. You must specify a version between 2019 and 2025.");
-        return prompt_for_revit_version();
+
+/// Returns the Revit version for the current project, asking for one if none is set. Exits if
+/// there is no version and none can be asked for
+async fn get_revit_version(version_override: Option<&str>) -> String {
+    match revit_version::ensure_revit_version_is_set(DEFAULT_STARTING_DIR, version_override).await {
+        Ok(revit_version) => revit_version,
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
-    println!(
-        "Invalid Revit version. You must specify a version between 2019 and 2025. Include only the year."
-    );
-    prompt_for_revit_version()
 }
 
 // cargo install --path C:\Users\grieger.EMA\Desktop\Rust\revitcli
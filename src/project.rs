@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::cancel::CancellationToken;
+use crate::cmds::export::{self, addin_file::AddinFileInfo};
+use crate::cmds::{build, locate, profile};
+use crate::error::{DiscoveryError, RevError};
+use crate::events::{Event, EventSink, NoEvents, Phase};
+use crate::project_config::{self, ProjectConfig};
+use crate::utils::error_list::ErrorList;
+
+/// A C# add-in project. The project is found once by `discover`, and its files are looked up
+/// only the first time they are needed, so a handle can be kept and reused for every operation
+pub struct Project {
+    starting_dir: PathBuf,
+    info: locate::ProjectInfo,
+    /// The built DLL, once it has been found
+    main_dll_path: OnceLock<PathBuf>,
+    events: Arc<dyn EventSink>,
+    cancel: CancellationToken,
+}
+
+impl Project {
+    /// Finds the .csproj file in `path` or up to 3 folders below it
+    pub fn discover(path: impl AsRef<Path>) -> Result<Project, RevError> {
+        Project::discover_with_events(path, NoEvents)
+    }
+
+    /// Same as `discover`, reporting the progress of this and every later operation on the
+    /// project to `events`
+    pub fn discover_with_events(
+        path: impl AsRef<Path>,
+        events: impl EventSink + 'static,
+    ) -> Result<Project, RevError> {
+        let starting_dir = path.as_ref().to_path_buf();
+        events.emit(Event::PhaseStarted(Phase::Discover));
+        let info = locate::get_project_info(&starting_dir.to_string_lossy());
+        events.emit(Event::PhaseFinished {
+            phase: Phase::Discover,
+            success: info.is_ok(),
+        });
+        let info = info.map_err(|_| DiscoveryError::Project {
+            starting_dir: starting_dir.clone(),
+        })?;
+        Ok(Project {
+            starting_dir,
+            info,
+            main_dll_path: OnceLock::new(),
+            events: Arc::new(events),
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Stops the builds and exports of the project when `token` is cancelled. A cancelled
+    /// export is rolled back, leaving the addins directory as it was
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// The name of the project, which is the name of the .csproj file without its extension
+    pub fn name(&self) -> &str {
+        &self.info.project_name
+    }
+
+    /// The folder that contains the .csproj file
+    pub fn directory(&self) -> &Path {
+        Path::new(&self.info.csproj_dir)
+    }
+
+    pub fn csproj_path(&self) -> PathBuf {
+        self.directory().join(&self.info.full_project_name)
+    }
+
+    /// The path of the project's '.addin' file, which may not exist yet
+    pub fn manifest_path(&self) -> PathBuf {
+        self.directory().join(format!("{}.addin", self.name()))
+    }
+
+    /// Reads the project's rev.json, or returns the defaults if it does not have one
+    pub fn config(&self) -> Result<ProjectConfig, RevError> {
+        project_config::get_project_config(&self.info.csproj_dir)
+    }
+
+    /// Builds the project. Returns the output from the build command if it was successful
+    pub async fn build(&self) -> Result<String, RevError> {
+        // A rev.json that cannot be read is reported by the operations that need its other settings
+        self.build_with_config(&self.config().unwrap_or_default())
+            .await
+    }
+
+    async fn build_with_config(&self, project_config: &ProjectConfig) -> Result<String, RevError> {
+        build::build_csproj(
+            &self.csproj_path().to_string_lossy(),
+            project_config,
+            self.events.as_ref(),
+            &self.cancel,
+        )
+        .await
+    }
+
+    /// Builds the project, then exports it to each of the Revit addins directories in `targets`
+    pub async fn export(&self, targets: &[&Path]) -> ErrorList {
+        self.export_with_dlls(targets, &[]).await
+    }
+
+    /// Same as `export`, also exporting `extra_dlls`. Dependencies of the addin are found
+    /// automatically, so these are only needed for DLLs that are loaded by name
+    pub async fn export_with_dlls(&self, targets: &[&Path], extra_dlls: &[&str]) -> ErrorList {
+        let mut error_list = ErrorList::new();
+        let project_config = match self.config() {
+            Ok(project_config) => project_config,
+            Err(e) => {
+                error_list.add_typed_error(e);
+                return error_list;
+            }
+        };
+        // A missing profile is reported before the build, which can take minutes
+        let profile = match profile::get_project_profile(&project_config, None) {
+            Ok(profile) => profile.map(|(_, profile)| profile),
+            Err(e) => {
+                error_list.add_typed_error(e);
+                return error_list;
+            }
+        };
+        if let Err(e) = self.build_with_config(&project_config).await {
+            error_list.add_typed_error(e);
+            return error_list;
+        }
+        let main_dll_path = match self.locate() {
+            Ok(main_dll_path) => main_dll_path,
+            Err(e) => {
+                error_list.add_typed_error(e);
+                return error_list;
+            }
+        };
+        let project = export::BuiltProject {
+            info: &self.info,
+            config: &project_config,
+            main_dll_path: &main_dll_path,
+        };
+        for target in targets {
+            error_list.extend(
+                &export::export_project(
+                    &self.starting_dir.to_string_lossy(),
+                    &project,
+                    extra_dlls,
+                    target,
+                    profile.as_ref(),
+                    self.events.as_ref(),
+                    &self.cancel,
+                )
+                .await,
+            );
+        }
+        error_list
+    }
+
+    /// Returns the absolute path of the built DLL. Returns an error if the project has not been built
+    pub fn locate(&self) -> Result<PathBuf, RevError> {
+        if let Some(main_dll_path) = self.main_dll_path.get() {
+            return Ok(main_dll_path.clone());
+        }
+        // A DLL that is not found is searched for again next time, as it may have been built since
+        let main_dll_path = locate::find_project_dll(true, self.name(), &self.info)
+            .map(PathBuf::from)
+            .map_err(|_| DiscoveryError::BuildOutput {
+                dll_name: format!("{}.dll", self.name()),
+                csproj_dir: self.directory().to_path_buf(),
+            })?;
+        Ok(self.main_dll_path.get_or_init(|| main_dll_path).clone())
+    }
+
+    /// Reads the project's '.addin' file
+    pub fn manifest(&self) -> Result<AddinFileInfo, RevError> {
+        Ok(export::addin_file::get_addin_file_info(
+            &self.manifest_path().to_string_lossy(),
+        )?)
+    }
+
+    /// Returns the absolute paths of every DLL in the project folder, including the build output
+    pub fn dlls(&self) -> Vec<PathBuf> {
+        locate::find_all_project_dlls(&self.info)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for Project {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Project")
+            .field("starting_dir", &self.starting_dir)
+            .field("info", &self.info)
+            .field("main_dll_path", &self.main_dll_path)
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::RevError;
+use crate::external_cmds::package_manager::PackageManager;
+
+/// The name of the per-project config file, which lives next to the csproj file
+pub const PROJECT_CONFIG_FILE_NAME: &str = "rev.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProjectConfig {
+    /// The Revit version the project is built and exported for, instead of the global one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revit_version: Option<String>,
+    /// The vendor profile that pre-fills the '.addin' manifest of the project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub export: ExportConfig,
+    pub web: WebConfig,
+    pub timeouts: TimeoutConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Glob patterns, relative to the build output directory, of extra files to export with the addin.
+    /// A pattern ending in '/' includes everything in that folder, e.g. "runtimes/"
+    pub include: Vec<String>,
+    /// Glob patterns, relative to the build output directory, of files to leave out even if they are included
+    pub exclude: Vec<String>,
+}
+
+/// How many seconds each step may run before it is stopped. A step without a timeout can run
+/// for as long as it needs
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TimeoutConfig {
+    /// Building the C# project, including the dotnet fallback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<u64>,
+    /// Installing the dependencies of the web apps and building them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_build: Option<u64>,
+    /// Exporting to one addins directory, including the web build but not the C# build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export: Option<u64>,
+}
+
+impl TimeoutConfig {
+    pub fn build_timeout(&self) -> Option<Duration> {
+        self.build.map(Duration::from_secs)
+    }
+
+    pub fn web_build_timeout(&self) -> Option<Duration> {
+        self.web_build.map(Duration::from_secs)
+    }
+
+    pub fn export_timeout(&self) -> Option<Duration> {
+        self.export.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WebConfig {
+    /// The package manager used to build the web app. Detected from the web app when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<PackageManager>,
+    /// Glob patterns of folders that are never searched for web apps, matched against the
+    /// folder name or its path relative to the directory rev is run from, e.g. "tools/*".
+    /// Defaults to 'node_modules', since the packages inside of it have build scripts of their own
+    #[serde(skip_serializing_if = "is_default_web_exclude")]
+    pub exclude: Vec<String>,
+    /// The frontends to build and export with the addin. When empty, a single web app is
+    /// detected and exported to the 'web' folder
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frontends: Vec<FrontendConfig>,
+    /// The C# message classes to generate TypeScript types from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<TypesConfig>,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            package_manager: None,
+            exclude: default_web_exclude(),
+            frontends: Vec::new(),
+            types: None,
+        }
+    }
+}
+
+fn default_web_exclude() -> Vec<String> {
+    vec!["node_modules".to_string()]
+}
+
+fn is_default_web_exclude(exclude: &Vec<String>) -> bool {
+    *exclude == default_web_exclude()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FrontendConfig {
+    /// The folder containing the frontend's package.json, relative to the csproj directory
+    pub dir: String,
+    /// The command that builds the frontend, run with the system shell in `dir`.
+    /// Defaults to running the "build" script with the package manager
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_command: Option<String>,
+    /// The folder the build writes the static files to, relative to `dir`, e.g. "dist".
+    /// Detected from the common Next.js, Vite and Angular output folders when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// The folder inside of the addin directory that the static files are copied to
+    pub destination: String,
+    /// Overrides the package manager set for all web apps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<PackageManager>,
+}
+
+impl Default for FrontendConfig {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            build_command: None,
+            output_dir: None,
+            destination: "web".to_string(),
+            package_manager: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TypesConfig {
+    /// Glob patterns, relative to the csproj directory, of the C# files that declare the messages
+    /// sent between the addin and its web UI, e.g. "Messages/**/*.cs"
+    pub sources: Vec<String>,
+    /// The TypeScript file to write, relative to the csproj directory, e.g. "ui/src/messages.ts"
+    pub output: String,
+    /// How property names are written in JSON. Should match the JsonSerializerOptions of the addin
+    pub property_naming: PropertyNaming,
+    /// Writes every enum as a union of its member names, for when the addin serializes all enums
+    /// with JsonStringEnumConverter. Enums with the converter attribute are always written this way
+    pub enums_as_strings: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PropertyNaming {
+    /// The C# property names as they are, which is the default of JsonSerializer and Json.NET
+    #[default]
+    Preserve,
+    /// JsonNamingPolicy.CamelCase, e.g. "DisplayName" is written as "displayName"
+    CamelCase,
+}
+
+pub fn get_project_config_path(csproj_dir: &str) -> PathBuf {
+    Path::new(csproj_dir).join(PROJECT_CONFIG_FILE_NAME)
+}
+
+/// Returns the config for the project, or the default config if the project does not have one.
+/// Returns an error if the config exists but cannot be read or parsed
+pub fn get_project_config(csproj_dir: &str) -> Result<ProjectConfig, RevError> {
+    let config_path = get_project_config_path(csproj_dir);
+    match File::open(&config_path) {
+        Ok(config_file) => serde_json::from_reader(config_file).map_err(|e| {
+            RevError::config(format!(
+                "Could not parse {}: {}",
+                config_path.to_string_lossy(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ProjectConfig::default()),
+        Err(e) => Err(RevError::io(config_path, e)),
+    }
+}
+
+/// Returns the project's rev.json as JSON, or an empty object if the project does not have one.
+/// Unlike `get_project_config`, settings that this version of rev does not know about are kept
+pub fn get_project_config_json(csproj_dir: &str) -> Result<serde_json::Value, RevError> {
+    let config_path = get_project_config_path(csproj_dir);
+    match std::fs::read_to_string(&config_path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+            RevError::config(format!(
+                "Could not parse {}: {}",
+                config_path.to_string_lossy(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(serde_json::Value::Object(serde_json::Map::new()))
+        }
+        Err(e) => Err(RevError::io(config_path, e)),
+    }
+}
+
+/// Saves the project's rev.json, returning an error without saving if it is not a valid config
+pub fn save_project_config_json(
+    csproj_dir: &str,
+    config: &serde_json::Value,
+) -> Result<(), RevError> {
+    let config_path = get_project_config_path(csproj_dir);
+    serde_json::from_value::<ProjectConfig>(config.clone())
+        .map_err(|e| RevError::config(format!("The new settings are not valid: {}", e)))?;
+    // Serializing a JSON value cannot fail
+    let contents = serde_json::to_string_pretty(config).unwrap_or_default();
+    std::fs::write(&config_path, contents + "\n").map_err(|e| RevError::io(config_path, e))
+}
@@ -0,0 +1,142 @@
+use std::fmt::Display;
+use std::io::{IsTerminal, stdin};
+
+use crate::cmds::locate;
+use crate::error::RevError;
+use crate::project_config;
+use crate::state;
+
+/// Selects the Revit version for every command run while it is set, e.g. on a build server
+pub const REVIT_VERSION_VARIABLE: &str = "REV_REVIT_VERSION";
+/// The Revit versions that rev can build and export add-ins for
+pub const SUPPORTED_REVIT_VERSIONS: std::ops::RangeInclusive<u32> = 2019..=2025;
+
+/// Where the Revit version used by a command was set, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevitVersionSource {
+    /// The `--revit-version` flag
+    Flag,
+    /// The `REV_REVIT_VERSION` environment variable
+    Environment,
+    /// The "revitVersion" pinned in the project's rev.json
+    Project,
+    /// The version saved in the global settings
+    Global,
+}
+
+impl Display for RevitVersionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RevitVersionSource::Flag => write!(f, "--revit-version"),
+            RevitVersionSource::Environment => write!(f, "{}", REVIT_VERSION_VARIABLE),
+            RevitVersionSource::Project => {
+                write!(f, "{}", project_config::PROJECT_CONFIG_FILE_NAME)
+            }
+            RevitVersionSource::Global => write!(f, "the global settings"),
+        }
+    }
+}
+
+/// Returns the Revit version for the project in `starting_dir` and where it was set:
+/// `version_override` if given, then `REV_REVIT_VERSION`, the version pinned by the project and
+/// the global setting. Returns None if none of them is set, and an error if the version that
+/// applies is not a supported year
+pub fn resolve_revit_version(
+    starting_dir: &str,
+    version_override: Option<&str>,
+) -> Result<Option<(String, RevitVersionSource)>, RevError> {
+    if let Some(version) = version_override {
+        return parse_revit_version(version)
+            .map(|version| Some((version, RevitVersionSource::Flag)))
+            .map_err(|e| RevError::config(format!("--revit-version: {}", e)));
+    }
+    if let Ok(version) = std::env::var(REVIT_VERSION_VARIABLE)
+        && !version.trim().is_empty()
+    {
+        return parse_revit_version(&version)
+            .map(|version| Some((version, RevitVersionSource::Environment)))
+            .map_err(|e| RevError::config(format!("{}: {}", REVIT_VERSION_VARIABLE, e)));
+    }
+    // Commands such as 'rev list' can run outside of a project
+    if let Ok(project_info) = locate::get_project_info(starting_dir)
+        && let Some(version) =
+            project_config::get_project_config(&project_info.csproj_dir)?.revit_version
+    {
+        return parse_revit_version(&version)
+            .map(|version| Some((version, RevitVersionSource::Project)))
+            .map_err(|e| {
+                RevError::config(format!(
+                    "The revitVersion in {}: {}",
+                    project_config::PROJECT_CONFIG_FILE_NAME,
+                    e
+                ))
+            });
+    }
+    // The global version is saved as an empty string when it has not been selected
+    Ok(state::get_state()
+        .map(|state| state.revit_version)
+        .filter(|version| !version.is_empty())
+        .map(|version| (version, RevitVersionSource::Global)))
+}
+
+/// Returns the Revit version for the project in `starting_dir`, asking for one and saving it to
+/// the global settings if none is set. Returns an error instead of asking when stdin is not a
+/// terminal, so that scripts and build servers never wait for input
+pub async fn ensure_revit_version_is_set(
+    starting_dir: &str,
+    version_override: Option<&str>,
+) -> Result<String, String> {
+    if let Some((version, _)) =
+        resolve_revit_version(starting_dir, version_override).map_err(|e| e.to_string())?
+    {
+        return Ok(version);
+    }
+    let version = prompt_for_revit_version()?;
+    if let Err(e) = state::update_state(|state| state.revit_version = version.clone()).await {
+        println!("Warning: could not save the Revit version: {}", e);
+    }
+    Ok(version)
+}
+
+/// Asks for a Revit version until a supported one is entered. Returns an error if stdin is not a
+/// terminal or is closed
+pub fn prompt_for_revit_version() -> Result<String, String> {
+    if !stdin().is_terminal() {
+        return Err(format!(
+            "rev cannot ask for the Revit version because it is not running in a terminal. Pass --revit-version <year>, set {}, or run 'rev config set revitVersion <year>'",
+            REVIT_VERSION_VARIABLE
+        ));
+    }
+    loop {
+        println!("Enter the Revit version you want to use:");
+        let mut revit_version = String::new();
+        match stdin().read_line(&mut revit_version) {
+            Ok(0) => return Err("No Revit version was entered".to_string()),
+            Ok(_) => {}
+            Err(e) => return Err(format!("Could not read the Revit version: {}", e)),
+        }
+        match parse_revit_version(&revit_version) {
+            Ok(revit_version) => return Ok(revit_version),
+            Err(e) => println!("Invalid Revit version. {}", e),
+        }
+    }
+}
+
+/// Returns the year of a supported Revit version, e.g. "2025", or an error explaining why it is not one
+pub fn parse_revit_version(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let Ok(year) = value.parse::<u32>() else {
+        return Err(format!(
+            "'{}' is not a Revit version. Include only the year, e.g. 2025",
+            value
+        ));
+    };
+    if !SUPPORTED_REVIT_VERSIONS.contains(&year) {
+        return Err(format!(
+            "You must specify a version between {} and {}",
+            SUPPORTED_REVIT_VERSIONS.start(),
+            SUPPORTED_REVIT_VERSIONS.end()
+        ));
+    }
+    Ok(year.to_string())
+}
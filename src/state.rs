@@ -1,41 +1,355 @@
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-#[derive(Serialize, Deserialize, Default)]
+use crate::error::RevError;
+
+/// Overrides the directory the state is stored in, which defaults to '<data dir>/rev'
+pub const REV_HOME_VARIABLE: &str = "REV_HOME";
+/// The version of the state file written by this build. Older files are migrated when read
+pub const STATE_VERSION: u32 = 1;
+
+const STATE_FILE_NAME: &str = "RevitState.json";
+const LOCK_FILE_NAME: &str = "RevitState.json.lock";
+/// How long to wait for another rev process to finish writing the state
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check whether another rev process has finished writing the state
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+/// A lock older than this was left behind by a process that did not exit cleanly
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// Upgrades the state from each version to the next, indexed by the version it upgrades from
+const MIGRATIONS: [fn(&mut Map<String, Value>); 1] = [migrate_from_v0];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct State {
+    pub version: u32,
     pub revit_version: String,
     pub email_address: String,
+    /// Vendor identities for the clients add-ins are built for, keyed by profile name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, VendorProfile>,
+    /// Settings this build does not know about, written by a newer version of rev. They are kept
+    /// so that saving the state does not remove them
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-pub fn save_state(state: &State) {
-    let app_data_dir = dirs::data_dir();
-
-    if let Some(app_data_dir) = app_data_dir {
-        let state_file_path = app_data_dir.join("rev");
-        if !state_file_path.exists() {
-            std::fs::create_dir_all(state_file_path.clone()).unwrap();
-        }
-        let state_json_path = state_file_path.join("RevitState.json");
-        if let Ok(state_file) = File::create(state_json_path) {
-            serde_json::to_writer(state_file, state).unwrap();
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            revit_version: String::new(),
+            email_address: String::new(),
+            profiles: BTreeMap::new(),
+            other: Map::new(),
         }
     }
 }
 
-pub fn get_state() -> Option<State> {
-    let app_data_dir = dirs::data_dir();
-    if let Some(app_data_dir) = app_data_dir {
-        let state_file_path = app_data_dir.join("rev/RevitState.json");
-        if let Ok(state_file) = File::open(state_file_path) {
-            let state: State = serde_json::from_reader(state_file).unwrap();
-            return Some(state);
-        }
+/// The vendor details written to the '.addin' manifests of a client's add-ins. Empty fields are
+/// prompted for as usual
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct VendorProfile {
+    pub vendor_id: String,
+    pub vendor_description: String,
+    pub email_address: String,
+}
+
+/// Returns the directory the state is stored in: `REV_HOME` if it is set, or '<data dir>/rev'
+pub fn get_state_dir() -> Option<PathBuf> {
+    match std::env::var_os(REV_HOME_VARIABLE) {
+        Some(rev_home) if !rev_home.is_empty() => Some(PathBuf::from(rev_home)),
+        _ => dirs::data_dir().map(|data_dir| data_dir.join("rev")),
     }
-    None
+}
+
+/// Reads the state, changes it and saves it, without another rev process writing in between.
+/// The file is replaced in a single step, so it is never left half written. Returns the saved state
+pub async fn update_state(update: impl FnOnce(&mut State)) -> Result<State, RevError> {
+    let state_dir = get_state_dir().ok_or_else(|| {
+        RevError::config(format!(
+            "Could not find the application data directory. Set {} to the folder to save the settings in",
+            REV_HOME_VARIABLE
+        ))
+    })?;
+    std::fs::create_dir_all(&state_dir).map_err(|e| RevError::io(&state_dir, e))?;
+    let _lock = StateLock::acquire(&state_dir).await?;
+    let state_path = state_dir.join(STATE_FILE_NAME);
+    let mut state = read_state_file(&state_path).unwrap_or_default();
+    update(&mut state);
+    write_state_file(&state_path, &state)?;
+    Ok(state)
+}
+
+/// Returns the saved state, or None if nothing has been saved yet
+///
+/// A state file that cannot be read is moved aside as a backup, and None is returned after
+/// printing a warning, so that a damaged file never stops rev from running
+pub fn get_state() -> Option<State> {
+    read_state_file(&get_state_dir()?.join(STATE_FILE_NAME))
 }
 
 pub fn get_state_or_default() -> State {
     get_state().unwrap_or_default()
 }
+
+fn read_state_file(state_path: &Path) -> Option<State> {
+    let contents = std::fs::read_to_string(state_path).ok()?;
+    match parse_state(&contents) {
+        Ok(state) => Some(state),
+        Err(e) => {
+            let backup_path = state_path.with_file_name(format!(
+                "{}.corrupt-{}",
+                STATE_FILE_NAME,
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            ));
+            match std::fs::rename(state_path, &backup_path) {
+                Ok(_) => println!(
+                    "Warning: {} could not be read ({}). It was moved to {} and the default settings will be used",
+                    state_path.to_string_lossy(),
+                    e,
+                    backup_path.to_string_lossy()
+                ),
+                Err(_) => println!(
+                    "Warning: {} could not be read ({}). The default settings will be used",
+                    state_path.to_string_lossy(),
+                    e
+                ),
+            }
+            None
+        }
+    }
+}
+
+/// Parses the contents of a state file, migrating it from older versions
+fn parse_state(contents: &str) -> Result<State, String> {
+    let mut value = match serde_json::from_str(contents).map_err(|e| e.to_string())? {
+        Value::Object(value) => value,
+        _ => return Err("the state is not a JSON object".to_string()),
+    };
+    // Files written before the version was recorded have no version field
+    let version = match value.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or("the version is not a number")?,
+    };
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(&mut value);
+    }
+    // Newer files are read as well as possible, and keep their version so that saving does not
+    // mark them as older than they are
+    value.insert(
+        "version".to_string(),
+        Value::from(version.max(STATE_VERSION)),
+    );
+    serde_json::from_value(Value::Object(value)).map_err(|e| e.to_string())
+}
+
+/// Version 0 is every file written before the version field was added. Its settings are unchanged
+fn migrate_from_v0(_state: &mut Map<String, Value>) {}
+
+/// Writes the state to a temporary file next to the state file, then renames it over the state file
+fn write_state_file(state_path: &Path, state: &State) -> Result<(), RevError> {
+    let temp_path =
+        state_path.with_file_name(format!("{}.{}.tmp", STATE_FILE_NAME, std::process::id()));
+    let written = File::create(&temp_path).and_then(|mut temp_file| {
+        serde_json::to_writer_pretty(&mut temp_file, state)?;
+        temp_file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| std::fs::rename(&temp_path, state_path)) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(RevError::io(state_path, e));
+    }
+    Ok(())
+}
+
+/// An advisory lock on the state, held by creating a lock file next to it. Other rev processes
+/// wait for the file to be removed before they write the state. It is removed when dropped
+struct StateLock {
+    path: PathBuf,
+}
+
+impl StateLock {
+    /// Waits for the lock without blocking the thread, so other tasks keep running meanwhile
+    async fn acquire(state_dir: &Path) -> Result<StateLock, RevError> {
+        let path = state_dir.join(LOCK_FILE_NAME);
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut lock_file) => {
+                    let _ = write!(lock_file, "{}", std::process::id());
+                    return Ok(StateLock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        remove_stale_lock(state_dir, &path);
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                        return Err(RevError::StateLocked { lock_path: path });
+                    }
+                    tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(e) => return Err(RevError::io(path, e)),
+            }
+        }
+    }
+}
+
+fn is_stale(lock_path: &Path) -> bool {
+    std::fs::metadata(lock_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_LOCK_AGE)
+}
+
+/// Removes a lock left behind by a process that did not exit cleanly. The lock is renamed away
+/// first, which only one of the processes waiting for it can do. If another process took the lock
+/// after it was found to be stale, the fresh lock was renamed instead and is put back
+fn remove_stale_lock(state_dir: &Path, lock_path: &Path) {
+    let stale_path = state_dir.join(format!("{}.{}.stale", LOCK_FILE_NAME, std::process::id()));
+    if std::fs::rename(lock_path, &stale_path).is_err() {
+        return;
+    }
+    if !is_stale(&stale_path) {
+        // Linking fails rather than replacing a lock that was taken in the meantime
+        let _ = std::fs::hard_link(&stale_path, lock_path);
+    }
+    let _ = std::fs::remove_file(&stale_path);
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_files_without_a_version() {
+        let state = parse_state(r#"{"revit_version": "2024", "email_address": "a@b.com"}"#)
+            .expect("a version 0 file is read");
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.revit_version, "2024");
+        assert_eq!(state.email_address, "a@b.com");
+        assert!(state.other.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_version_and_settings_of_newer_files() {
+        let newer_version = STATE_VERSION + 1;
+        let contents = format!(
+            r#"{{"version": {}, "revit_version": "2025", "newSetting": {{"enabled": true}}}}"#,
+            newer_version
+        );
+        let state = parse_state(&contents).expect("a newer file is read");
+        assert_eq!(state.version, newer_version);
+        assert_eq!(state.revit_version, "2025");
+
+        let saved = serde_json::to_value(&state).unwrap();
+        assert_eq!(saved["version"], newer_version);
+        assert_eq!(saved["newSetting"]["enabled"], true);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_a_state() {
+        assert!(parse_state("{").is_err());
+        assert!(parse_state("[]").is_err());
+        assert!(parse_state(r#"{"version": "one"}"#).is_err());
+    }
+
+    #[test]
+    fn moves_a_corrupt_file_aside() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let state_path = state_dir.path().join(STATE_FILE_NAME);
+        std::fs::write(&state_path, "{ not json").unwrap();
+
+        assert!(read_state_file(&state_path).is_none());
+        assert!(!state_path.exists());
+        let backups: Vec<_> = std::fs::read_dir(state_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(
+            backups[0]
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with(&format!("{}.corrupt-", STATE_FILE_NAME))
+        );
+        assert_eq!(std::fs::read_to_string(&backups[0]).unwrap(), "{ not json");
+
+        // The next update starts over from the default state
+        assert!(read_state_file(&state_path).is_none());
+        write_state_file(&state_path, &State::default()).unwrap();
+        assert_eq!(read_state_file(&state_path).unwrap().version, STATE_VERSION);
+    }
+
+    #[tokio::test]
+    async fn removes_a_stale_lock() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let lock_path = state_dir.path().join(LOCK_FILE_NAME);
+        let lock_file = File::create(&lock_path).unwrap();
+        lock_file
+            .set_modified(SystemTime::now() - STALE_LOCK_AGE * 2)
+            .unwrap();
+        drop(lock_file);
+
+        let lock = StateLock::acquire(state_dir.path()).await.unwrap();
+        assert!(!is_stale(&lock_path));
+        drop(lock);
+        assert!(!lock_path.exists());
+        assert_eq!(std::fs::read_dir(state_dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn puts_back_a_lock_taken_after_it_was_found_stale() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let lock_path = state_dir.path().join(LOCK_FILE_NAME);
+        std::fs::write(&lock_path, "42").unwrap();
+
+        remove_stale_lock(state_dir.path(), &lock_path);
+        assert_eq!(std::fs::read_to_string(&lock_path).unwrap(), "42");
+        assert_eq!(std::fs::read_dir(state_dir.path()).unwrap().count(), 1);
+    }
+
+    /// Every test that reads `REV_HOME` is in this one test, as the environment is shared by the
+    /// tests running at the same time
+    #[tokio::test]
+    async fn saves_the_state_in_rev_home() {
+        let rev_home = tempfile::tempdir().unwrap();
+        let state_dir = rev_home.path().join("nested");
+        // SAFETY: no other test reads or writes the environment
+        unsafe { std::env::set_var(REV_HOME_VARIABLE, &state_dir) };
+
+        assert_eq!(get_state_dir(), Some(state_dir.clone()));
+        assert!(get_state().is_none());
+        let saved = update_state(|state| state.revit_version = "2026".to_string())
+            .await
+            .unwrap();
+        assert_eq!(saved.revit_version, "2026");
+        assert_eq!(get_state().unwrap().revit_version, "2026");
+        assert!(state_dir.join(STATE_FILE_NAME).exists());
+        assert!(!state_dir.join(LOCK_FILE_NAME).exists());
+
+        // An empty REV_HOME is treated as unset
+        unsafe { std::env::set_var(REV_HOME_VARIABLE, "") };
+        assert_eq!(get_state_dir(), dirs::data_dir().map(|dir| dir.join("rev")));
+        unsafe { std::env::remove_var(REV_HOME_VARIABLE) };
+    }
+}
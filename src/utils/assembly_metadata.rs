@@ -0,0 +1,1026 @@
+// A minimal reader for the CLI metadata embedded in .NET assemblies (ECMA-335 partition II),
+// so that built DLLs can be inspected without needing .NET to be installed
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Serialized as a dotted string, e.g. "13.0.0.0"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct AssemblyVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub build: u16,
+    pub revision: u16,
+}
+
+impl Display for AssemblyVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.revision
+        )
+    }
+}
+
+impl FromStr for AssemblyVersion {
+    type Err = String;
+
+    /// Parses a version such as "13.0.0.0". Missing parts default to 0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = [0u16; 4];
+        for (i, part) in s.split('.').enumerate() {
+            if i >= parts.len() {
+                return Err(format!("Invalid assembly version: {}", s));
+            }
+            parts[i] = part
+                .parse()
+                .map_err(|_| format!("Invalid assembly version: {}", s))?;
+        }
+        Ok(Self {
+            major: parts[0],
+            minor: parts[1],
+            build: parts[2],
+            revision: parts[3],
+        })
+    }
+}
+
+impl From<AssemblyVersion> for String {
+    fn from(version: AssemblyVersion) -> Self {
+        version.to_string()
+    }
+}
+
+impl TryFrom<String> for AssemblyVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyReference {
+    pub name: String,
+    pub version: AssemblyVersion,
+}
+
+/// A class, interface or other type defined in an assembly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyType {
+    /// The namespace and name of the type. Nested types are separated from the type that
+    /// contains them by '+', e.g. "MyAddin.Commands+Export"
+    pub full_name: String,
+    pub is_public: bool,
+    /// True for abstract classes and interfaces, which cannot be instantiated
+    pub is_abstract: bool,
+    /// The full names of the interfaces the type implements, including those implemented by
+    /// base types in the same assembly. Generic interfaces are not included
+    pub interfaces: Vec<String>,
+    /// The first base type that is defined in another assembly, whose interfaces are not known.
+    /// None if every base type is in this assembly or is part of .NET
+    #[serde(default)]
+    pub external_base_type: Option<String>,
+}
+
+impl AssemblyType {
+    pub fn implements(&self, interface: &str) -> bool {
+        self.interfaces.iter().any(|name| name == interface)
+    }
+}
+
+/// The parts of an assembly's metadata that we care about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyMetadata {
+    pub name: String,
+    pub version: AssemblyVersion,
+    /// The value of the assembly's `TargetFrameworkAttribute`, e.g. ".NETFramework,Version=v4.8"
+    pub target_framework: Option<String>,
+    pub references: Vec<AssemblyReference>,
+    pub types: Vec<AssemblyType>,
+}
+
+impl AssemblyMetadata {
+    /// Returns the referenced assembly with the given name, ignoring case
+    pub fn get_reference(&self, name: &str) -> Option<&AssemblyReference> {
+        self.references
+            .iter()
+            .find(|reference| reference.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the type with the given full name. Unlike assembly names, type names are case sensitive
+    pub fn get_type(&self, full_name: &str) -> Option<&AssemblyType> {
+        self.types.iter().find(|t| t.full_name == full_name)
+    }
+}
+
+/// Reads the metadata of the .NET assembly at the given path.
+/// Returns an error if the file is not a .NET assembly or its metadata is malformed
+pub fn read_assembly_metadata(path: &Path) -> Result<AssemblyMetadata, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    parse_assembly_metadata(&bytes)
+}
+
+fn parse_assembly_metadata(bytes: &[u8]) -> Result<AssemblyMetadata, String> {
+    let metadata = Metadata::parse(bytes)?;
+
+    if metadata.tables.row_count(TABLE_ASSEMBLY) == 0 {
+        return Err("The file does not contain an assembly manifest".to_string());
+    }
+    let assembly = metadata.tables.row(TABLE_ASSEMBLY, 1)?;
+    let name = metadata.string(assembly[7])?;
+    let version = AssemblyVersion {
+        major: assembly[1] as u16,
+        minor: assembly[2] as u16,
+        build: assembly[3] as u16,
+        revision: assembly[4] as u16,
+    };
+
+    let mut references = Vec::new();
+    for row in 1..=metadata.tables.row_count(TABLE_ASSEMBLY_REF) {
+        let assembly_ref = metadata.tables.row(TABLE_ASSEMBLY_REF, row)?;
+        references.push(AssemblyReference {
+            name: metadata.string(assembly_ref[6])?,
+            version: AssemblyVersion {
+                major: assembly_ref[0] as u16,
+                minor: assembly_ref[1] as u16,
+                build: assembly_ref[2] as u16,
+                revision: assembly_ref[3] as u16,
+            },
+        });
+    }
+
+    let target_framework = metadata
+        .get_assembly_attribute_string("System.Runtime.Versioning", "TargetFrameworkAttribute")?;
+
+    Ok(AssemblyMetadata {
+        name,
+        version,
+        target_framework,
+        references,
+        types: metadata.get_types()?,
+    })
+}
+
+const TABLE_TYPE_REF: usize = 0x01;
+const TABLE_TYPE_DEF: usize = 0x02;
+const TABLE_METHOD_DEF: usize = 0x06;
+const TABLE_INTERFACE_IMPL: usize = 0x09;
+const TABLE_MEMBER_REF: usize = 0x0A;
+const TABLE_CUSTOM_ATTRIBUTE: usize = 0x0C;
+const TABLE_ASSEMBLY: usize = 0x20;
+const TABLE_ASSEMBLY_REF: usize = 0x23;
+const TABLE_NESTED_CLASS: usize = 0x29;
+const TABLE_COUNT: usize = 0x2D;
+
+/// The kinds of column that appear in the metadata tables
+#[derive(Clone, Copy)]
+enum Column {
+    U16,
+    U32,
+    String,
+    Guid,
+    Blob,
+    /// An index into a single table
+    Table(usize),
+    /// An index into one of several tables, with the table given by the low bits
+    Coded(&'static [usize]),
+}
+
+// The tables each coded index can point at, in tag order. Unused tags are given TABLE_COUNT
+const TYPE_DEF_OR_REF: &[usize] = &[0x02, 0x01, 0x1B];
+const HAS_CONSTANT: &[usize] = &[0x04, 0x08, 0x17];
+const HAS_CUSTOM_ATTRIBUTE: &[usize] = &[
+    0x06, 0x04, 0x01, 0x02, 0x08, 0x09, 0x0A, 0x00, 0x0E, 0x17, 0x14, 0x11, 0x1A, 0x1B, 0x20, 0x23,
+    0x26, 0x27, 0x28, 0x2A, 0x2C, 0x2B,
+];
+const HAS_FIELD_MARSHAL: &[usize] = &[0x04, 0x08];
+const HAS_DECL_SECURITY: &[usize] = &[0x02, 0x06, 0x20];
+const MEMBER_REF_PARENT: &[usize] = &[0x02, 0x01, 0x1A, 0x06, 0x1B];
+const HAS_SEMANTICS: &[usize] = &[0x14, 0x17];
+const METHOD_DEF_OR_REF: &[usize] = &[0x06, 0x0A];
+const MEMBER_FORWARDED: &[usize] = &[0x04, 0x06];
+const IMPLEMENTATION: &[usize] = &[0x26, 0x23, 0x27];
+const CUSTOM_ATTRIBUTE_TYPE: &[usize] = &[TABLE_COUNT, TABLE_COUNT, 0x06, 0x0A, TABLE_COUNT];
+const RESOLUTION_SCOPE: &[usize] = &[0x00, 0x1A, 0x23, 0x01];
+const TYPE_OR_METHOD_DEF: &[usize] = &[0x02, 0x06];
+
+/// The columns of every metadata table, indexed by table number
+fn table_schema(table: usize) -> &'static [Column] {
+    use Column::*;
+    match table {
+        0x00 => &[U16, String, Guid, Guid, Guid],
+        0x01 => &[Coded(RESOLUTION_SCOPE), String, String],
+        0x02 => &[
+            U32,
+            String,
+            String,
+            Coded(TYPE_DEF_OR_REF),
+            Table(0x04),
+            Table(0x06),
+        ],
+        0x03 => &[Table(0x04)],
+        0x04 => &[U16, String, Blob],
+        0x05 => &[Table(0x06)],
+        0x06 => &[U32, U16, U16, String, Blob, Table(0x08)],
+        0x07 => &[Table(0x08)],
+        0x08 => &[U16, U16, String],
+        0x09 => &[Table(0x02), Coded(TYPE_DEF_OR_REF)],
+        0x0A => &[Coded(MEMBER_REF_PARENT), String, Blob],
+        0x0B => &[U16, Coded(HAS_CONSTANT), Blob],
+        0x0C => &[
+            Coded(HAS_CUSTOM_ATTRIBUTE),
+            Coded(CUSTOM_ATTRIBUTE_TYPE),
+            Blob,
+        ],
+        0x0D => &[Coded(HAS_FIELD_MARSHAL), Blob],
+        0x0E => &[U16, Coded(HAS_DECL_SECURITY), Blob],
+        0x0F => &[U16, U32, Table(0x02)],
+        0x10 => &[U32, Table(0x04)],
+        0x11 => &[Blob],
+        0x12 => &[Table(0x02), Table(0x14)],
+        0x13 => &[Table(0x14)],
+        0x14 => &[U16, String, Coded(TYPE_DEF_OR_REF)],
+        0x15 => &[Table(0x02), Table(0x17)],
+        0x16 => &[Table(0x17)],
+        0x17 => &[U16, String, Blob],
+        0x18 => &[U16, Table(0x06), Coded(HAS_SEMANTICS)],
+        0x19 => &[
+            Table(0x02),
+            Coded(METHOD_DEF_OR_REF),
+            Coded(METHOD_DEF_OR_REF),
+        ],
+        0x1A => &[String],
+        0x1B => &[Blob],
+        0x1C => &[U16, Coded(MEMBER_FORWARDED), String, Table(0x1A)],
+        0x1D => &[U32, Table(0x04)],
+        0x1E => &[U32, U32],
+        0x1F => &[U32],
+        0x20 => &[U32, U16, U16, U16, U16, U32, Blob, String, String],
+        0x21 => &[U32],
+        0x22 => &[U32, U32, U32],
+        0x23 => &[U16, U16, U16, U16, U32, Blob, String, String, Blob],
+        0x24 => &[U32, Table(0x23)],
+        0x25 => &[U32, U32, U32, Table(0x23)],
+        0x26 => &[U32, String, Blob],
+        0x27 => &[U32, U32, String, String, Coded(IMPLEMENTATION)],
+        0x28 => &[U32, U32, String, Coded(IMPLEMENTATION)],
+        0x29 => &[Table(0x02), Table(0x02)],
+        0x2A => &[U16, U16, Coded(TYPE_OR_METHOD_DEF), String],
+        0x2B => &[Coded(METHOD_DEF_OR_REF), Blob],
+        0x2C => &[Table(0x2A), Coded(TYPE_DEF_OR_REF)],
+        _ => &[],
+    }
+}
+
+/// A little-endian cursor over a byte slice that fails instead of panicking on truncated input
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], position: usize) -> Self {
+        Self { bytes, position }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or("Unexpected end of file while reading metadata")?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.take(8)?;
+        let mut array = [0u8; 8];
+        array.copy_from_slice(b);
+        Ok(u64::from_le_bytes(array))
+    }
+
+    /// Reads an ECMA-335 compressed unsigned integer (II.23.2)
+    fn compressed_u32(&mut self) -> Result<u32, String> {
+        let first = self.u8()? as u32;
+        if first & 0x80 == 0 {
+            Ok(first)
+        } else if first & 0xC0 == 0x80 {
+            Ok(((first & 0x3F) << 8) | self.u8()? as u32)
+        } else if first & 0xE0 == 0xC0 {
+            let rest = self.take(3)?;
+            Ok(((first & 0x1F) << 24)
+                | ((rest[0] as u32) << 16)
+                | ((rest[1] as u32) << 8)
+                | rest[2] as u32)
+        } else {
+            Err("Invalid compressed integer in metadata".to_string())
+        }
+    }
+}
+
+/// The metadata tables from the '#~' stream
+struct Tables<'a> {
+    data: &'a [u8],
+    row_counts: [u32; TABLE_COUNT],
+    /// The offset of each table within `data`
+    table_offsets: [usize; TABLE_COUNT],
+    /// The size in bytes of each column of each table
+    column_sizes: Vec<Vec<usize>>,
+}
+
+impl<'a> Tables<'a> {
+    fn parse(stream: &'a [u8]) -> Result<Self, String> {
+        let mut reader = Reader::new(stream, 0);
+        reader.take(6)?; // Reserved, MajorVersion and MinorVersion
+        let heap_sizes = reader.u8()?;
+        reader.u8()?; // Reserved
+        let valid = reader.u64()?;
+        reader.u64()?; // Sorted
+
+        let mut row_counts = [0u32; TABLE_COUNT];
+        for (table, row_count) in row_counts.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                *row_count = reader.u32()?;
+            }
+        }
+        // Tables we do not know about would shift the layout of everything after them
+        for table in TABLE_COUNT..64 {
+            if valid & (1 << table) != 0 {
+                return Err(format!("Unsupported metadata table 0x{:02X}", table));
+            }
+        }
+
+        let heap_index_size = |flag: u8| if heap_sizes & flag != 0 { 4 } else { 2 };
+        let table_index_size = |table: usize| {
+            if row_counts.get(table).copied().unwrap_or(0) < 1 << 16 {
+                2
+            } else {
+                4
+            }
+        };
+        let coded_index_size = |tables: &[usize]| {
+            let tag_bits = usize::BITS - (tables.len() - 1).leading_zeros();
+            let max_rows = tables
+                .iter()
+                .map(|table| row_counts.get(*table).copied().unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+            if max_rows < 1 << (16 - tag_bits) {
+                2
+            } else {
+                4
+            }
+        };
+
+        let column_sizes: Vec<Vec<usize>> = (0..TABLE_COUNT)
+            .map(|table| {
+                table_schema(table)
+                    .iter()
+                    .map(|column| match column {
+                        Column::U16 => 2,
+                        Column::U32 => 4,
+                        Column::String => heap_index_size(0x01),
+                        Column::Guid => heap_index_size(0x02),
+                        Column::Blob => heap_index_size(0x04),
+                        Column::Table(table) => table_index_size(*table),
+                        Column::Coded(tables) => coded_index_size(tables),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut table_offsets = [0usize; TABLE_COUNT];
+        let mut offset = reader.position;
+        for table in 0..TABLE_COUNT {
+            table_offsets[table] = offset;
+            let row_size: usize = column_sizes[table].iter().sum();
+            offset += row_size * row_counts[table] as usize;
+        }
+        if offset > stream.len() {
+            return Err("The metadata tables are truncated".to_string());
+        }
+
+        Ok(Self {
+            data: stream,
+            row_counts,
+            table_offsets,
+            column_sizes,
+        })
+    }
+
+    fn row_count(&self, table: usize) -> u32 {
+        self.row_counts[table]
+    }
+
+    /// Returns the raw column values of a row. Rows are numbered from 1
+    fn row(&self, table: usize, row: u32) -> Result<Vec<u32>, String> {
+        if row == 0 || row > self.row_counts[table] {
+            return Err(format!(
+                "Row {} of table 0x{:02X} does not exist",
+                row, table
+            ));
+        }
+        let row_size: usize = self.column_sizes[table].iter().sum();
+        let mut reader = Reader::new(
+            self.data,
+            self.table_offsets[table] + row_size * (row as usize - 1),
+        );
+        self.column_sizes[table]
+            .iter()
+            .map(|size| {
+                if *size == 2 {
+                    reader.u16().map(u32::from)
+                } else {
+                    reader.u32()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Splits a coded index into the table it points at and the row number
+fn decode_coded_index(value: u32, tables: &[usize]) -> (usize, u32) {
+    let tag_bits = usize::BITS - (tables.len() - 1).leading_zeros();
+    let tag = (value & ((1 << tag_bits) - 1)) as usize;
+    (
+        tables.get(tag).copied().unwrap_or(TABLE_COUNT),
+        value >> tag_bits,
+    )
+}
+
+/// The metadata streams of an assembly
+struct Metadata<'a> {
+    tables: Tables<'a>,
+    strings: &'a [u8],
+    blobs: &'a [u8],
+}
+
+impl<'a> Metadata<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self, String> {
+        let not_dotnet = || "The file is not a .NET assembly".to_string();
+
+        // PE headers (Microsoft PE/COFF specification)
+        let pe_offset = Reader::new(bytes, 0x3C).u32().map_err(|_| not_dotnet())? as usize;
+        let mut reader = Reader::new(bytes, pe_offset);
+        if reader.take(4).map_err(|_| not_dotnet())? != b"PE\0\0" {
+            return Err(not_dotnet());
+        }
+        reader.take(2)?; // Machine
+        let section_count = reader.u16()? as usize;
+        reader.take(12)?; // TimeDateStamp, PointerToSymbolTable and NumberOfSymbols
+        let optional_header_size = reader.u16()? as usize;
+        reader.take(2)?; // Characteristics
+
+        let optional_header = reader.position;
+        let data_directories = match reader.u16()? {
+            0x10B => optional_header + 96,
+            0x20B => optional_header + 112,
+            _ => return Err(not_dotnet()),
+        };
+        // The CLI header is the 15th data directory
+        let mut reader = Reader::new(bytes, data_directories + 14 * 8);
+        let cli_header_rva = reader.u32().map_err(|_| not_dotnet())?;
+        if cli_header_rva == 0 {
+            return Err(not_dotnet());
+        }
+
+        let sections = optional_header + optional_header_size;
+        let rva_to_offset = |rva: u32| -> Result<usize, String> {
+            for section in 0..section_count {
+                let mut reader = Reader::new(bytes, sections + section * 40 + 8);
+                let virtual_size = reader.u32()?;
+                let virtual_address = reader.u32()?;
+                let raw_size = reader.u32()?;
+                let raw_pointer = reader.u32()?;
+                let size = virtual_size.max(raw_size);
+                if rva >= virtual_address && rva < virtual_address.saturating_add(size) {
+                    return (rva - virtual_address)
+                        .checked_add(raw_pointer)
+                        .map(|offset| offset as usize)
+                        .ok_or_else(|| format!("RVA 0x{:X} points outside of the file", rva));
+                }
+            }
+            Err(format!("RVA 0x{:X} is not inside any section", rva))
+        };
+
+        // CLI header (II.25.3.3)
+        let mut reader = Reader::new(bytes, rva_to_offset(cli_header_rva)? + 8);
+        let metadata_offset = rva_to_offset(reader.u32()?)?;
+
+        // Metadata root (II.24.2.1)
+        let mut reader = Reader::new(bytes, metadata_offset);
+        if reader.u32()? != 0x424A_5342 {
+            return Err("Invalid metadata signature".to_string());
+        }
+        reader.take(8)?; // MajorVersion, MinorVersion and Reserved
+        let version_length = reader.u32()? as usize;
+        reader.take(version_length)?;
+        reader.take(2)?; // Flags
+        let stream_count = reader.u16()?;
+
+        let mut tables = None;
+        let mut strings: &[u8] = &[];
+        let mut blobs: &[u8] = &[];
+        for _ in 0..stream_count {
+            let offset = reader.u32()? as usize;
+            let size = reader.u32()? as usize;
+            let mut name = Vec::new();
+            loop {
+                let byte = reader.u8()?;
+                if byte == 0 {
+                    break;
+                }
+                name.push(byte);
+            }
+            // Stream names are padded to a multiple of 4 bytes, including the terminator
+            let padding = (4 - (name.len() + 1) % 4) % 4;
+            reader.take(padding)?;
+
+            let stream = Reader::new(bytes, metadata_offset + offset).take(size)?;
+            match name.as_slice() {
+                b"#~" | b"#-" => tables = Some(stream),
+                b"#Strings" => strings = stream,
+                b"#Blob" => blobs = stream,
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            tables: Tables::parse(tables.ok_or("The metadata has no tables stream")?)?,
+            strings,
+            blobs,
+        })
+    }
+
+    fn string(&self, index: u32) -> Result<String, String> {
+        let start = index as usize;
+        let rest = self
+            .strings
+            .get(start..)
+            .ok_or("String index is outside of the strings heap")?;
+        let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    fn blob(&self, index: u32) -> Result<&'a [u8], String> {
+        let mut reader = Reader::new(self.blobs, index as usize);
+        let length = reader.compressed_u32()? as usize;
+        reader.take(length)
+    }
+
+    /// Returns the namespace and name of the type that defines a custom attribute's constructor
+    fn get_attribute_type_name(&self, attribute_type: u32) -> Result<(String, String), String> {
+        let (table, row) = decode_coded_index(attribute_type, CUSTOM_ATTRIBUTE_TYPE);
+        let (parent_table, parent_row) = match table {
+            TABLE_MEMBER_REF => {
+                let member_ref = self.tables.row(TABLE_MEMBER_REF, row)?;
+                decode_coded_index(member_ref[0], MEMBER_REF_PARENT)
+            }
+            TABLE_METHOD_DEF => {
+                // Find the type that owns the method from the method lists of the type definitions
+                let mut owner = 0;
+                for type_row in 1..=self.tables.row_count(TABLE_TYPE_DEF) {
+                    if self.tables.row(TABLE_TYPE_DEF, type_row)?[5] <= row {
+                        owner = type_row;
+                    }
+                }
+                (TABLE_TYPE_DEF, owner)
+            }
+            _ => return Err("Invalid custom attribute type".to_string()),
+        };
+        match parent_table {
+            TABLE_TYPE_REF => {
+                let type_ref = self.tables.row(TABLE_TYPE_REF, parent_row)?;
+                Ok((self.string(type_ref[2])?, self.string(type_ref[1])?))
+            }
+            TABLE_TYPE_DEF => {
+                let type_def = self.tables.row(TABLE_TYPE_DEF, parent_row)?;
+                Ok((self.string(type_def[2])?, self.string(type_def[1])?))
+            }
+            _ => Ok((String::new(), String::new())),
+        }
+    }
+
+    /// Returns the first string argument of a custom attribute applied to the assembly itself
+    fn get_assembly_attribute_string(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Option<String>, String> {
+        for row in 1..=self.tables.row_count(TABLE_CUSTOM_ATTRIBUTE) {
+            let attribute = self.tables.row(TABLE_CUSTOM_ATTRIBUTE, row)?;
+            let (parent_table, _) = decode_coded_index(attribute[0], HAS_CUSTOM_ATTRIBUTE);
+            if parent_table != TABLE_ASSEMBLY {
+                continue;
+            }
+            let (attribute_namespace, attribute_name) =
+                self.get_attribute_type_name(attribute[1])?;
+            if attribute_namespace != namespace || attribute_name != name {
+                continue;
+            }
+
+            // The value starts with the prolog 0x0001, followed by the fixed arguments (II.23.3)
+            let mut reader = Reader::new(self.blob(attribute[2])?, 0);
+            if reader.u16()? != 0x0001 {
+                return Err("Invalid custom attribute value".to_string());
+            }
+            if reader.bytes.get(reader.position) == Some(&0xFF) {
+                return Ok(None);
+            }
+            let length = reader.compressed_u32()? as usize;
+            let value = reader.take(length)?;
+            return Ok(Some(String::from_utf8_lossy(value).into_owned()));
+        }
+        Ok(None)
+    }
+
+    /// Returns every type defined in the assembly, apart from the `<Module>` pseudo type
+    fn get_types(&self) -> Result<Vec<AssemblyType>, String> {
+        let type_count = self.tables.row_count(TABLE_TYPE_DEF);
+        let type_defs = (1..=type_count)
+            .map(|row| self.tables.row(TABLE_TYPE_DEF, row))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut enclosing_types = HashMap::new();
+        for row in 1..=self.tables.row_count(TABLE_NESTED_CLASS) {
+            let nested_class = self.tables.row(TABLE_NESTED_CLASS, row)?;
+            enclosing_types.insert(nested_class[0], nested_class[1]);
+        }
+        let full_names = (1..=type_count)
+            .map(|row| self.get_type_def_full_name(row, &enclosing_types))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut direct_interfaces = vec![Vec::new(); type_count as usize];
+        for row in 1..=self.tables.row_count(TABLE_INTERFACE_IMPL) {
+            let interface_impl = self.tables.row(TABLE_INTERFACE_IMPL, row)?;
+            let (table, interface_row) = decode_coded_index(interface_impl[1], TYPE_DEF_OR_REF);
+            let interface = match table {
+                TABLE_TYPE_DEF => full_names
+                    .get((interface_row as usize).wrapping_sub(1))
+                    .cloned(),
+                TABLE_TYPE_REF => Some(self.get_type_ref_full_name(interface_row)?),
+                // Generic instantiations such as IEnumerable<T> are stored as type specs
+                _ => None,
+            };
+            if let (Some(interfaces), Some(interface)) = (
+                direct_interfaces.get_mut((interface_impl[0] as usize).wrapping_sub(1)),
+                interface,
+            ) {
+                interfaces.push(interface);
+            }
+        }
+
+        let mut types = Vec::new();
+        for row in 2..=type_count {
+            let type_def = &type_defs[row as usize - 1];
+            let flags = type_def[0];
+            // Interfaces are also inherited from base types, as long as they are defined in this assembly
+            let mut interfaces = Vec::new();
+            let mut external_base_type = None;
+            let mut current = row;
+            for _ in 0..type_count {
+                for interface in &direct_interfaces[current as usize - 1] {
+                    if !interfaces.contains(interface) {
+                        interfaces.push(interface.clone());
+                    }
+                }
+                let (table, base) =
+                    decode_coded_index(type_defs[current as usize - 1][3], TYPE_DEF_OR_REF);
+                if table == TABLE_TYPE_REF && base != 0 {
+                    let base_name = self.get_type_ref_full_name(base)?;
+                    // Base types from .NET itself, such as System.Object, implement no add-in interfaces
+                    if !base_name.starts_with("System.") {
+                        external_base_type = Some(base_name);
+                    }
+                }
+                if table != TABLE_TYPE_DEF || base == 0 || base > type_count {
+                    break;
+                }
+                current = base;
+            }
+            types.push(AssemblyType {
+                full_name: full_names[row as usize - 1].clone(),
+                // Public (1) or NestedPublic (2) visibility
+                is_public: matches!(flags & 0x07, 1 | 2),
+                is_abstract: flags & 0x80 != 0,
+                interfaces,
+                external_base_type,
+            });
+        }
+        Ok(types)
+    }
+
+    fn get_type_def_full_name(
+        &self,
+        row: u32,
+        enclosing_types: &HashMap<u32, u32>,
+    ) -> Result<String, String> {
+        let mut full_name = String::new();
+        let mut current = row;
+        // Walk out through the enclosing types, which are limited to avoid looping on bad metadata
+        for _ in 0..=enclosing_types.len() {
+            let type_def = self.tables.row(TABLE_TYPE_DEF, current)?;
+            let name = self.string(type_def[1])?;
+            full_name = if full_name.is_empty() {
+                name
+            } else {
+                format!("{}+{}", name, full_name)
+            };
+            match enclosing_types.get(&current) {
+                Some(enclosing) => current = *enclosing,
+                None => {
+                    let namespace = self.string(type_def[2])?;
+                    if !namespace.is_empty() {
+                        full_name = format!("{}.{}", namespace, full_name);
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(full_name)
+    }
+
+    fn get_type_ref_full_name(&self, row: u32) -> Result<String, String> {
+        let mut full_name = String::new();
+        let mut current = row;
+        for _ in 0..self.tables.row_count(TABLE_TYPE_REF) {
+            let type_ref = self.tables.row(TABLE_TYPE_REF, current)?;
+            let name = self.string(type_ref[1])?;
+            full_name = if full_name.is_empty() {
+                name
+            } else {
+                format!("{}+{}", name, full_name)
+            };
+            // References to nested types are scoped to the type reference that contains them
+            let (scope_table, scope_row) = decode_coded_index(type_ref[0], RESOLUTION_SCOPE);
+            if scope_table != TABLE_TYPE_REF {
+                let namespace = self.string(type_ref[2])?;
+                if !namespace.is_empty() {
+                    full_name = format!("{}.{}", namespace, full_name);
+                }
+                break;
+            }
+            current = scope_row;
+        }
+        Ok(full_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION_RVA: u32 = 0x200;
+    const CLI_HEADER_DIRECTORY: usize = 0x98 + 96 + 14 * 8;
+    const SECTION_RAW_POINTER: usize = 0x178 + 20;
+
+    /// Builds a PE32 file with one section and the smallest metadata that has an assembly
+    /// manifest. The section is mapped at the same offset in the file as its RVA
+    fn minimal_assembly(name: &str, version: [u16; 4]) -> Vec<u8> {
+        TestAssembly::new(name, version).build()
+    }
+
+    /// The metadata tables and strings of a test assembly. Every index is 2 bytes, which holds
+    /// as long as the tables and heaps stay small
+    struct TestAssembly {
+        strings: Vec<u8>,
+        tables: std::collections::BTreeMap<usize, Vec<Vec<u8>>>,
+    }
+
+    impl TestAssembly {
+        fn new(name: &str, version: [u16; 4]) -> Self {
+            let mut assembly = Self {
+                strings: vec![0],
+                tables: Default::default(),
+            };
+            let mut row = 0x8004u32.to_le_bytes().to_vec(); // HashAlgId
+            for part in version {
+                row.extend(part.to_le_bytes());
+            }
+            row.extend(0u32.to_le_bytes()); // Flags
+            row.extend(0u16.to_le_bytes()); // PublicKey
+            row.extend(assembly.string(name));
+            row.extend(0u16.to_le_bytes()); // Culture
+            assembly.add_row(TABLE_ASSEMBLY, row);
+            assembly
+        }
+
+        /// Adds the string to the '#Strings' heap and returns its index
+        fn string(&mut self, value: &str) -> [u8; 2] {
+            let index = self.strings.len() as u16;
+            self.strings.extend(value.as_bytes());
+            self.strings.push(0);
+            index.to_le_bytes()
+        }
+
+        /// Adds a row to the table and returns its 1-based row number
+        fn add_row(&mut self, table: usize, row: Vec<u8>) -> u16 {
+            let rows = self.tables.entry(table).or_default();
+            rows.push(row);
+            rows.len() as u16
+        }
+
+        /// Adds a type reference to an assembly that is not in the tables, and returns its
+        /// TypeDefOrRef coded index
+        fn add_type_ref(&mut self, namespace: &str, name: &str) -> u16 {
+            let mut row = ((1u16 << 2) | 2).to_le_bytes().to_vec(); // AssemblyRef 1
+            row.extend(self.string(name));
+            row.extend(self.string(namespace));
+            (self.add_row(TABLE_TYPE_REF, row) << 2) | 1
+        }
+
+        /// Adds a public class, and the `<Module>` type before the first one. Returns its
+        /// TypeDefOrRef coded index, which is its row number shifted by the tag
+        fn add_class(&mut self, namespace: &str, name: &str, extends: u16) -> u16 {
+            if !self.tables.contains_key(&TABLE_TYPE_DEF) {
+                let mut module = 0u32.to_le_bytes().to_vec();
+                module.extend(self.string("<Module>"));
+                module.extend([0; 8]);
+                self.add_row(TABLE_TYPE_DEF, module);
+            }
+            let mut row = 0x0010_0001u32.to_le_bytes().to_vec(); // Public, BeforeFieldInit
+            row.extend(self.string(name));
+            row.extend(self.string(namespace));
+            row.extend(extends.to_le_bytes());
+            row.extend(1u16.to_le_bytes()); // FieldList
+            row.extend(1u16.to_le_bytes()); // MethodList
+            self.add_row(TABLE_TYPE_DEF, row) << 2
+        }
+
+        fn add_interface_impl(&mut self, class: u16, interface: u16) {
+            let mut row = (class >> 2).to_le_bytes().to_vec();
+            row.extend(interface.to_le_bytes());
+            self.add_row(TABLE_INTERFACE_IMPL, row);
+        }
+
+        fn build(self) -> Vec<u8> {
+            let mut bytes = vec![0u8; SECTION_RVA as usize];
+            let put = |bytes: &mut Vec<u8>, offset: usize, value: &[u8]| {
+                bytes[offset..offset + value.len()].copy_from_slice(value);
+            };
+            put(&mut bytes, 0x3C, &0x80u32.to_le_bytes());
+            put(&mut bytes, 0x80, b"PE\0\0");
+            put(&mut bytes, 0x86, &1u16.to_le_bytes()); // NumberOfSections
+            put(&mut bytes, 0x94, &224u16.to_le_bytes()); // SizeOfOptionalHeader
+            put(&mut bytes, 0x98, &0x10Bu16.to_le_bytes()); // PE32
+            put(&mut bytes, CLI_HEADER_DIRECTORY, &SECTION_RVA.to_le_bytes());
+            put(&mut bytes, CLI_HEADER_DIRECTORY + 4, &72u32.to_le_bytes());
+            // The section table starts right after the optional header
+            put(&mut bytes, 0x178 + 8, &0x1000u32.to_le_bytes()); // VirtualSize
+            put(&mut bytes, 0x178 + 12, &SECTION_RVA.to_le_bytes());
+            put(&mut bytes, 0x178 + 16, &0x1000u32.to_le_bytes()); // SizeOfRawData
+            put(&mut bytes, SECTION_RAW_POINTER, &SECTION_RVA.to_le_bytes());
+
+            // CLI header, pointing at the metadata root right after it
+            let metadata_rva = SECTION_RVA + 72;
+            let mut cli_header = vec![0u8; 72];
+            cli_header[8..12].copy_from_slice(&metadata_rva.to_le_bytes());
+            bytes.extend(cli_header);
+
+            let mut tables = vec![0, 0, 0, 0, 2, 0, 0, 1];
+            let valid = self
+                .tables
+                .keys()
+                .fold(0u64, |valid, table| valid | 1 << table);
+            tables.extend(valid.to_le_bytes());
+            tables.extend(0u64.to_le_bytes()); // Sorted
+            for rows in self.tables.values() {
+                tables.extend((rows.len() as u32).to_le_bytes());
+            }
+            for rows in self.tables.values() {
+                tables.extend(rows.concat());
+            }
+            let mut strings = self.strings;
+            strings.resize(strings.len().next_multiple_of(4), 0);
+            let streams: [(&[u8], Vec<u8>); 3] = [
+                (b"#~", tables),
+                (b"#Strings", strings),
+                (b"#Blob", vec![0; 4]),
+            ];
+
+            let mut root = 0x424A_5342u32.to_le_bytes().to_vec();
+            root.extend([0; 8]);
+            root.extend(12u32.to_le_bytes());
+            root.extend(b"v4.0.30319\0\0");
+            root.extend(0u16.to_le_bytes());
+            root.extend((streams.len() as u16).to_le_bytes());
+            let headers_size: usize = streams
+                .iter()
+                .map(|(name, _)| 8 + (name.len() + 1).next_multiple_of(4))
+                .sum();
+            let mut offset = root.len() + headers_size;
+            let mut data: Vec<u8> = Vec::new();
+            for (name, stream) in &streams {
+                root.extend((offset as u32).to_le_bytes());
+                root.extend((stream.len() as u32).to_le_bytes());
+                let mut padded_name = name.to_vec();
+                padded_name.resize((name.len() + 1).next_multiple_of(4), 0);
+                root.extend(padded_name);
+                offset += stream.len();
+                data.extend(stream);
+            }
+            bytes.extend(root);
+            bytes.extend(data);
+            bytes
+        }
+    }
+
+    #[test]
+    fn reads_minimal_assembly() {
+        let metadata = parse_assembly_metadata(&minimal_assembly("MyAddin", [1, 2, 3, 4])).unwrap();
+        assert_eq!(metadata.name, "MyAddin");
+        assert_eq!(metadata.version.to_string(), "1.2.3.4");
+        assert!(metadata.references.is_empty());
+        assert!(metadata.types.is_empty());
+        assert_eq!(metadata.target_framework, None);
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let bytes = minimal_assembly("MyAddin", [1, 0, 0, 0]);
+        for length in [0, 0x40, 0x84, 0x100] {
+            assert!(parse_assembly_metadata(&bytes[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_file_without_pe_signature() {
+        let mut bytes = minimal_assembly("MyAddin", [1, 0, 0, 0]);
+        bytes[0x80] = b'X';
+        assert_eq!(
+            parse_assembly_metadata(&bytes).unwrap_err(),
+            "The file is not a .NET assembly"
+        );
+    }
+
+    #[test]
+    fn rejects_rva_outside_of_sections() {
+        let mut bytes = minimal_assembly("MyAddin", [1, 0, 0, 0]);
+        bytes[CLI_HEADER_DIRECTORY..CLI_HEADER_DIRECTORY + 4]
+            .copy_from_slice(&0x9000u32.to_le_bytes());
+        assert_eq!(
+            parse_assembly_metadata(&bytes).unwrap_err(),
+            "RVA 0x9000 is not inside any section"
+        );
+    }
+
+    #[test]
+    fn rejects_rva_that_overflows_the_file_offset() {
+        let mut bytes = minimal_assembly("MyAddin", [1, 0, 0, 0]);
+        bytes[CLI_HEADER_DIRECTORY..CLI_HEADER_DIRECTORY + 4]
+            .copy_from_slice(&(SECTION_RVA + 0x10).to_le_bytes());
+        bytes[SECTION_RAW_POINTER..SECTION_RAW_POINTER + 4]
+            .copy_from_slice(&(u32::MAX - 4).to_le_bytes());
+        assert_eq!(
+            parse_assembly_metadata(&bytes).unwrap_err(),
+            "RVA 0x210 points outside of the file"
+        );
+    }
+
+    #[test]
+    fn reads_interfaces_of_base_types() {
+        let mut assembly = TestAssembly::new("MyAddin", [1, 0, 0, 0]);
+        let command = assembly.add_type_ref("Autodesk.Revit.UI", "IExternalCommand");
+        let object = assembly.add_type_ref("System", "Object");
+        let shared_base = assembly.add_type_ref("CompanyLib", "CommandBase");
+        let local_base = assembly.add_class("MyAddin", "LocalBase", object);
+        assembly.add_interface_impl(local_base, command);
+        assembly.add_class("MyAddin", "Local", local_base);
+        assembly.add_class("MyAddin", "Shared", shared_base);
+        let metadata = parse_assembly_metadata(&assembly.build()).unwrap();
+
+        let local = metadata.get_type("MyAddin.Local").unwrap();
+        assert!(local.is_public);
+        assert!(local.implements("Autodesk.Revit.UI.IExternalCommand"));
+        assert_eq!(local.external_base_type, None);
+        let shared = metadata.get_type("MyAddin.Shared").unwrap();
+        assert!(shared.interfaces.is_empty());
+        assert_eq!(
+            shared.external_base_type.as_deref(),
+            Some("CompanyLib.CommandBase")
+        );
+    }
+}
@@ -0,0 +1,1096 @@
+// Reads the shape of the classes, records, structs, interfaces and enums declared in C# source
+// files. This is not a full C# parser: it only understands declarations and skips over method
+// bodies, initializers and everything else it does not need, which is enough for data transfer
+// objects that are serialized to JSON
+
+/// A type used by a property, e.g. `List<string>?`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeReference {
+    /// A possibly generic type, with any namespace or containing type removed
+    Named {
+        name: String,
+        arguments: Vec<TypeReference>,
+    },
+    Array(Box<TypeReference>),
+    Nullable(Box<TypeReference>),
+    /// A tuple such as `(int X, int Y)`, which is serialized as an object with Item1, Item2, ...
+    Tuple(Vec<TypeReference>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSharpProperty {
+    pub name: String,
+    pub type_reference: TypeReference,
+    /// The name set with `[JsonPropertyName("...")]`, which replaces the naming policy
+    pub json_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSharpClass {
+    pub name: String,
+    pub type_parameters: Vec<String>,
+    /// The base class and interfaces, in the order they are declared
+    pub base_types: Vec<TypeReference>,
+    pub properties: Vec<CSharpProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSharpEnumMember {
+    pub name: String,
+    /// The value expression after '=', if there is one
+    pub value: Option<String>,
+    /// The name set with `[JsonStringEnumMemberName("...")]`
+    pub json_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSharpEnum {
+    pub name: String,
+    pub members: Vec<CSharpEnumMember>,
+    /// True if the enum has `[JsonConverter(typeof(JsonStringEnumConverter))]`, so it is
+    /// serialized by name instead of by value
+    pub serialized_as_string: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CSharpDeclaration {
+    Class(CSharpClass),
+    Enum(CSharpEnum),
+}
+
+impl CSharpDeclaration {
+    pub fn name(&self) -> &str {
+        match self {
+            CSharpDeclaration::Class(class) => &class.name,
+            CSharpDeclaration::Enum(csharp_enum) => &csharp_enum.name,
+        }
+    }
+}
+
+/// Returns the classes, records, structs, interfaces and enums declared in C# source code,
+/// including nested ones, in the order they are declared. Static classes are left out since
+/// they cannot be serialized
+///
+/// Only public, non-static properties are read, along with the parameters of positional records.
+/// Properties marked with `[JsonIgnore]` are skipped
+pub fn parse_declarations(source: &str) -> Result<Vec<CSharpDeclaration>, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        declarations: Vec::new(),
+    };
+    parser.parse_members(None)?;
+    Ok(parser.declarations)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Literal(String),
+    String(String),
+    Punctuation(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut at_line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            at_line_start = true;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' && at_line_start {
+            // Preprocessor directives such as #nullable and #region take up the rest of the line
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if let Some(quote) = find_string_start(&chars, i) {
+            let (value, end) = read_string(&chars, i, quote)
+                .ok_or_else(|| format!("Unterminated string at character {}", i))?;
+            tokens.push(Token::String(value));
+            i = end;
+        } else if c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Literal("'?'".to_string()));
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let identifier: String = chars[start..i].iter().collect();
+            tokens.push(Token::Identifier(
+                identifier.trim_start_matches('@').to_string(),
+            ));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Literal(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Punctuation(c));
+            i += 1;
+        }
+        if !c.is_whitespace() {
+            at_line_start = false;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the index of the opening quote if a string literal starts at `i`, after any '@' and
+/// '$' prefixes
+fn find_string_start(chars: &[char], i: usize) -> Option<usize> {
+    let quote = i + chars[i..]
+        .iter()
+        .take_while(|c| matches!(c, '@' | '$'))
+        .count();
+    (chars.get(quote) == Some(&'"')).then_some(quote)
+}
+
+/// Reads a string literal starting at its prefixes, with the opening quote at `quote`. Returns
+/// its value and the index after the closing quote. Interpolations are kept as they are written
+fn read_string(chars: &[char], start: usize, quote: usize) -> Option<(String, usize)> {
+    let verbatim = chars[start..quote].contains(&'@');
+    let dollars = chars[start..quote].iter().filter(|c| **c == '$').count();
+    let quotes = count_run(chars, quote, '"');
+    if quotes >= 3 && !verbatim {
+        return read_raw_string(chars, quote, quotes, dollars);
+    }
+    let mut value = String::new();
+    let mut i = quote + 1;
+    loop {
+        match *chars.get(i)? {
+            '"' if verbatim && chars.get(i + 1) == Some(&'"') => {
+                value.push('"');
+                i += 2;
+            }
+            '"' => return Some((value, i + 1)),
+            '\\' if !verbatim => {
+                value.push(*chars.get(i + 1)?);
+                i += 2;
+            }
+            c @ ('{' | '}') if dollars > 0 && chars.get(i + 1) == Some(&c) => {
+                value.push(c);
+                i += 2;
+            }
+            '{' if dollars > 0 => {
+                let end = skip_interpolation(chars, i + 1, 1)?;
+                value.extend(&chars[i..end]);
+                i = end;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Reads a raw string literal such as `"""..."""`, which ends at as many quotes as it started
+/// with. Interpolations start with as many braces as the string has '$' prefixes. Like C#, the
+/// lines of a multi-line raw string lose the indentation of its closing quotes
+fn read_raw_string(
+    chars: &[char],
+    quote: usize,
+    quotes: usize,
+    dollars: usize,
+) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut i = quote + quotes;
+    let end = loop {
+        chars.get(i)?;
+        let run = count_run(chars, i, chars[i]);
+        match chars[i] {
+            '"' if run >= quotes => break i + run,
+            '{' if dollars > 0 && run >= dollars => {
+                value.extend(&chars[i..i + run - dollars]);
+                let hole_end = skip_interpolation(chars, i + run, dollars)?;
+                value.extend(&chars[i + run - dollars..hole_end]);
+                i = hole_end;
+            }
+            _ => {
+                value.extend(&chars[i..i + run]);
+                i += run;
+            }
+        }
+    };
+    let Some(content) = value.strip_prefix('\n').or(value.strip_prefix("\r\n")) else {
+        return Some((value, end));
+    };
+    let (content, indentation) = content.rsplit_once('\n').unwrap_or(("", content));
+    let lines: Vec<&str> = content
+        .lines()
+        .map(|line| line.strip_prefix(indentation).unwrap_or(line.trim_start()))
+        .collect();
+    Some((lines.join("\n"), end))
+}
+
+/// Skips an interpolation inside of a string, starting after its opening braces. Returns the
+/// index after the `closing_braces` braces that end it. Strings, characters and brackets inside
+/// of the interpolation are skipped over, so their braces and quotes do not end it
+fn skip_interpolation(chars: &[char], start: usize, closing_braces: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    loop {
+        if let Some(quote) = find_string_start(chars, i) {
+            i = read_string(chars, i, quote)?.1;
+            continue;
+        }
+        match *chars.get(i)? {
+            '\'' => {
+                i += 1;
+                while *chars.get(i)? != '\'' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' if count_run(chars, i, '}') >= closing_braces => return Some(i + closing_braces),
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Returns how many times `c` repeats starting at `start`
+fn count_run(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|next| **next == c).count()
+}
+
+const MODIFIERS: [&str; 20] = [
+    "public",
+    "private",
+    "protected",
+    "internal",
+    "static",
+    "sealed",
+    "abstract",
+    "partial",
+    "readonly",
+    "required",
+    "virtual",
+    "override",
+    "new",
+    "unsafe",
+    "const",
+    "async",
+    "extern",
+    "volatile",
+    "file",
+    "ref",
+];
+
+/// An attribute on a declaration, with the first string argument it was given
+struct Attribute {
+    name: String,
+    argument: Option<String>,
+    /// Every identifier inside of the attribute, e.g. the converter in `JsonConverter(typeof(...))`
+    identifiers: Vec<String>,
+}
+
+fn find_attribute<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attributes.iter().find(|attribute| {
+        attribute.name == name || attribute.name.strip_suffix("Attribute") == Some(name)
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    declarations: Vec<CSharpDeclaration>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset)
+    }
+
+    fn is_punctuation(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punctuation(c))
+    }
+
+    fn is_identifier(&self, identifier: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(i)) if i == identifier)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_punctuation(&mut self, c: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Punctuation(p)) if p == c => Ok(()),
+            other => Err(format!(
+                "Expected '{}' but found {}",
+                c,
+                describe(other.as_ref())
+            )),
+        }
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Identifier(identifier)) => Ok(identifier),
+            other => Err(format!(
+                "Expected a name but found {}",
+                describe(other.as_ref())
+            )),
+        }
+    }
+
+    /// Skips a bracketed group starting at the opening bracket, including any nested groups
+    fn skip_group(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Punctuation('(' | '[' | '{') => depth += 1,
+                Token::Punctuation(')' | ']' | '}') => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips to after the next ';' outside of any brackets, or to the end of a '{ }' block
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punctuation(';') => {
+                    self.position += 1;
+                    return;
+                }
+                Token::Punctuation('{') => {
+                    self.skip_group();
+                    if self.is_punctuation(';') {
+                        self.position += 1;
+                    }
+                    return;
+                }
+                Token::Punctuation('(' | '[') => self.skip_group(),
+                Token::Punctuation('}') => return,
+                _ => self.position += 1,
+            }
+        }
+    }
+
+    fn parse_attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        while self.is_punctuation('[') {
+            let start = self.position + 1;
+            self.skip_group();
+            let tokens = &self.tokens[start..self.position.saturating_sub(1)];
+            // A single [...] can hold several attributes separated by commas, and each can have a
+            // target such as 'property:' on the parameters of positional records
+            let mut depth = 0;
+            let mut current: Vec<&Token> = Vec::new();
+            for token in tokens
+                .iter()
+                .chain(std::iter::once(&Token::Punctuation(',')))
+            {
+                match token {
+                    Token::Punctuation('(') => depth += 1,
+                    Token::Punctuation(')') => depth -= 1,
+                    Token::Punctuation(',') if depth == 0 => {
+                        if let Some(attribute) = to_attribute(&current) {
+                            attributes.push(attribute);
+                        }
+                        current.clear();
+                        continue;
+                    }
+                    _ => {}
+                }
+                current.push(token);
+            }
+        }
+        attributes
+    }
+
+    fn parse_modifiers(&mut self) -> Vec<String> {
+        let mut modifiers = Vec::new();
+        while let Some(Token::Identifier(identifier)) = self.peek() {
+            if !MODIFIERS.contains(&identifier.as_str()) {
+                break;
+            }
+            modifiers.push(identifier.clone());
+            self.position += 1;
+        }
+        modifiers
+    }
+
+    /// Parses declarations until the closing '}' of the enclosing block, or the end of the file.
+    /// Returns the properties of the enclosing type
+    ///
+    /// `containing_type` is the name of the enclosing type and whether it is an interface, whose
+    /// members are public without a modifier. It is None for namespaces and the top of the file
+    fn parse_members(
+        &mut self,
+        containing_type: Option<(&str, bool)>,
+    ) -> Result<Vec<CSharpProperty>, String> {
+        let mut properties = Vec::new();
+        while let Some(token) = self.peek() {
+            if *token == Token::Punctuation('}') {
+                break;
+            }
+            let attributes = self.parse_attributes();
+            let modifiers = self.parse_modifiers();
+            let keyword = match self.peek() {
+                Some(Token::Identifier(identifier)) => identifier.clone(),
+                _ => String::new(),
+            };
+            match keyword.as_str() {
+                "using" | "delegate" | "event" | "operator" | "implicit" | "explicit" => {
+                    self.skip_statement();
+                }
+                "namespace" => {
+                    self.position += 1;
+                    while !self.is_punctuation('{')
+                        && !self.is_punctuation(';')
+                        && self.peek().is_some()
+                    {
+                        self.position += 1;
+                    }
+                    if self.is_punctuation('{') {
+                        self.position += 1;
+                        self.parse_members(None)?;
+                        self.expect_punctuation('}')?;
+                    } else {
+                        self.position += 1;
+                    }
+                }
+                "class" | "struct" | "interface" | "record" => {
+                    self.position += 1;
+                    self.parse_class(&keyword, &modifiers)?;
+                }
+                "enum" => {
+                    self.position += 1;
+                    self.parse_enum(&attributes)?;
+                }
+                _ => {
+                    let Some((type_name, is_interface)) = containing_type else {
+                        self.skip_statement();
+                        continue;
+                    };
+                    let is_public = is_interface || modifiers.iter().any(|m| m == "public");
+                    if let Some(property) = self.parse_member(type_name)?
+                        && is_public
+                        && !modifiers.iter().any(|m| m == "static" || m == "const")
+                        && find_attribute(&attributes, "JsonIgnore").is_none()
+                    {
+                        properties.push(CSharpProperty {
+                            json_name: find_attribute(&attributes, "JsonPropertyName")
+                                .and_then(|attribute| attribute.argument.clone()),
+                            ..property
+                        });
+                    }
+                }
+            }
+        }
+        Ok(properties)
+    }
+
+    /// Parses a class, struct, interface or record after its keyword
+    fn parse_class(&mut self, keyword: &str, modifiers: &[String]) -> Result<(), String> {
+        let is_interface = keyword == "interface";
+        if keyword == "record" && (self.is_identifier("class") || self.is_identifier("struct")) {
+            self.position += 1;
+        }
+        let name = self.expect_identifier()?;
+        let type_parameters = self.parse_type_parameters()?;
+
+        // Positional records declare their properties as parameters
+        let mut properties = Vec::new();
+        if self.is_punctuation('(') {
+            self.position += 1;
+            while !self.is_punctuation(')') {
+                let attributes = self.parse_attributes();
+                let type_reference = self.parse_type()?;
+                let parameter_name = self.expect_identifier()?;
+                if self.is_punctuation('=') {
+                    while !self.is_punctuation(',')
+                        && !self.is_punctuation(')')
+                        && self.peek().is_some()
+                    {
+                        if self.is_punctuation('(') {
+                            self.skip_group();
+                        } else {
+                            self.position += 1;
+                        }
+                    }
+                }
+                if find_attribute(&attributes, "JsonIgnore").is_none() {
+                    properties.push(CSharpProperty {
+                        name: parameter_name,
+                        type_reference,
+                        json_name: find_attribute(&attributes, "JsonPropertyName")
+                            .and_then(|attribute| attribute.argument.clone()),
+                    });
+                }
+                if self.is_punctuation(',') {
+                    self.position += 1;
+                }
+            }
+            self.expect_punctuation(')')?;
+        }
+
+        let mut base_types = Vec::new();
+        if self.is_punctuation(':') {
+            self.position += 1;
+            loop {
+                base_types.push(self.parse_type()?);
+                // A positional record passes its parameters on to its base record
+                if self.is_punctuation('(') {
+                    self.skip_group();
+                }
+                if !self.is_punctuation(',') {
+                    break;
+                }
+                self.position += 1;
+            }
+        }
+        // Generic constraints, e.g. 'where T : class'
+        while !self.is_punctuation('{') && !self.is_punctuation(';') && self.peek().is_some() {
+            self.position += 1;
+        }
+
+        // The declaration is added before its nested types, which are added while parsing the body
+        let index = self.declarations.len();
+        self.declarations
+            .push(CSharpDeclaration::Class(CSharpClass {
+                name: name.clone(),
+                type_parameters,
+                base_types,
+                properties: Vec::new(),
+            }));
+        if self.is_punctuation('{') {
+            self.position += 1;
+            properties.extend(self.parse_members(Some((&name, is_interface)))?);
+            self.expect_punctuation('}')?;
+        }
+        if self.is_punctuation(';') {
+            self.position += 1;
+        }
+
+        if modifiers.iter().any(|m| m == "static") {
+            self.declarations.remove(index);
+        } else if let CSharpDeclaration::Class(class) = &mut self.declarations[index] {
+            class.properties = properties;
+        }
+        Ok(())
+    }
+
+    fn parse_type_parameters(&mut self) -> Result<Vec<String>, String> {
+        let mut type_parameters = Vec::new();
+        if !self.is_punctuation('<') {
+            return Ok(type_parameters);
+        }
+        self.position += 1;
+        while !self.is_punctuation('>') {
+            let _ = self.parse_attributes();
+            // Variance, e.g. 'out T'
+            if self.is_identifier("in") || self.is_identifier("out") {
+                self.position += 1;
+            }
+            type_parameters.push(self.expect_identifier()?);
+            if self.is_punctuation(',') {
+                self.position += 1;
+            } else if !self.is_punctuation('>') {
+                return Err(format!("Expected '>' but found {}", describe(self.peek())));
+            }
+        }
+        self.position += 1;
+        Ok(type_parameters)
+    }
+
+    fn parse_enum(&mut self, attributes: &[Attribute]) -> Result<(), String> {
+        let name = self.expect_identifier()?;
+        while !self.is_punctuation('{') && self.peek().is_some() {
+            self.position += 1;
+        }
+        self.expect_punctuation('{')?;
+        let mut members = Vec::new();
+        while !self.is_punctuation('}') {
+            let member_attributes = self.parse_attributes();
+            let member_name = self.expect_identifier()?;
+            let mut value = None;
+            if self.is_punctuation('=') {
+                self.position += 1;
+                let start = self.position;
+                while !self.is_punctuation(',')
+                    && !self.is_punctuation('}')
+                    && self.peek().is_some()
+                {
+                    self.position += 1;
+                }
+                value = Some(join_tokens(&self.tokens[start..self.position]));
+            }
+            members.push(CSharpEnumMember {
+                name: member_name,
+                value,
+                json_name: find_attribute(&member_attributes, "JsonStringEnumMemberName")
+                    .and_then(|attribute| attribute.argument.clone()),
+            });
+            if self.is_punctuation(',') {
+                self.position += 1;
+            }
+        }
+        self.expect_punctuation('}')?;
+        if self.is_punctuation(';') {
+            self.position += 1;
+        }
+        let serialized_as_string =
+            find_attribute(attributes, "JsonConverter").is_some_and(|attribute| {
+                attribute
+                    .identifiers
+                    .iter()
+                    .any(|identifier| identifier.starts_with("JsonStringEnumConverter"))
+            });
+        self.declarations.push(CSharpDeclaration::Enum(CSharpEnum {
+            name,
+            members,
+            serialized_as_string,
+        }));
+        Ok(())
+    }
+
+    /// Parses a field, property, method or constructor of `type_name`. Returns the property if it is one
+    fn parse_member(&mut self, type_name: &str) -> Result<Option<CSharpProperty>, String> {
+        // Constructors and finalizers have no return type
+        if (self.is_identifier(type_name) && self.peek_at(1) == Some(&Token::Punctuation('(')))
+            || self.is_punctuation('~')
+        {
+            self.skip_method();
+            return Ok(None);
+        }
+        let type_reference = match self.parse_type() {
+            Ok(type_reference) => type_reference,
+            Err(_) => {
+                self.skip_statement();
+                return Ok(None);
+            }
+        };
+        let Some(Token::Identifier(name)) = self.peek().cloned() else {
+            // Indexers and anything else that is not a named member
+            self.skip_method();
+            return Ok(None);
+        };
+        self.position += 1;
+        // Explicit interface implementations, e.g. 'IFoo.Bar', are not serialized
+        if self.is_punctuation('.') {
+            self.skip_method();
+            return Ok(None);
+        }
+        if self.is_punctuation('<') || self.is_punctuation('(') {
+            self.skip_method();
+            return Ok(None);
+        }
+        let is_property = self.is_punctuation('{')
+            || (self.is_punctuation('=') && self.peek_at(1) == Some(&Token::Punctuation('>')));
+        self.skip_statement();
+        // A property with an accessor block may have an initializer, e.g. '{ get; set; } = [];'
+        if is_property && self.is_punctuation('=') {
+            self.skip_statement();
+        }
+        Ok(is_property.then_some(CSharpProperty {
+            name,
+            type_reference,
+            json_name: None,
+        }))
+    }
+
+    /// Skips a method from after its name, including its parameters and body
+    fn skip_method(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punctuation('(' | '[') => self.skip_group(),
+                Token::Punctuation('{') => {
+                    self.skip_group();
+                    return;
+                }
+                Token::Punctuation(';') => {
+                    self.position += 1;
+                    return;
+                }
+                Token::Punctuation('}') => return,
+                _ => self.position += 1,
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<TypeReference, String> {
+        let mut type_reference = if self.is_punctuation('(') {
+            self.position += 1;
+            let mut elements = Vec::new();
+            while !self.is_punctuation(')') {
+                elements.push(self.parse_type()?);
+                // Tuple elements can be named
+                if matches!(self.peek(), Some(Token::Identifier(_))) {
+                    self.position += 1;
+                }
+                if self.is_punctuation(',') {
+                    self.position += 1;
+                } else if !self.is_punctuation(')') {
+                    return Err(format!("Expected ')' but found {}", describe(self.peek())));
+                }
+            }
+            self.position += 1;
+            TypeReference::Tuple(elements)
+        } else {
+            let mut name = self.expect_identifier()?;
+            let mut arguments = Vec::new();
+            loop {
+                if self.is_punctuation('<') {
+                    self.position += 1;
+                    arguments.clear();
+                    while !self.is_punctuation('>') {
+                        arguments.push(self.parse_type()?);
+                        if self.is_punctuation(',') {
+                            self.position += 1;
+                        } else if !self.is_punctuation('>') {
+                            return Err(format!(
+                                "Expected '>' but found {}",
+                                describe(self.peek())
+                            ));
+                        }
+                    }
+                    self.position += 1;
+                }
+                // Qualified names, e.g. 'System.Collections.Generic.List' or 'global::System.Guid'
+                let qualified = self.is_punctuation('.')
+                    || (self.is_punctuation(':')
+                        && self.peek_at(1) == Some(&Token::Punctuation(':')));
+                if !qualified {
+                    break;
+                }
+                self.position += if self.is_punctuation('.') { 1 } else { 2 };
+                name = self.expect_identifier()?;
+            }
+            TypeReference::Named { name, arguments }
+        };
+        loop {
+            if self.is_punctuation('?') {
+                self.position += 1;
+                type_reference = TypeReference::Nullable(Box::new(type_reference));
+            } else if self.is_punctuation('[')
+                && matches!(self.peek_at(1), Some(Token::Punctuation(']' | ',')))
+            {
+                self.skip_group();
+                type_reference = TypeReference::Array(Box::new(type_reference));
+            } else {
+                break;
+            }
+        }
+        Ok(type_reference)
+    }
+}
+
+fn to_attribute(tokens: &[&Token]) -> Option<Attribute> {
+    // Skip the target, e.g. 'property:'
+    let tokens = match tokens {
+        [Token::Identifier(_), Token::Punctuation(':'), rest @ ..]
+            if !matches!(rest.first(), Some(Token::Punctuation(':'))) =>
+        {
+            rest
+        }
+        _ => tokens,
+    };
+    let mut name = None;
+    let mut position = 0;
+    while let Some(Token::Identifier(identifier)) = tokens.get(position) {
+        name = Some(identifier.clone());
+        position += 1;
+        if tokens.get(position) != Some(&&Token::Punctuation('.')) {
+            break;
+        }
+        position += 1;
+    }
+    Some(Attribute {
+        name: name?,
+        argument: tokens[position..].iter().find_map(|token| match token {
+            Token::String(value) => Some(value.clone()),
+            _ => None,
+        }),
+        identifiers: tokens[position..]
+            .iter()
+            .filter_map(|token| match token {
+                Token::Identifier(identifier) => Some(identifier.clone()),
+                _ => None,
+            })
+            .collect(),
+    })
+}
+
+fn join_tokens(tokens: &[Token]) -> String {
+    let mut joined = String::new();
+    let mut previous_was_word = false;
+    for token in tokens {
+        let (text, is_word) = match token {
+            Token::Identifier(identifier) => (identifier.clone(), true),
+            Token::Literal(literal) => (literal.clone(), true),
+            Token::String(value) => (format!("{:?}", value), false),
+            Token::Punctuation(c) => (c.to_string(), false),
+        };
+        if previous_was_word && is_word {
+            joined.push(' ');
+        }
+        joined.push_str(&text);
+        previous_was_word = is_word;
+    }
+    joined
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        Some(Token::Identifier(identifier)) => format!("'{}'", identifier),
+        Some(Token::Literal(literal)) => format!("'{}'", literal),
+        Some(Token::String(value)) => format!("\"{}\"", value),
+        Some(Token::Punctuation(c)) => format!("'{}'", c),
+        None => "the end of the file".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> TypeReference {
+        TypeReference::Named {
+            name: name.to_string(),
+            arguments: Vec::new(),
+        }
+    }
+
+    fn parse_class(source: &str) -> CSharpClass {
+        match parse_declarations(source).unwrap().remove(0) {
+            CSharpDeclaration::Class(class) => class,
+            other => panic!("Expected a class but found {:?}", other),
+        }
+    }
+
+    fn property_types(class: &CSharpClass) -> Vec<(&str, &TypeReference)> {
+        class
+            .properties
+            .iter()
+            .map(|property| (property.name.as_str(), &property.type_reference))
+            .collect()
+    }
+
+    #[test]
+    fn reads_positional_record_parameters() {
+        let class = parse_class(
+            r#"public record Point(int X, [property: JsonPropertyName("y")] int Y = 0)
+            {
+                public string Label { get; init; } = "";
+            }"#,
+        );
+        assert_eq!(class.name, "Point");
+        assert_eq!(
+            property_types(&class),
+            [
+                ("X", &named("int")),
+                ("Y", &named("int")),
+                ("Label", &named("string"))
+            ]
+        );
+        assert_eq!(class.properties[1].json_name.as_deref(), Some("y"));
+    }
+
+    #[test]
+    fn reads_nullable_generic_and_array_types() {
+        let class = parse_class(
+            "public class Model<T> : Base<T>, IModel
+            {
+                public int? Count { get; set; }
+                public System.Collections.Generic.Dictionary<string, List<T>> Groups { get; set; }
+                public string[]? Names { get; set; }
+                public (int X, int Y) Position { get; set; }
+                private int hidden;
+                public static int Shared { get; set; }
+                public void Method() { var x = new { A = 1 }; }
+            }",
+        );
+        assert_eq!(class.type_parameters, ["T"]);
+        assert_eq!(
+            class.base_types,
+            [
+                TypeReference::Named {
+                    name: "Base".to_string(),
+                    arguments: vec![named("T")],
+                },
+                named("IModel"),
+            ]
+        );
+        let list = TypeReference::Named {
+            name: "List".to_string(),
+            arguments: vec![named("T")],
+        };
+        assert_eq!(
+            property_types(&class),
+            [
+                ("Count", &TypeReference::Nullable(Box::new(named("int")))),
+                (
+                    "Groups",
+                    &TypeReference::Named {
+                        name: "Dictionary".to_string(),
+                        arguments: vec![named("string"), list],
+                    }
+                ),
+                (
+                    "Names",
+                    &TypeReference::Nullable(Box::new(TypeReference::Array(Box::new(named(
+                        "string"
+                    )))))
+                ),
+                (
+                    "Position",
+                    &TypeReference::Tuple(vec![named("int"), named("int")])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_json_attributes() {
+        let class = parse_class(
+            r#"public class Message
+            {
+                [JsonPropertyName("message_id")]
+                public string Id { get; set; }
+                [JsonIgnore]
+                public string Secret { get; set; }
+                [System.Text.Json.Serialization.JsonIgnoreAttribute]
+                public string Other { get; set; }
+                public string Text { get; set; }
+            }"#,
+        );
+        assert_eq!(
+            property_types(&class),
+            [("Id", &named("string")), ("Text", &named("string"))]
+        );
+        assert_eq!(class.properties[0].json_name.as_deref(), Some("message_id"));
+        assert_eq!(class.properties[1].json_name, None);
+    }
+
+    #[test]
+    fn reads_enums_serialized_as_strings() {
+        let declarations = parse_declarations(
+            r#"[JsonConverter(typeof(JsonStringEnumConverter))]
+            public enum Color { Red, [JsonStringEnumMemberName("dark-green")] Green = 2 }
+            public enum Size { Small = 1 << 0, Large = 1 << 1 }"#,
+        )
+        .unwrap();
+        let [
+            CSharpDeclaration::Enum(color),
+            CSharpDeclaration::Enum(size),
+        ] = declarations.as_slice()
+        else {
+            panic!("Expected two enums but found {:?}", declarations);
+        };
+        assert!(color.serialized_as_string);
+        assert_eq!(
+            color.members,
+            [
+                CSharpEnumMember {
+                    name: "Red".to_string(),
+                    value: None,
+                    json_name: None,
+                },
+                CSharpEnumMember {
+                    name: "Green".to_string(),
+                    value: Some("2".to_string()),
+                    json_name: Some("dark-green".to_string()),
+                },
+            ]
+        );
+        assert!(!size.serialized_as_string);
+        assert_eq!(size.members[1].value.as_deref(), Some("1<<1"));
+    }
+
+    #[test]
+    fn skips_over_strings_in_initializers() {
+        let class = parse_class(
+            r#"public class Texts
+            {
+                public string Verbatim { get; set; } = @"C:\folder\""quoted"" }";
+                public string Interpolated { get; set; } = $"{(a ? "b" : "c")} }} {{";
+                public string Nested { get; set; } = $"{Format($"{x}", '}')}";
+                public string Raw { get; set; } = """She said "}" """;
+                public string RawInterpolated { get; set; } = $$"""{{{x}}} "{" """;
+                public char Brace { get; set; } = '}';
+                public int After { get; set; }
+            }"#,
+        );
+        assert_eq!(class.properties.len(), 7);
+        assert_eq!(class.properties[6].name, "After");
+    }
+
+    #[test]
+    fn reads_string_values() {
+        let strings = |source: &str| -> Vec<String> {
+            tokenize(source)
+                .unwrap()
+                .into_iter()
+                .filter_map(|token| match token {
+                    Token::String(value) => Some(value),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(strings(r#""a\"b" @"c""d""#), [r#"a"b"#, r#"c"d"#]);
+        assert_eq!(
+            strings(r#"$"{(a ? "b" : "c")}x{{""#),
+            [r#"{(a ? "b" : "c")}x{"#]
+        );
+        assert_eq!(strings(r#""""a "quoted" b""""#), [r#"a "quoted" b"#]);
+        assert_eq!(
+            strings("\"\"\"\n        first\n          second\n        \"\"\""),
+            ["first\n  second"]
+        );
+        assert_eq!(strings(r#"$$"""{x} {{y}}""""#), ["{x} {{y}}"]);
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        assert!(tokenize(r#"var a = "abc"#).is_err());
+        assert!(tokenize(r#"var a = """abc"""#).is_err());
+        assert!(tokenize(r#"var a = $"{x"#).is_err());
+    }
+}
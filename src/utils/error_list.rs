@@ -1,8 +1,13 @@
+use crate::error::RevError;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorList {
     errors: Vec<String>,
     warnings: Vec<String>,
+    /// The errors that were added with their type, which are also in `errors` as messages
+    #[serde(skip)]
+    typed_errors: Vec<RevError>,
 }
 
 impl ErrorList {
@@ -10,6 +15,7 @@ impl ErrorList {
         Self {
             errors: Vec::new(),
             warnings: Vec::new(),
+            typed_errors: Vec::new(),
         }
     }
     pub fn new_with_error(error: &str) -> Self {
@@ -20,6 +26,11 @@ impl ErrorList {
     pub fn add_error(&mut self, error: &str) {
         self.errors.push(error.to_string());
     }
+    /// Adds an error that callers can tell apart by its code, as well as by its message
+    pub fn add_typed_error(&mut self, error: RevError) {
+        self.errors.push(error.to_string());
+        self.typed_errors.push(error);
+    }
     pub fn add_warning(&mut self, warning: &str) {
         self.warnings.push(warning.to_string());
     }
@@ -35,9 +46,14 @@ impl ErrorList {
     pub fn view_warnings(&self) -> Vec<String> {
         self.warnings.clone()
     }
+    /// Returns the errors added with `add_typed_error`. Errors added as messages are only in `view_errors`
+    pub fn view_typed_errors(&self) -> Vec<RevError> {
+        self.typed_errors.clone()
+    }
     pub fn extend(&mut self, other: &ErrorList) {
         self.errors.extend(other.errors.clone());
         self.warnings.extend(other.warnings.clone());
+        self.typed_errors.extend(other.typed_errors.clone());
     }
 }
 
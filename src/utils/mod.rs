@@ -1,8 +1,12 @@
 pub mod input;
 pub mod error_list;
+pub mod assembly_metadata;
+pub mod csharp_types;
 use glob::Pattern;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::events::{Event, EventSink};
 
 pub enum SearchDirection {
     Parent,
@@ -71,4 +75,48 @@ pub fn recursively_check_for_file(
     }
 
     None
-}
\ No newline at end of file
+}
+/// Returns the paths of all files in the directory and its subdirectories.
+/// Returns an empty list if the directory does not exist
+pub fn get_all_files_in_directory(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(directory) {
+        for entry in entries.flatten() {
+            if let Ok(file_type) = entry.file_type() {
+                if file_type.is_file() {
+                    files.push(entry.path());
+                } else if file_type.is_dir() {
+                    files.extend(get_all_files_in_directory(&entry.path()));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Copies the directory and everything in it to `destination`, creating any missing directories.
+/// Returns the paths of the copied files in `destination`
+pub fn copy_dir_recursive(
+    source: &Path,
+    destination: &Path,
+    events: &dyn EventSink,
+) -> std::io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(destination)?;
+    let mut copied_files = Vec::new();
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target_path = destination.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copied_files.extend(copy_dir_recursive(&entry.path(), &target_path, events)?);
+        } else {
+            let bytes = fs::copy(entry.path(), &target_path)?;
+            events.emit(Event::FileCopied {
+                source: entry.path(),
+                destination: target_path.clone(),
+                bytes,
+            });
+            copied_files.push(target_path);
+        }
+    }
+    Ok(copied_files)
+}